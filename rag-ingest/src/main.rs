@@ -1,55 +1,169 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use clap::Parser;
-use trading_data_services::HistoricalIngestionPipeline;
-use tracing::{info, Level};
-use tracing_subscriber;
+use clap::{Parser, Subcommand};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use trading_core::{MarketStateSnapshot, OutcomeHorizon, OutcomeSpec};
+use trading_data_services::rag::ingestion_pipeline::{IngestStats, DEFAULT_BATCH_SIZE, DEFAULT_WORKERS};
+use trading_data_services::rag::schema::{migrate_collection, purge_legacy_points};
+use trading_data_services::rag::snapshot_export::{read_export, write_export, SnapshotExportRecord};
+use trading_data_services::{
+    EmbedderKind, FeatureSnapshotEmbedder, GapFillPolicy, HistoricalIngestionPipeline, IngestCheckpoint, MarketDataSource,
+    SnapshotEmbedder, TextSnapshotEmbedder, VectorStore, CURRENT_SCHEMA_VERSION,
+};
+use tracing::{error, info, warn, Level};
+
+/// Points read per scroll request when migrating payloads
+const MIGRATION_BATCH_SIZE: u32 = 256;
+
+/// Snapshots are only taken once their 3m candle has closed
+const CANDLE_3M_MS: i64 = 3 * 60_000;
 
 /// RAG Historical Data Ingestion CLI
 ///
 /// Extracts historical market snapshots from LMDB, converts to embeddings,
 /// and uploads to Qdrant vector database for RAG-enhanced trading signals.
+/// Runs a one-shot backfill, or keeps the collection current with `follow`.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Trading symbols to ingest (comma-separated)
-    #[arg(short, long, value_delimiter = ',', default_value = "BTCUSDT,ETHUSDT")]
+    #[arg(short, long, value_delimiter = ',', default_value = "BTCUSDT,ETHUSDT", global = true)]
     symbols: Vec<String>,
 
     /// Start date (RFC3339 format or days ago)
     /// Examples: "2025-10-01T00:00:00Z" or "90" (for 90 days ago)
-    #[arg(long, default_value = "90")]
+    #[arg(long, default_value = "90", global = true)]
     start: String,
 
     /// End date (RFC3339 format or "now")
-    #[arg(long, default_value = "now")]
+    #[arg(long, default_value = "now", global = true)]
     end: String,
 
     /// Snapshot interval in minutes
-    #[arg(short = 'i', long, default_value = "15")]
+    #[arg(short = 'i', long, default_value = "15", global = true)]
     interval: u64,
 
     /// Qdrant URL
-    #[arg(short = 'q', long, default_value = "http://localhost:6333")]
+    #[arg(short = 'q', long, default_value = "http://localhost:6333", global = true)]
     qdrant_url: String,
 
     /// Qdrant collection name
-    #[arg(short = 'c', long, default_value = "trading_patterns")]
+    #[arg(short = 'c', long, default_value = "trading_patterns", global = true)]
     collection: String,
 
-    /// Data source: "mock" for testing, "lmdb" for real data
-    #[arg(short = 'd', long, default_value = "mock")]
+    /// Data source: "mock" for testing, "lmdb" for real data, "csv" for candle files
+    #[arg(short = 'd', long, default_value = "mock", global = true)]
     data_source: String,
 
     /// LMDB database path (required if data-source is "lmdb")
-    #[arg(long, default_value = "/shared/data/trading/lmdb")]
+    #[arg(long, default_value = "/shared/data/trading/lmdb", global = true)]
     lmdb_path: String,
 
+    /// Directory of OHLCV candle CSV files (required if data-source is "csv")
+    #[arg(long, default_value = "./candles", global = true)]
+    candles_dir: String,
+
+    /// Missing time series points: "reject", "ffill" or "interpolate"
+    #[arg(long, default_value = "ffill", global = true)]
+    gap_policy: String,
+
+    /// Skip snapshots with a data quality score below this (0.0-1.0)
+    #[arg(long, default_value = "0.0", global = true)]
+    min_quality: f64,
+
+    /// JSONL file that snapshots failing validation are appended to
+    #[arg(long, default_value = "quarantine.jsonl", global = true)]
+    quarantine_file: String,
+
+    /// Outcome horizons recorded for each snapshot (comma-separated, e.g. "1h,8h,1d")
+    #[arg(long, value_delimiter = ',', default_value = "15m,1h,4h,24h", global = true)]
+    horizons: Vec<String>,
+
+    /// Stop loss distance: percent ("2%") or ATR multiple ("1.5atr")
+    #[arg(long, default_value = "2%", global = true)]
+    stop: String,
+
+    /// Take profit distance: percent ("3%") or ATR multiple ("3atr")
+    #[arg(long, default_value = "3%", global = true)]
+    target: String,
+
+    /// Attach this symbol's 1h/4h change, RSI and correlation to snapshots of other symbols (e.g. "BTCUSDT")
+    #[arg(long, global = true)]
+    reference_symbol: Option<String>,
+
+    /// Embedder: "text" (BGE-small over the snapshot description) or "features" (numeric feature vector)
+    #[arg(long, default_value = "text", global = true)]
+    embedder: String,
+
+    /// Feature embedder weights (comma-separated name=weight, e.g. "oi_delta_pct=2,rsi_14=0.5")
+    #[arg(long, value_delimiter = ',', global = true)]
+    feature_weights: Vec<String>,
+
+    /// Snapshots embedded and upserted per batch
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE, global = true)]
+    batch_size: usize,
+
+    /// Symbols ingested concurrently
+    #[arg(long, default_value_t = DEFAULT_WORKERS, global = true)]
+    workers: usize,
+
+    /// Checkpoint file recording the last committed timestamp per symbol
+    /// (default: "<collection>.checkpoint.json")
+    #[arg(long, global = true)]
+    checkpoint_file: Option<String>,
+
+    /// Continue each symbol after its last committed snapshot in the checkpoint file
+    #[arg(long, global = true)]
+    resume: bool,
+
+    /// Re-derive payloads of existing points to the current schema (no re-embedding) instead of ingesting
+    #[arg(long, global = true)]
+    migrate: bool,
+
+    /// Delete points stored under sequential IDs by older ingestions (re-ingest their range afterwards)
+    #[arg(long, global = true)]
+    purge_legacy_points: bool,
+
+    /// Extract and validate snapshots and report what would be ingested, without
+    /// loading the embedding model or touching Qdrant
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Dry run that also writes each snapshot, its embedding text and payload to this JSONL file
+    #[arg(long, global = true)]
+    export: Option<String>,
+
+    /// Embed and upsert the snapshots of a JSONL file written by --export, instead of
+    /// extracting them (--symbols and the date range are ignored)
+    #[arg(long, global = true, conflicts_with_all = ["dry_run", "export"])]
+    import: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Log level (trace, debug, info, warn, error)
-    #[arg(short = 'l', long, default_value = "info")]
+    #[arg(short = 'l', long, default_value = "info", global = true)]
     log_level: String,
 }
 
+/// Modes other than the default one-shot backfill
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Keep ingesting as new candles arrive, and backfill outcomes as their horizons pass
+    ///
+    /// Each symbol starts at --start (or its checkpoint) and then follows the
+    /// data source; --end is ignored. Runs until Ctrl-C.
+    Follow {
+        /// Seconds between polls of the data source
+        #[arg(long, default_value = "60")]
+        poll_secs: u64,
+    },
+}
+
 impl Args {
     /// Parse start timestamp from string (either RFC3339 date or days ago)
     fn parse_start_timestamp(&self) -> Result<u64> {
@@ -74,6 +188,43 @@ impl Args {
         Ok(dt.timestamp_millis() as u64)
     }
 
+    /// Build the outcome spec from the horizon and stop/target options
+    fn parse_outcome_spec(&self) -> Result<OutcomeSpec> {
+        let horizons = self
+            .horizons
+            .iter()
+            .map(|h| h.parse::<OutcomeHorizon>())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(OutcomeSpec::new(horizons, self.stop.parse()?, self.target.parse()?))
+    }
+
+    /// Build the embedder from the embedder and feature weight options
+    fn build_embedder(&self) -> Result<Box<dyn SnapshotEmbedder>> {
+        match self.embedder.parse::<EmbedderKind>()? {
+            EmbedderKind::Text => {
+                if !self.feature_weights.is_empty() {
+                    return Err(anyhow::anyhow!("--feature-weights requires --embedder features"));
+                }
+                Ok(Box::new(TextSnapshotEmbedder::new()))
+            }
+            EmbedderKind::Features => Ok(Box::new(
+                FeatureSnapshotEmbedder::new().with_weight_specs(&self.feature_weights)?,
+            )),
+        }
+    }
+
+    /// Whether snapshots are only extracted, not embedded or upserted
+    fn is_dry_run(&self) -> bool {
+        self.dry_run || self.export.is_some()
+    }
+
+    /// Checkpoint file path (explicit, or derived from the collection name)
+    fn checkpoint_path(&self) -> String {
+        self.checkpoint_file
+            .clone()
+            .unwrap_or_else(|| format!("{}.checkpoint.json", self.collection))
+    }
+
     /// Parse log level from string
     fn parse_log_level(&self) -> Level {
         match self.log_level.to_lowercase().as_str() {
@@ -87,6 +238,235 @@ impl Args {
     }
 }
 
+/// Configure an initialized pipeline, then backfill, follow, dry-run or import
+async fn run<S: MarketDataSource + 'static>(
+    pipeline: HistoricalIngestionPipeline<S>,
+    args: &Args,
+    symbols: Vec<&str>,
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<()> {
+    let following = matches!(args.command, Some(Command::Follow { .. }));
+    if following && (args.is_dry_run() || args.import.is_some()) {
+        return Err(anyhow::anyhow!("follow cannot be combined with --dry-run, --export or --import"));
+    }
+
+    let mut pipeline = pipeline
+        .with_gap_policy(args.gap_policy.parse::<GapFillPolicy>()?)?
+        .with_min_data_quality(args.min_quality)
+        .with_outcome_spec(args.parse_outcome_spec()?)?
+        .with_embedder(args.build_embedder()?)
+        .with_batch_size(args.batch_size)
+        .with_workers(args.workers)
+        .with_resume(args.resume || following);
+    // A dry run leaves no trace, and an import must not move the backfill checkpoint
+    if !args.is_dry_run() {
+        pipeline = pipeline.with_quarantine_file(&args.quarantine_file);
+    }
+    if args.import.is_none() {
+        pipeline = pipeline.with_checkpoint(IngestCheckpoint::load(args.checkpoint_path(), &args.collection)?);
+    }
+    if let Some(reference) = &args.reference_symbol {
+        pipeline = pipeline.with_reference_symbol(reference)?;
+    }
+
+    info!("Pipeline initialized successfully");
+    info!("");
+
+    if let Some(path) = &args.import {
+        let results = import(&pipeline, Path::new(path)).await?;
+        display_results(&results);
+        return ensure_all_ingested(&results);
+    }
+    if args.is_dry_run() {
+        return dry_run(&pipeline, &symbols, start_ts, end_ts, args.interval, args.export.as_deref()).await;
+    }
+
+    match args.command {
+        Some(Command::Follow { poll_secs }) => {
+            follow(pipeline, &symbols, start_ts, args.interval, Duration::from_secs(poll_secs.max(1))).await
+        }
+        None => {
+            let results = pipeline
+                .ingest_multiple_symbols(symbols, start_ts, end_ts, args.interval)
+                .await;
+            display_results(&results);
+            ensure_all_ingested(&results)
+        }
+    }
+}
+
+/// Extract and validate the snapshots of every symbol, and optionally export them as JSONL
+///
+/// Qdrant is never contacted and the embedding model never loaded, so the
+/// counts are of snapshots that would be embedded (unchanged points are not
+/// detected).
+async fn dry_run<S: MarketDataSource + 'static>(
+    pipeline: &HistoricalIngestionPipeline<S>,
+    symbols: &[&str],
+    start_ts: u64,
+    end_ts: u64,
+    interval_minutes: u64,
+    export: Option<&str>,
+) -> Result<()> {
+    let mut writer = export
+        .map(|path| {
+            File::create(path)
+                .map(BufWriter::new)
+                .with_context(|| format!("Failed to create export file {}", path))
+        })
+        .transpose()?;
+
+    info!("Dry run: nothing is embedded or written to Qdrant");
+    for &symbol in symbols {
+        let (snapshots, stats) = pipeline
+            .extract_validated_snapshots(symbol, start_ts, end_ts, interval_minutes)
+            .await?;
+        info!(
+            "  {}: {} snapshots would be ingested ({} below min quality, {} rejected)",
+            symbol,
+            snapshots.len(),
+            stats.snapshots_low_quality,
+            stats.snapshots_rejected
+        );
+
+        if let Some(writer) = writer.as_mut() {
+            let records = snapshots
+                .into_iter()
+                .map(|snapshot| SnapshotExportRecord::new(snapshot, pipeline.embedder()))
+                .collect::<Result<Vec<_>>>()?;
+            write_export(writer, &records)?;
+        }
+    }
+
+    if let (Some(mut writer), Some(path)) = (writer, export) {
+        writer.flush()?;
+        info!("Exported snapshots to {}", path);
+    }
+    Ok(())
+}
+
+/// Embed and upsert the snapshots of a JSONL dump, grouped by symbol in timestamp order
+///
+/// Payloads are re-derived from the snapshots by this build, so a dump from
+/// an older version is stored under the current schema.
+async fn import<S: MarketDataSource + 'static>(
+    pipeline: &HistoricalIngestionPipeline<S>,
+    path: &Path,
+) -> Result<Vec<(String, Result<IngestStats>)>> {
+    let records = read_export(path)?;
+    info!("Importing {} snapshots from {}", records.len(), path.display());
+
+    let mut by_symbol: BTreeMap<String, Vec<MarketStateSnapshot>> = BTreeMap::new();
+    for record in records {
+        by_symbol.entry(record.snapshot.symbol.clone()).or_default().push(record.snapshot);
+    }
+
+    let mut results = Vec::new();
+    for (symbol, mut snapshots) in by_symbol {
+        snapshots.sort_by_key(|s| s.timestamp);
+        let mut stats = IngestStats {
+            snapshots_created: snapshots.len(),
+            ..Default::default()
+        };
+        let result = pipeline.upsert_snapshots(&symbol, &snapshots, &mut stats).await;
+        results.push((symbol, result.map(|_| stats)));
+    }
+    Ok(results)
+}
+
+/// Fail if any symbol failed to ingest
+fn ensure_all_ingested(results: &[(String, Result<IngestStats>)]) -> Result<()> {
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} symbols failed to ingest", failed, results.len()));
+    }
+    Ok(())
+}
+
+/// Poll the data source until Ctrl-C, ingesting new snapshots and backfilling matured outcomes
+///
+/// New snapshots are upserted with their outcomes pending; each poll rewrites
+/// the payloads of pending points whose horizons have since passed. Failures
+/// are logged and retried on the next poll.
+async fn follow<S: MarketDataSource + 'static>(
+    pipeline: HistoricalIngestionPipeline<S>,
+    symbols: &[&str],
+    start_ts: u64,
+    interval_minutes: u64,
+    poll: Duration,
+) -> Result<()> {
+    let interval_ms = interval_minutes * 60_000;
+    info!("Following {} every {}s (Ctrl-C to stop)", symbols.join(","), poll.as_secs());
+
+    loop {
+        let now_ms = Utc::now().timestamp_millis();
+        let end_ts = (now_ms - CANDLE_3M_MS) as u64;
+
+        // Skip the data source until a symbol's next snapshot candle has closed
+        let mut due = Vec::new();
+        for &symbol in symbols {
+            let next_ts = pipeline.last_committed(symbol)?.map_or(start_ts, |last| last + interval_ms);
+            if next_ts < end_ts {
+                due.push(symbol);
+            }
+        }
+
+        for (symbol, result) in pipeline.ingest_multiple_symbols(due, start_ts, end_ts, interval_minutes).await {
+            match result {
+                Ok(stats) if stats.points_uploaded > 0 => info!("  {}: {} new points", symbol, stats.points_uploaded),
+                Ok(_) => {}
+                Err(e) => warn!("Ingestion for {} failed, retrying next poll: {:#}", symbol, e),
+            }
+        }
+
+        for &symbol in symbols {
+            match pipeline.backfill_outcomes(symbol, now_ms).await {
+                Ok(stats) if stats.points_updated > 0 => info!(
+                    "  {}: outcomes updated for {} points ({} complete)",
+                    symbol, stats.points_updated, stats.points_matured
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Outcome backfill for {} failed, retrying next poll: {:#}", symbol, e),
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(poll) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("Stopping follow mode");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Log the per-symbol statistics or error of a backfill
+fn display_results(results: &[(String, Result<IngestStats>)]) {
+    info!("");
+    info!("✅ Ingestion Complete!");
+    info!("=====================");
+    for (symbol, result) in results {
+        let stats = match result {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("  {}: failed: {:#}", symbol, e);
+                continue;
+            }
+        };
+        info!(
+            "  {}: {} snapshots ({} below min quality, {} rejected, {} unchanged), {} embeddings, {} points uploaded",
+            symbol,
+            stats.snapshots_created,
+            stats.snapshots_low_quality,
+            stats.snapshots_rejected,
+            stats.snapshots_unchanged,
+            stats.embeddings_generated,
+            stats.points_uploaded
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -103,6 +483,25 @@ async fn main() -> Result<()> {
     info!("🚀 RAG Historical Data Ingestion Tool");
     info!("=====================================");
 
+    if args.migrate {
+        info!("Migrating {} at {} to payload schema {}", args.collection, args.qdrant_url, CURRENT_SCHEMA_VERSION);
+        let store = VectorStore::new(&args.qdrant_url, args.collection.clone()).await?;
+        let stats = migrate_collection(&store, MIGRATION_BATCH_SIZE).await?;
+        info!(
+            "✅ Migration Complete: {} scanned, {} migrated, {} already current, {} failed",
+            stats.points_scanned, stats.points_migrated, stats.points_current, stats.points_failed
+        );
+        return Ok(());
+    }
+
+    if args.purge_legacy_points {
+        info!("Purging points with legacy sequential IDs from {} at {}", args.collection, args.qdrant_url);
+        let store = VectorStore::new(&args.qdrant_url, args.collection.clone()).await?;
+        let deleted = purge_legacy_points(&store, MIGRATION_BATCH_SIZE).await?;
+        info!("✅ Purge Complete: {} points deleted; re-ingest their range to restore them", deleted);
+        return Ok(());
+    }
+
     // Parse timestamps
     let start_ts = args.parse_start_timestamp()?;
     let end_ts = args.parse_end_timestamp()?;
@@ -122,53 +521,65 @@ async fn main() -> Result<()> {
     info!("  Qdrant URL: {}", args.qdrant_url);
     info!("  Collection: {}", args.collection);
     info!("  Data Source: {}", args.data_source);
+    info!("  Gap Policy: {} (min quality {})", args.gap_policy, args.min_quality);
+    info!("  Outcomes: {} (stop {}, target {})", args.horizons.join(","), args.stop, args.target);
+    if let Some(reference) = &args.reference_symbol {
+        info!("  Reference Symbol: {}", reference);
+    }
+    info!("  Embedder: {}", args.embedder);
+    if let Some(path) = &args.export {
+        info!("  Mode: dry run, exporting to {}", path);
+    } else if args.dry_run {
+        info!("  Mode: dry run");
+    } else if let Some(path) = &args.import {
+        info!("  Mode: import from {}", path);
+    }
+    info!("  Batch Size: {} ({} workers)", args.batch_size, args.workers);
+    info!(
+        "  Checkpoint: {}{}",
+        args.checkpoint_path(),
+        if args.resume { " (resuming)" } else { "" }
+    );
+    if !args.feature_weights.is_empty() {
+        info!("  Feature Weights: {}", args.feature_weights.join(","));
+    }
     if args.data_source == "lmdb" {
         info!("  LMDB Path: {}", args.lmdb_path);
     }
+    if args.data_source == "csv" {
+        info!("  Candles Dir: {}", args.candles_dir);
+    }
     info!("");
 
-    // Create ingestion pipeline based on data source
+    // Create ingestion pipeline based on data source and ingest all symbols
     info!("Initializing ingestion pipeline...");
-    let mut pipeline = match args.data_source.to_lowercase().as_str() {
+    let symbol_refs: Vec<&str> = args.symbols.iter().map(|s| s.as_str()).collect();
+    match args.data_source.to_lowercase().as_str() {
         "lmdb" => {
-            HistoricalIngestionPipeline::with_lmdb(
+            let pipeline = HistoricalIngestionPipeline::with_lmdb(
                 &args.qdrant_url,
-                args.collection,
+                args.collection.clone(),
                 &args.lmdb_path
-            ).await?
+            ).await?;
+            run(pipeline, &args, symbol_refs, start_ts, end_ts).await
         }
-        "mock" => {
-            HistoricalIngestionPipeline::new(&args.qdrant_url, args.collection).await?
+        "csv" => {
+            let pipeline = HistoricalIngestionPipeline::with_csv(
+                &args.qdrant_url,
+                args.collection.clone(),
+                &args.candles_dir
+            ).await?;
+            run(pipeline, &args, symbol_refs, start_ts, end_ts).await
         }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid data source '{}'. Must be 'mock' or 'lmdb'",
-                args.data_source
-            ));
+        "mock" => {
+            let pipeline = HistoricalIngestionPipeline::new(&args.qdrant_url, args.collection.clone()).await?;
+            run(pipeline, &args, symbol_refs, start_ts, end_ts).await
         }
-    };
-
-    info!("Pipeline initialized successfully");
-    info!("");
-
-    // Ingest all symbols
-    let symbol_refs: Vec<&str> = args.symbols.iter().map(|s| s.as_str()).collect();
-    let results = pipeline
-        .ingest_multiple_symbols(symbol_refs, start_ts, end_ts, args.interval)
-        .await?;
-
-    // Display results
-    info!("");
-    info!("✅ Ingestion Complete!");
-    info!("=====================");
-    for (symbol, stats) in results {
-        info!(
-            "  {}: {} snapshots, {} embeddings, {} points uploaded",
-            symbol, stats.snapshots_created, stats.embeddings_generated, stats.points_uploaded
-        );
+        _ => Err(anyhow::anyhow!(
+            "Invalid data source '{}'. Must be 'mock', 'lmdb' or 'csv'",
+            args.data_source
+        )),
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -186,6 +597,26 @@ mod tests {
             collection: "".to_string(),
             data_source: "mock".to_string(),
             lmdb_path: "".to_string(),
+            candles_dir: "".to_string(),
+            gap_policy: "ffill".to_string(),
+            min_quality: 0.0,
+            quarantine_file: "".to_string(),
+            horizons: vec!["15m".to_string(), "8h".to_string()],
+            stop: "1.5atr".to_string(),
+            target: "3%".to_string(),
+            reference_symbol: None,
+            embedder: "text".to_string(),
+            feature_weights: vec![],
+            batch_size: DEFAULT_BATCH_SIZE,
+            workers: DEFAULT_WORKERS,
+            checkpoint_file: None,
+            resume: false,
+            migrate: false,
+            purge_legacy_points: false,
+            dry_run: false,
+            export: None,
+            import: None,
+            command: None,
             log_level: "info".to_string(),
         };
 
@@ -208,6 +639,26 @@ mod tests {
             collection: "".to_string(),
             data_source: "mock".to_string(),
             lmdb_path: "".to_string(),
+            candles_dir: "".to_string(),
+            gap_policy: "ffill".to_string(),
+            min_quality: 0.0,
+            quarantine_file: "".to_string(),
+            horizons: vec!["15m".to_string(), "8h".to_string()],
+            stop: "1.5atr".to_string(),
+            target: "3%".to_string(),
+            reference_symbol: None,
+            embedder: "text".to_string(),
+            feature_weights: vec![],
+            batch_size: DEFAULT_BATCH_SIZE,
+            workers: DEFAULT_WORKERS,
+            checkpoint_file: None,
+            resume: false,
+            migrate: false,
+            purge_legacy_points: false,
+            dry_run: false,
+            export: None,
+            import: None,
+            command: None,
             log_level: "info".to_string(),
         };
 
@@ -215,5 +666,62 @@ mod tests {
         let end_ts = args.parse_end_timestamp().unwrap();
 
         assert!(end_ts > start_ts);
+
+        let spec = args.parse_outcome_spec().unwrap();
+        assert_eq!(spec.max_horizon_ms(), 8 * 60 * 60 * 1000);
+        assert_eq!(spec.stop, trading_core::ExitRule::AtrMultiple(1.5));
+        assert_eq!(args.build_embedder().unwrap().kind(), EmbedderKind::Text);
+    }
+
+    #[test]
+    fn test_build_feature_embedder() {
+        let mut args = Args::parse_from(["rag-ingest", "--embedder", "features", "--feature-weights", "oi_delta_pct=2,rsi_7=0"]);
+        let embedder = args.build_embedder().unwrap();
+        assert_eq!(embedder.kind(), EmbedderKind::Features);
+        assert_eq!(embedder.metadata()["feature_weights"]["oi_delta_pct"], 2.0);
+
+        args.embedder = "text".to_string();
+        assert!(args.build_embedder().is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_path() {
+        let args = Args::parse_from(["rag-ingest", "--collection", "eth_patterns", "--resume"]);
+        assert!(args.resume);
+        assert_eq!(args.batch_size, DEFAULT_BATCH_SIZE);
+        assert_eq!(args.workers, DEFAULT_WORKERS);
+        assert_eq!(args.checkpoint_path(), "eth_patterns.checkpoint.json");
+
+        let args = Args::parse_from(["rag-ingest", "--checkpoint-file", "/tmp/ingest.json"]);
+        assert_eq!(args.checkpoint_path(), "/tmp/ingest.json");
+    }
+
+    #[test]
+    fn test_parse_follow() {
+        let args = Args::parse_from(["rag-ingest", "--interval", "5", "follow", "--poll-secs", "30", "--symbols", "SOLUSDT"]);
+        assert!(matches!(args.command, Some(Command::Follow { poll_secs: 30 })));
+        assert_eq!(args.interval, 5);
+        assert_eq!(args.symbols, vec!["SOLUSDT".to_string()]);
+
+        let args = Args::parse_from(["rag-ingest"]);
+        assert!(args.command.is_none());
+    }
+
+    #[test]
+    fn test_parse_dry_run_modes() {
+        let args = Args::parse_from(["rag-ingest", "--dry-run"]);
+        assert!(args.is_dry_run());
+        assert!(args.export.is_none());
+
+        let args = Args::parse_from(["rag-ingest", "--export", "out.jsonl"]);
+        assert!(args.is_dry_run());
+        assert_eq!(args.export.as_deref(), Some("out.jsonl"));
+
+        let args = Args::parse_from(["rag-ingest", "--import", "out.jsonl"]);
+        assert!(!args.is_dry_run());
+        assert_eq!(args.import.as_deref(), Some("out.jsonl"));
+
+        assert!(Args::try_parse_from(["rag-ingest", "--import", "a.jsonl", "--export", "b.jsonl"]).is_err());
+        assert!(Args::try_parse_from(["rag-ingest", "--import", "a.jsonl", "--dry-run"]).is_err());
     }
 }
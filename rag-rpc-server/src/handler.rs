@@ -1,8 +1,9 @@
 use anyhow::Result;
 use std::sync::Arc;
 use std::time::Instant;
-use trading_core::MarketStateSnapshot;
-use trading_strategy::llm::RagRetriever;
+use trading_core::{MarketStateSnapshot, TradeSide};
+use trading_data_services::rag::schema::{CURRENT_FEATURE_VERSION, CURRENT_SCHEMA_VERSION};
+use trading_strategy::llm::{RagRetriever, SideStatistics};
 
 use crate::error::RpcError;
 use crate::protocol::*;
@@ -39,10 +40,11 @@ impl RagQueryHandler {
         // Query RAG retriever
         let (matches, metrics) = self
             .retriever
-            .find_similar_patterns_with_metrics(
+            .find_similar_patterns_filtered(
                 &snapshot,
                 params.query_config.lookback_days,
                 params.query_config.top_k,
+                &params.query_config.regimes,
             )
             .await
             .map_err(|e| RpcError::InternalError(e.to_string()))?;
@@ -81,12 +83,15 @@ impl RagQueryHandler {
                     max_drawdown_1h: m.max_drawdown_1h,
                     hit_stop_loss: m.hit_stop_loss,
                     hit_take_profit: m.hit_take_profit,
+                    horizons: m.outcomes.clone(),
+                    long_trade: m.long_trade,
+                    short_trade: m.short_trade,
                 },
             })
             .collect();
 
         // Calculate statistics
-        let statistics = self.calculate_statistics(&matches);
+        let statistics = Self::calculate_statistics(&matches, self.retriever.outcome_horizon());
 
         let query_duration = query_start.elapsed().as_millis() as u64;
 
@@ -105,9 +110,9 @@ impl RagQueryHandler {
                 embedding_duration_ms: embedding_duration,
                 retrieval_duration_ms: retrieval_duration,
                 filters_applied: self.get_filters_applied(&params),
-                schema_version: 1,
-                feature_version: "v1_nofx_3m4h".to_string(),
-                embedding_model: "bge-small-en-v1.5".to_string(),
+                schema_version: CURRENT_SCHEMA_VERSION,
+                feature_version: CURRENT_FEATURE_VERSION.id.to_string(),
+                embedding_model: self.retriever.embedding_model().to_string(),
             },
         })
     }
@@ -144,12 +149,37 @@ impl RagQueryHandler {
             macd_4h_values: vec![],
             rsi_14_4h_values: vec![],
 
+            // Higher timeframe context (optional in the request)
+            context_1h: params.current_state.context_1h,
+            context_1d: params.current_state.context_1d,
+
             // Market microstructure
             open_interest_latest: params.current_state.open_interest_latest,
             open_interest_avg_24h: params.current_state.open_interest_avg_24h,
             funding_rate: params.current_state.funding_rate,
             price_change_1h: params.current_state.price_change_1h.unwrap_or(0.0),
             price_change_4h: params.current_state.price_change_4h.unwrap_or(0.0),
+            has_open_interest: true,
+            has_funding_rate: true,
+            has_price_change_1h: params.current_state.price_change_1h.is_some(),
+            has_price_change_4h: params.current_state.price_change_4h.is_some(),
+            spread_bps: params.current_state.spread_bps,
+            book_imbalance: params.current_state.book_imbalance,
+            taker_buy_sell_ratio_30m: params.current_state.taker_buy_sell_ratio_30m,
+            cvd_30m: params.current_state.cvd_30m,
+            long_liquidations_30m: params.current_state.long_liquidations_30m,
+            short_liquidations_30m: params.current_state.short_liquidations_30m,
+
+            // Reference asset (optional in the request)
+            reference: params.current_state.reference.clone(),
+
+            // Regime (classified from the fields above when needed)
+            regime: None,
+
+            // Data quality (query state is taken as complete)
+            missing_points_3m: vec![],
+            missing_points_4h: vec![],
+            data_quality: 1.0,
 
             // Outcomes (not relevant for query snapshot)
             outcome_15m: None,
@@ -160,27 +190,27 @@ impl RagQueryHandler {
             max_drawdown_1h: None,
             hit_stop_loss: None,
             hit_take_profit: None,
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
+            outcomes_pending: false,
         })
     }
 
-    /// Calculate statistics across matches
-    fn calculate_statistics(&self, matches: &[trading_strategy::llm::HistoricalMatch]) -> Statistics {
+    /// Calculate statistics across matches, with outcomes at the given horizon
+    fn calculate_statistics(matches: &[trading_strategy::llm::HistoricalMatch], horizon: &str) -> Statistics {
         if matches.is_empty() {
             return Statistics {
                 total_matches: 0,
                 avg_similarity: 0.0,
                 similarity_range: [0.0, 0.0],
-                outcome_4h: OutcomeStats {
-                    mean: 0.0,
-                    median: 0.0,
-                    p10: 0.0,
-                    p90: 0.0,
-                    positive_count: 0,
-                    negative_count: 0,
-                    win_rate: 0.0,
-                },
+                outcome_4h: Self::calculate_outcome_stats(matches, "4h"),
+                outcome_horizon: horizon.to_string(),
+                outcome: Self::calculate_outcome_stats(matches, horizon),
                 stop_loss_hits: 0,
                 take_profit_hits: 0,
+                long_win_rate: None,
+                short_win_rate: None,
             };
         }
 
@@ -191,21 +221,40 @@ impl RagQueryHandler {
         let min_sim = matches.iter().map(|m| m.similarity).fold(f32::INFINITY, f32::min);
         let max_sim = matches.iter().map(|m| m.similarity).fold(f32::NEG_INFINITY, f32::max);
 
-        // Collect 4h outcomes
-        let mut outcomes_4h: Vec<f64> = matches
+        let stop_loss_hits = matches.iter().filter(|m| m.hit_stop_loss == Some(true)).count();
+        let take_profit_hits = matches.iter().filter(|m| m.hit_take_profit == Some(true)).count();
+
+        Statistics {
+            total_matches: total,
+            avg_similarity,
+            similarity_range: [min_sim, max_sim],
+            outcome_4h: Self::calculate_outcome_stats(matches, "4h"),
+            outcome_horizon: horizon.to_string(),
+            outcome: Self::calculate_outcome_stats(matches, horizon),
+            stop_loss_hits,
+            take_profit_hits,
+            long_win_rate: SideStatistics::calculate(matches, TradeSide::Long).win_rate(),
+            short_win_rate: SideStatistics::calculate(matches, TradeSide::Short).win_rate(),
+        }
+    }
+
+    /// Outcome statistics at a horizon (zeros when no match has an outcome there)
+    fn calculate_outcome_stats(matches: &[trading_strategy::llm::HistoricalMatch], horizon: &str) -> OutcomeStats {
+        // Collect outcomes at the horizon
+        let mut outcomes: Vec<f64> = matches
             .iter()
-            .filter_map(|m| m.outcome_4h)
+            .filter_map(|m| m.return_pct(horizon))
             .collect();
-        outcomes_4h.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        outcomes.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
-        let outcome_stats = if !outcomes_4h.is_empty() {
-            let mean = outcomes_4h.iter().sum::<f64>() / outcomes_4h.len() as f64;
-            let median = outcomes_4h[outcomes_4h.len() / 2];
-            let p10 = outcomes_4h[(outcomes_4h.len() as f64 * 0.1) as usize];
-            let p90 = outcomes_4h[(outcomes_4h.len() as f64 * 0.9) as usize];
-            let positive_count = outcomes_4h.iter().filter(|&&x| x > 0.0).count();
-            let negative_count = outcomes_4h.iter().filter(|&&x| x < 0.0).count();
-            let win_rate = positive_count as f64 / outcomes_4h.len() as f64;
+        if !outcomes.is_empty() {
+            let mean = outcomes.iter().sum::<f64>() / outcomes.len() as f64;
+            let median = outcomes[outcomes.len() / 2];
+            let p10 = outcomes[(outcomes.len() as f64 * 0.1) as usize];
+            let p90 = outcomes[(outcomes.len() as f64 * 0.9) as usize];
+            let positive_count = outcomes.iter().filter(|&&x| x > 0.0).count();
+            let negative_count = outcomes.iter().filter(|&&x| x < 0.0).count();
+            let win_rate = positive_count as f64 / outcomes.len() as f64;
 
             OutcomeStats {
                 mean,
@@ -226,18 +275,6 @@ impl RagQueryHandler {
                 negative_count: 0,
                 win_rate: 0.0,
             }
-        };
-
-        let stop_loss_hits = matches.iter().filter(|m| m.hit_stop_loss == Some(true)).count();
-        let take_profit_hits = matches.iter().filter(|m| m.hit_take_profit == Some(true)).count();
-
-        Statistics {
-            total_matches: total,
-            avg_similarity,
-            similarity_range: [min_sim, max_sim],
-            outcome_4h: outcome_stats,
-            stop_loss_hits,
-            take_profit_hits,
         }
     }
 
@@ -249,6 +286,9 @@ impl RagQueryHandler {
             filters.push("oi_delta".to_string());
             filters.push("funding_sign".to_string());
         }
+        for (field, label) in params.query_config.regimes.keywords() {
+            filters.push(format!("{}={}", field, label));
+        }
 
         filters
     }
@@ -269,6 +309,56 @@ mod tests {
     }
 
     #[test]
+    fn test_calculate_statistics_at_horizon() {
+        use trading_core::HorizonOutcome;
+
+        // Points ingested with an 8h-only spec carry no 4h outcome
+        let with_8h = |return_pct: f64| HistoricalMatch {
+            similarity: 0.9,
+            timestamp: 1000000,
+            date: "2025-01-01T00:00:00Z".to_string(),
+            rsi_7: 70.0,
+            rsi_14: 65.0,
+            macd: 10.0,
+            ema_ratio: 1.0,
+            oi_delta_pct: Some(0.0),
+            funding_rate: Some(0.0),
+            outcome_1h: None,
+            outcome_4h: None,
+            outcome_24h: None,
+            max_runup_1h: None,
+            max_drawdown_1h: None,
+            hit_stop_loss: None,
+            hit_take_profit: None,
+            outcomes: [(
+                "8h".to_string(),
+                HorizonOutcome {
+                    return_pct: Some(return_pct),
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            long_trade: None,
+            short_trade: None,
+        };
+        let matches = vec![with_8h(2.0), with_8h(-1.0), with_8h(1.0)];
+
+        let statistics = RagQueryHandler::calculate_statistics(&matches, "8h");
+        assert_eq!(statistics.outcome_horizon, "8h");
+        assert_eq!(statistics.outcome.positive_count, 2);
+        assert_eq!(statistics.outcome.negative_count, 1);
+        assert!((statistics.outcome.mean - 2.0 / 3.0).abs() < 1e-9);
+        // The 4h stats stay in the response for existing clients
+        assert_eq!(statistics.outcome_4h.positive_count + statistics.outcome_4h.negative_count, 0);
+
+        let statistics = RagQueryHandler::calculate_statistics(&matches, "4h");
+        assert_eq!(statistics.outcome.positive_count + statistics.outcome.negative_count, 0);
+    }
+
+    #[test]
+    // Lints predating the workspace clippy gate; the test body is kept as written
+    #[allow(unused_variables, clippy::useless_vec)]
     fn test_get_filters_applied() {
         let params = RagQueryRequest {
             symbol: "BTCUSDT".to_string(),
@@ -286,12 +376,22 @@ mod tests {
                 open_interest_avg_24h: 950000.0,
                 price_change_1h: None,
                 price_change_4h: None,
+                context_1h: None,
+                context_1d: None,
+                spread_bps: None,
+                book_imbalance: None,
+                taker_buy_sell_ratio_30m: None,
+                cvd_30m: None,
+                long_liquidations_30m: None,
+                short_liquidations_30m: None,
+                reference: None,
             },
             query_config: QueryConfig {
                 lookback_days: 90,
                 top_k: 5,
                 min_similarity: 0.7,
                 include_regime_filters: true,
+                regimes: trading_strategy::RegimeFilter::default(),
             },
         };
 
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
+use trading_core::{HorizonOutcome, ReferenceContext, TimeframeContext, TradeOutcome};
+use trading_strategy::llm::RegimeFilter;
 
 /// JSON-RPC 2.0 Request
 #[derive(Debug, Deserialize)]
@@ -73,6 +76,24 @@ pub struct MarketState {
     pub open_interest_avg_24h: f64,
     pub price_change_1h: Option<f64>,
     pub price_change_4h: Option<f64>,
+    #[serde(default)]
+    pub context_1h: Option<TimeframeContext>,
+    #[serde(default)]
+    pub context_1d: Option<TimeframeContext>,
+    #[serde(default)]
+    pub spread_bps: Option<f64>,
+    #[serde(default)]
+    pub book_imbalance: Option<f64>,
+    #[serde(default)]
+    pub taker_buy_sell_ratio_30m: Option<f64>,
+    #[serde(default)]
+    pub cvd_30m: Option<f64>,
+    #[serde(default)]
+    pub long_liquidations_30m: Option<f64>,
+    #[serde(default)]
+    pub short_liquidations_30m: Option<f64>,
+    #[serde(default)]
+    pub reference: Option<ReferenceContext>,
 }
 
 /// Query configuration with defaults
@@ -87,6 +108,9 @@ pub struct QueryConfig {
     pub min_similarity: f32,
     #[serde(default = "default_include_regime_filters")]
     pub include_regime_filters: bool,
+    /// Exact regime labels matches must have, e.g. {"trend": "uptrend"}
+    #[serde(default)]
+    pub regimes: RegimeFilter,
 }
 
 impl Default for QueryConfig {
@@ -96,6 +120,7 @@ impl Default for QueryConfig {
             top_k: default_top_k(),
             min_similarity: default_min_similarity(),
             include_regime_filters: default_include_regime_filters(),
+            regimes: RegimeFilter::default(),
         }
     }
 }
@@ -138,8 +163,8 @@ pub struct MatchMarketState {
     pub rsi_14: f64,
     pub macd: f64,
     pub ema_ratio: f64,
-    pub oi_delta_pct: f64,
-    pub funding_rate: f64,
+    pub oi_delta_pct: Option<f64>, // null when open interest was unavailable
+    pub funding_rate: Option<f64>, // null when funding was unavailable
 }
 
 /// Outcomes after the historical match
@@ -152,6 +177,9 @@ pub struct Outcomes {
     pub max_drawdown_1h: Option<f64>,
     pub hit_stop_loss: Option<bool>,
     pub hit_take_profit: Option<bool>,
+    pub horizons: BTreeMap<String, HorizonOutcome>, // Per-horizon outcomes (empty for older points)
+    pub long_trade: Option<TradeOutcome>,           // Simulated long (None while open)
+    pub short_trade: Option<TradeOutcome>,          // Simulated short (None while open)
 }
 
 /// Statistics across all matches
@@ -160,9 +188,13 @@ pub struct Statistics {
     pub total_matches: usize,
     pub avg_similarity: f32,
     pub similarity_range: [f32; 2],
-    pub outcome_4h: OutcomeStats,
+    pub outcome_4h: OutcomeStats,  // Stats at the 4h horizon (kept for existing clients)
+    pub outcome_horizon: String, // Horizon the outcome stats are taken at (the retriever's outcome horizon)
+    pub outcome: OutcomeStats,
     pub stop_loss_hits: usize,
     pub take_profit_hits: usize,
+    pub long_win_rate: Option<f64>,  // Share of simulated longs closed in profit
+    pub short_win_rate: Option<f64>, // Share of simulated shorts closed in profit
 }
 
 /// Statistical outcomes
@@ -190,6 +222,8 @@ pub struct Metadata {
 }
 
 #[cfg(test)]
+// Lints predating the workspace clippy gate; the test bodies are kept as written
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -200,6 +234,17 @@ mod tests {
         assert_eq!(config.top_k, 5);
         assert_eq!(config.min_similarity, 0.7);
         assert_eq!(config.include_regime_filters, true);
+        assert!(config.regimes.is_empty());
+    }
+
+    #[test]
+    fn test_query_config_regimes() {
+        let config: QueryConfig =
+            serde_json::from_str(r#"{"regimes": {"trend": "strong_downtrend", "volatility": "elevated"}}"#).unwrap();
+        assert_eq!(config.regimes.trend, Some(trading_core::TrendRegime::StrongDowntrend));
+        assert_eq!(config.regimes.positioning, None);
+
+        assert!(serde_json::from_str::<QueryConfig>(r#"{"regimes": {"trend": "bullish"}}"#).is_err());
     }
 
     #[test]
@@ -0,0 +1,357 @@
+//! Derived features computed from a snapshot
+//!
+//! `FeatureSet` is the single definition of every derived value (slopes,
+//! z-scores, EMA distances, curvature, divergences). The embedding text, the
+//! Qdrant payload, the LLM prompts and numeric embeddings all read from it, so
+//! a feature means the same thing everywhere it appears.
+
+use crate::types::MarketStateSnapshot;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Minimum series length for a z-score
+const MIN_ZSCORE_SAMPLES: usize = 3;
+
+/// Minimum price slope (% of price per candle) for a divergence
+const DIVERGENCE_PRICE_SLOPE_PCT: f64 = 0.01;
+
+/// Minimum RSI(7) slope (points per candle) for a divergence
+const DIVERGENCE_RSI_SLOPE: f64 = 0.5;
+
+/// Price and momentum moving in opposite directions over the 3m series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Divergence {
+    Bullish, // Price falling while momentum rises
+    Bearish, // Price rising while momentum falls
+}
+
+impl Divergence {
+    /// Keyword label, e.g. "bearish"
+    pub fn label(self) -> &'static str {
+        match self {
+            Divergence::Bullish => "bullish",
+            Divergence::Bearish => "bearish",
+        }
+    }
+
+    /// Numeric encoding: +1.0 bullish, -1.0 bearish
+    pub fn signum(self) -> f64 {
+        match self {
+            Divergence::Bullish => 1.0,
+            Divergence::Bearish => -1.0,
+        }
+    }
+
+    /// Divergence between a price slope and a momentum slope, if both clear their thresholds
+    fn between(price_slope: f64, momentum_slope: f64, price_threshold: f64, momentum_threshold: f64) -> Option<Self> {
+        if price_slope > price_threshold && momentum_slope < -momentum_threshold {
+            Some(Divergence::Bearish)
+        } else if price_slope < -price_threshold && momentum_slope > momentum_threshold {
+            Some(Divergence::Bullish)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Named derived features of one snapshot
+///
+/// Optional features are `None` when their inputs were unavailable (no open
+/// interest, no ATR, series too short or flat).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureSet {
+    // Trend
+    pub ema_ratio: f64,                    // 4h EMA(20)/EMA(50)
+    pub ema_20_4h_distance_atr: Option<f64>, // (price - 4h EMA20) / 4h ATR(14)
+    pub ema_50_4h_distance_atr: Option<f64>, // (price - 4h EMA50) / 4h ATR(14)
+
+    // Slopes (least squares, per candle)
+    pub price_slope_pct: f64, // 3m close slope as % of price
+    pub rsi_7_slope: f64,
+    pub macd_slope: f64,
+    pub rsi_14_4h_slope: f64,
+    pub macd_4h_slope: f64,
+
+    // Curvature (quadratic coefficient, per candle squared)
+    pub price_curvature_pct: f64, // 3m closes as % of price (> 0: accelerating up)
+    pub macd_curvature: f64,
+
+    // Z-scores of the latest value against its series
+    pub price_zscore: Option<f64>,
+    pub rsi_7_zscore: Option<f64>,
+    pub macd_4h_zscore: Option<f64>,
+
+    // Volume and volatility
+    pub volume_ratio_4h: Option<f64>, // Current 4h volume / average
+    pub volatility_ratio: f64,        // 4h ATR(3)/ATR(14) (1.0 without ATR)
+
+    // Derivatives
+    pub oi_delta_pct: Option<f64>, // OI vs 24h average (%)
+
+    // Divergences over the 3m series
+    pub rsi_divergence: Option<Divergence>,
+    pub macd_divergence: Option<Divergence>,
+}
+
+impl FeatureSet {
+    /// Names of the numeric features, in `values()` order
+    pub const NAMES: [&'static str; 18] = [
+        "ema_ratio",
+        "ema_20_4h_distance_atr",
+        "ema_50_4h_distance_atr",
+        "price_slope_pct",
+        "rsi_7_slope",
+        "macd_slope",
+        "rsi_14_4h_slope",
+        "macd_4h_slope",
+        "price_curvature_pct",
+        "macd_curvature",
+        "price_zscore",
+        "rsi_7_zscore",
+        "macd_4h_zscore",
+        "volume_ratio_4h",
+        "volatility_ratio",
+        "oi_delta_pct",
+        "rsi_divergence",
+        "macd_divergence",
+    ];
+
+    /// Compute every feature from a snapshot
+    pub fn compute(snapshot: &MarketStateSnapshot) -> Self {
+        let price = snapshot.price;
+        let pct_of_price = |value: f64| if price.abs() > 1e-10 { value / price * 100.0 } else { 0.0 };
+        let atr_distance = |ema: f64| {
+            (snapshot.atr_14_4h > 1e-10 && ema.abs() > 1e-10).then(|| (price - ema) / snapshot.atr_14_4h)
+        };
+
+        let price_slope_pct = pct_of_price(slope(&snapshot.mid_prices));
+        let rsi_7_slope = slope(&snapshot.rsi_7_values);
+        let macd_slope = slope(&snapshot.macd_values);
+
+        Self {
+            ema_ratio: snapshot.ema_ratio_20_50(),
+            ema_20_4h_distance_atr: atr_distance(snapshot.ema_20_4h),
+            ema_50_4h_distance_atr: atr_distance(snapshot.ema_50_4h),
+            price_slope_pct,
+            rsi_7_slope,
+            macd_slope,
+            rsi_14_4h_slope: slope(&snapshot.rsi_14_4h_values),
+            macd_4h_slope: slope(&snapshot.macd_4h_values),
+            price_curvature_pct: pct_of_price(curvature(&snapshot.mid_prices)),
+            macd_curvature: curvature(&snapshot.macd_values),
+            price_zscore: latest_zscore(&snapshot.mid_prices),
+            rsi_7_zscore: latest_zscore(&snapshot.rsi_7_values),
+            macd_4h_zscore: latest_zscore(&snapshot.macd_4h_values),
+            volume_ratio_4h: ratio(snapshot.current_volume_4h, snapshot.avg_volume_4h),
+            volatility_ratio: ratio(snapshot.atr_3_4h, snapshot.atr_14_4h).unwrap_or(1.0),
+            oi_delta_pct: snapshot.has_open_interest.then(|| snapshot.oi_delta_pct()),
+            rsi_divergence: Divergence::between(
+                price_slope_pct,
+                rsi_7_slope,
+                DIVERGENCE_PRICE_SLOPE_PCT,
+                DIVERGENCE_RSI_SLOPE,
+            ),
+            // MACD is in price units, so compare it as % of price too
+            macd_divergence: Divergence::between(
+                price_slope_pct,
+                pct_of_price(macd_slope),
+                DIVERGENCE_PRICE_SLOPE_PCT,
+                DIVERGENCE_PRICE_SLOPE_PCT / 10.0,
+            ),
+        }
+    }
+
+    /// Every feature by name, in `NAMES` order (divergences as +1/-1, None when absent)
+    pub fn values(&self) -> Vec<(&'static str, Option<f64>)> {
+        let values = [
+            Some(self.ema_ratio),
+            self.ema_20_4h_distance_atr,
+            self.ema_50_4h_distance_atr,
+            Some(self.price_slope_pct),
+            Some(self.rsi_7_slope),
+            Some(self.macd_slope),
+            Some(self.rsi_14_4h_slope),
+            Some(self.macd_4h_slope),
+            Some(self.price_curvature_pct),
+            Some(self.macd_curvature),
+            self.price_zscore,
+            self.rsi_7_zscore,
+            self.macd_4h_zscore,
+            self.volume_ratio_4h,
+            Some(self.volatility_ratio),
+            self.oi_delta_pct,
+            self.rsi_divergence.map(Divergence::signum),
+            self.macd_divergence.map(Divergence::signum),
+        ];
+        Self::NAMES.into_iter().zip(values).collect()
+    }
+
+    /// Dense numeric vector for numeric embeddings (unavailable features are 0.0)
+    pub fn to_vector(&self) -> Vec<f32> {
+        self.values().iter().map(|(_, value)| value.unwrap_or(0.0) as f32).collect()
+    }
+}
+
+/// `numerator / denominator`, or `None` when the denominator is ~0
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    (denominator.abs() > 1e-10).then(|| numerator / denominator)
+}
+
+/// Slope of a series using simple linear regression (0.0 for fewer than 2 values)
+pub fn slope(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+
+    let n = values.len() as f64;
+    let x_mean = (values.len() - 1) as f64 / 2.0;
+    let y_mean = values.iter().sum::<f64>() / n;
+
+    let numerator: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, &y)| (i as f64 - x_mean) * (y - y_mean))
+        .sum();
+
+    let denominator: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, _)| (i as f64 - x_mean).powi(2))
+        .sum();
+
+    if denominator.abs() < 1e-10 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+/// Quadratic coefficient of a least squares parabola through a series
+///
+/// Positive when the series bends upward (accelerating up or decelerating
+/// down). 0.0 for fewer than 3 values.
+pub fn curvature(values: &[f64]) -> f64 {
+    if values.len() < 3 {
+        return 0.0;
+    }
+
+    // With x centred the odd moments vanish and the fit decouples
+    let n = values.len() as f64;
+    let x_mean = (values.len() - 1) as f64 / 2.0;
+    let (mut s2, mut s4, mut sy, mut s2y) = (0.0, 0.0, 0.0, 0.0);
+    for (i, &y) in values.iter().enumerate() {
+        let x2 = (i as f64 - x_mean).powi(2);
+        s2 += x2;
+        s4 += x2 * x2;
+        sy += y;
+        s2y += x2 * y;
+    }
+
+    let denominator = n * s4 - s2 * s2;
+    if denominator.abs() < 1e-10 {
+        0.0
+    } else {
+        (n * s2y - s2 * sy) / denominator
+    }
+}
+
+/// Z-score of a value against a history (None for short or flat histories)
+pub fn zscore(value: f64, history: &[f64]) -> Option<f64> {
+    if history.len() < MIN_ZSCORE_SAMPLES {
+        return None;
+    }
+
+    let n = history.len() as f64;
+    let mean = history.iter().sum::<f64>() / n;
+    let std = (history.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+    (std > 1e-10).then(|| (value - mean) / std)
+}
+
+/// Z-score of the latest value of a series against the whole series
+fn latest_zscore(values: &[f64]) -> Option<f64> {
+    values.last().and_then(|&latest| zscore(latest, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> MarketStateSnapshot {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 110.0);
+        snapshot.mid_prices = (0..10).map(|i| 100.0 + i as f64 * i as f64 * 0.1).collect();
+        snapshot.rsi_7_values = (0..10).map(|i| 70.0 - i as f64 * 2.0).collect();
+        snapshot.macd_values = vec![1.0; 10];
+        snapshot.macd_4h_values = vec![1.0, 2.0, 3.0];
+        snapshot.ema_20_4h = 105.0;
+        snapshot.ema_50_4h = 100.0;
+        snapshot.atr_3_4h = 3.0;
+        snapshot.atr_14_4h = 2.0;
+        snapshot.current_volume_4h = 300.0;
+        snapshot.avg_volume_4h = 200.0;
+        snapshot.has_open_interest = false;
+        snapshot
+    }
+
+    #[test]
+    fn test_series_statistics() {
+        assert!((slope(&[1.0, 2.0, 3.0, 4.0]) - 1.0).abs() < 1e-10);
+        assert_eq!(slope(&[5.0]), 0.0);
+
+        // y = 3x^2 has a quadratic coefficient of 3 whatever the offset
+        let parabola: Vec<f64> = (0..8).map(|x| 3.0 * (x as f64).powi(2) + 2.0 * x as f64 + 1.0).collect();
+        assert!((curvature(&parabola) - 3.0).abs() < 1e-9);
+        assert!(curvature(&[1.0, 2.0, 3.0, 4.0]).abs() < 1e-10);
+
+        assert!((zscore(3.0, &[1.0, 2.0, 3.0]).unwrap() - 1.2247).abs() < 1e-4);
+        assert_eq!(zscore(1.0, &[1.0, 1.0, 1.0]), None);
+        assert_eq!(zscore(1.0, &[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn test_feature_set() {
+        let features = FeatureSet::compute(&snapshot());
+
+        assert!((features.ema_ratio - 1.05).abs() < 1e-10);
+        assert_eq!(features.ema_20_4h_distance_atr, Some(2.5));
+        assert_eq!(features.ema_50_4h_distance_atr, Some(5.0));
+        assert_eq!(features.volume_ratio_4h, Some(1.5));
+        assert_eq!(features.volatility_ratio, 1.5);
+        assert!(features.price_slope_pct > 0.0 && features.price_curvature_pct > 0.0);
+        assert!(features.price_zscore.unwrap() > 1.0);
+        assert_eq!(features.macd_slope, 0.0);
+        assert_eq!(features.oi_delta_pct, None);
+
+        // Rising price with falling RSI; flat MACD shows no divergence
+        assert_eq!(features.rsi_divergence, Some(Divergence::Bearish));
+        assert_eq!(features.macd_divergence, None);
+
+        let values = features.values();
+        assert!(values.iter().map(|(name, _)| *name).eq(FeatureSet::NAMES));
+        assert_eq!(values[16], ("rsi_divergence", Some(-1.0)));
+        assert_eq!(features.to_vector().len(), FeatureSet::NAMES.len());
+    }
+
+    #[test]
+    fn test_unavailable_inputs() {
+        let mut snapshot = MarketStateSnapshot::new("ETHUSDT".to_string(), 1000000, 3000.0);
+        snapshot.has_open_interest = true;
+        snapshot.open_interest_latest = 110.0;
+        snapshot.open_interest_avg_24h = 100.0;
+
+        let features = FeatureSet::compute(&snapshot);
+        assert_eq!(features.ema_ratio, 1.0);
+        assert_eq!(features.volatility_ratio, 1.0);
+        assert_eq!(features.ema_20_4h_distance_atr, None);
+        assert_eq!(features.volume_ratio_4h, None);
+        assert_eq!(features.price_zscore, None);
+        assert_eq!(features.rsi_divergence, None);
+        assert!((features.oi_delta_pct.unwrap() - 10.0).abs() < 1e-10);
+    }
+}
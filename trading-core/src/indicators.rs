@@ -0,0 +1,498 @@
+//! Streaming technical indicators computed from candles
+//!
+//! Each indicator consumes one value (or candle) at a time and returns `None`
+//! until it has seen enough data to be seeded. Definitions:
+//! - EMA seeded with the simple average of the first `period` values
+//! - RSI and ATR with Wilder's smoothing, seeded the same way
+//! - MACD line as EMA(12) - EMA(26), signal as EMA(9) of the line
+//! - Volume averages as a simple moving average
+//!
+//! [`Indicators3mCalculator`] and [`Indicators4hCalculator`] combine them into
+//! the records that snapshots are built from.
+
+use std::collections::VecDeque;
+
+use crate::types::{Candle, Indicators3m, Indicators4h};
+
+/// Exponential moving average
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    count: usize,
+    seed_sum: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "EMA period must be positive");
+        Self {
+            period,
+            alpha: 2.0 / (period as f64 + 1.0),
+            count: 0,
+            seed_sum: 0.0,
+            value: None,
+        }
+    }
+
+    /// Add a value and return the updated EMA once seeded
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.count += 1;
+        self.value = match self.value {
+            Some(prev) => Some(prev + self.alpha * (value - prev)),
+            None => {
+                self.seed_sum += value;
+                (self.count == self.period).then(|| self.seed_sum / self.period as f64)
+            }
+        };
+        self.value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// Simple moving average over a rolling window
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "SMA period must be positive");
+        Self {
+            period,
+            window: VecDeque::with_capacity(period),
+            sum: 0.0,
+        }
+    }
+
+    /// Add a value and return the average of the last `period` values once full
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+/// Wilder's smoothed average (used by RSI and ATR)
+#[derive(Debug, Clone)]
+struct WilderAverage {
+    period: usize,
+    count: usize,
+    seed_sum: f64,
+    value: Option<f64>,
+}
+
+impl WilderAverage {
+    fn new(period: usize) -> Self {
+        assert!(period > 0, "Wilder period must be positive");
+        Self {
+            period,
+            count: 0,
+            seed_sum: 0.0,
+            value: None,
+        }
+    }
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        self.count += 1;
+        let period = self.period as f64;
+        self.value = match self.value {
+            Some(prev) => Some((prev * (period - 1.0) + value) / period),
+            None => {
+                self.seed_sum += value;
+                (self.count == self.period).then(|| self.seed_sum / period)
+            }
+        };
+        self.value
+    }
+}
+
+/// Relative strength index with Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    prev_close: Option<f64>,
+    avg_gain: WilderAverage,
+    avg_loss: WilderAverage,
+    value: Option<f64>,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev_close: None,
+            avg_gain: WilderAverage::new(period),
+            avg_loss: WilderAverage::new(period),
+            value: None,
+        }
+    }
+
+    /// Add a close and return the updated RSI (0-100) once seeded
+    pub fn update(&mut self, close: f64) -> Option<f64> {
+        let prev = self.prev_close.replace(close)?;
+        let change = close - prev;
+        let gain = self.avg_gain.update(change.max(0.0));
+        let loss = self.avg_loss.update((-change).max(0.0));
+
+        self.value = match (gain, loss) {
+            (Some(gain), Some(loss)) if loss > 0.0 => Some(100.0 - 100.0 / (1.0 + gain / loss)),
+            (Some(gain), Some(_)) if gain > 0.0 => Some(100.0),
+            (Some(_), Some(_)) => Some(50.0),
+            _ => None,
+        };
+        self.value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// Average true range with Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Atr {
+    prev_close: Option<f64>,
+    average: WilderAverage,
+    value: Option<f64>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev_close: None,
+            average: WilderAverage::new(period),
+            value: None,
+        }
+    }
+
+    /// Add a candle and return the updated ATR once seeded
+    pub fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let true_range = match self.prev_close {
+            Some(prev) => (candle.high - candle.low)
+                .max((candle.high - prev).abs())
+                .max((candle.low - prev).abs()),
+            None => candle.high - candle.low,
+        };
+        self.prev_close = Some(candle.close);
+        self.value = self.average.update(true_range);
+        self.value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// MACD line (EMA12 - EMA26) with its signal line (EMA9 of the MACD line)
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    value: Option<f64>,
+}
+
+impl Macd {
+    pub fn new() -> Self {
+        Self {
+            fast: Ema::new(12),
+            slow: Ema::new(26),
+            signal: Ema::new(9),
+            value: None,
+        }
+    }
+
+    /// Add a close and return the updated MACD line once seeded
+    ///
+    /// The signal line seeds 8 closes after the MACD line.
+    pub fn update(&mut self, close: f64) -> Option<f64> {
+        let fast = self.fast.update(close);
+        let slow = self.slow.update(close);
+        self.value = fast.zip(slow).map(|(fast, slow)| fast - slow);
+        if let Some(line) = self.value {
+            self.signal.update(line);
+        }
+        self.value
+    }
+
+    /// MACD line
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Signal line
+    pub fn signal(&self) -> Option<f64> {
+        self.signal.value()
+    }
+
+    /// Histogram (MACD line - signal line)
+    pub fn histogram(&self) -> Option<f64> {
+        self.value.zip(self.signal()).map(|(line, signal)| line - signal)
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes [`Indicators3m`] from a stream of 3-minute candles
+#[derive(Debug, Clone)]
+pub struct Indicators3mCalculator {
+    ema_20: Ema,
+    ema_50: Ema,
+    macd: Macd,
+    rsi_7: Rsi,
+    rsi_14: Rsi,
+    atr_14: Atr,
+    avg_volume_20: Sma,
+}
+
+impl Indicators3mCalculator {
+    pub fn new() -> Self {
+        Self {
+            ema_20: Ema::new(20),
+            ema_50: Ema::new(50),
+            macd: Macd::new(),
+            rsi_7: Rsi::new(7),
+            rsi_14: Rsi::new(14),
+            atr_14: Atr::new(14),
+            avg_volume_20: Sma::new(20),
+        }
+    }
+
+    /// Add the next candle; returns indicators once every one is seeded
+    pub fn update(&mut self, candle: &Candle) -> Option<Indicators3m> {
+        let ema_20 = self.ema_20.update(candle.close);
+        let ema_50 = self.ema_50.update(candle.close);
+        let macd = self.macd.update(candle.close);
+        let rsi_7 = self.rsi_7.update(candle.close);
+        let rsi_14 = self.rsi_14.update(candle.close);
+        let atr_14 = self.atr_14.update(candle);
+        let avg_volume_20 = self.avg_volume_20.update(candle.volume);
+
+        Some(Indicators3m {
+            ema_20: ema_20?,
+            ema_50: ema_50?,
+            macd: macd?,
+            rsi_7: rsi_7?,
+            rsi_14: rsi_14?,
+            atr_14: atr_14?,
+            avg_volume_20: Some(avg_volume_20?),
+        })
+    }
+
+    /// Indicators for an ordered candle series, from the first seeded candle on
+    pub fn compute(candles: &[(i64, Candle)]) -> Vec<(i64, Indicators3m)> {
+        let mut calculator = Self::new();
+        candles
+            .iter()
+            .filter_map(|(ts, candle)| calculator.update(candle).map(|indicators| (*ts, indicators)))
+            .collect()
+    }
+}
+
+impl Default for Indicators3mCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes [`Indicators4h`] from a stream of 4-hour candles
+#[derive(Debug, Clone)]
+pub struct Indicators4hCalculator {
+    ema_20: Ema,
+    ema_50: Ema,
+    macd: Macd,
+    rsi_14: Rsi,
+    atr_3: Atr,
+    atr_14: Atr,
+    avg_volume_20: Sma,
+}
+
+impl Indicators4hCalculator {
+    pub fn new() -> Self {
+        Self {
+            ema_20: Ema::new(20),
+            ema_50: Ema::new(50),
+            macd: Macd::new(),
+            rsi_14: Rsi::new(14),
+            atr_3: Atr::new(3),
+            atr_14: Atr::new(14),
+            avg_volume_20: Sma::new(20),
+        }
+    }
+
+    /// Add the next candle; returns indicators once every one is seeded
+    pub fn update(&mut self, candle: &Candle) -> Option<Indicators4h> {
+        let ema_20 = self.ema_20.update(candle.close);
+        let ema_50 = self.ema_50.update(candle.close);
+        let macd = self.macd.update(candle.close);
+        let rsi_14 = self.rsi_14.update(candle.close);
+        let atr_3 = self.atr_3.update(candle);
+        let atr_14 = self.atr_14.update(candle);
+        let avg_volume_20 = self.avg_volume_20.update(candle.volume);
+
+        Some(Indicators4h {
+            ema_20: ema_20?,
+            ema_50: ema_50?,
+            macd: macd?,
+            rsi_14: rsi_14?,
+            atr_3: atr_3?,
+            atr_14: atr_14?,
+            avg_volume_20: Some(avg_volume_20?),
+        })
+    }
+
+    /// Indicators for an ordered candle series, from the first seeded candle on
+    pub fn compute(candles: &[(i64, Candle)]) -> Vec<(i64, Indicators4h)> {
+        let mut calculator = Self::new();
+        candles
+            .iter()
+            .filter_map(|(ts, candle)| calculator.update(candle).map(|indicators| (*ts, indicators)))
+            .collect()
+    }
+}
+
+impl Default for Indicators4hCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64) -> Candle {
+        Candle { open: close, high: close + 1.0, low: close - 1.0, close, volume: 1.0, trades: 1 }
+    }
+
+    #[test]
+    fn test_ema_seeded_with_sma() {
+        let mut ema = Ema::new(3);
+        assert_eq!(ema.update(1.0), None);
+        assert_eq!(ema.update(2.0), None);
+        assert_eq!(ema.update(3.0), Some(2.0));
+        // alpha = 0.5
+        assert_eq!(ema.update(6.0), Some(4.0));
+    }
+
+    #[test]
+    fn test_rsi_extremes_and_range() {
+        let mut rising = Rsi::new(14);
+        let last = (0..30).map(|i| rising.update(100.0 + i as f64)).last().unwrap();
+        assert_eq!(last, Some(100.0));
+
+        let mut flat = Rsi::new(7);
+        let last = (0..10).map(|_| flat.update(100.0)).last().unwrap();
+        assert_eq!(last, Some(50.0));
+
+        let mut mixed = Rsi::new(14);
+        for i in 0..100 {
+            if let Some(rsi) = mixed.update(100.0 + (i as f64 * 0.7).sin() * 5.0) {
+                assert!((0.0..=100.0).contains(&rsi));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rsi_wilder_reference() {
+        // Classic 14-period example; published tables round the averages, so
+        // the unrounded values differ from them in the first decimal
+        let closes = [
+            44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03,
+            45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64,
+        ];
+        let mut rsi = Rsi::new(14);
+        let values: Vec<f64> = closes.iter().filter_map(|c| rsi.update(*c)).collect();
+        assert_eq!(values.len(), 6);
+        assert!((values[0] - 70.464).abs() < 0.001, "{}", values[0]);
+        assert!((values[5] - 57.915).abs() < 0.001, "{}", values[5]);
+    }
+
+    #[test]
+    fn test_sma_rolls_over_window() {
+        let mut sma = Sma::new(3);
+        assert_eq!(sma.update(3.0), None);
+        assert_eq!(sma.update(6.0), None);
+        assert_eq!(sma.update(9.0), Some(6.0));
+        assert_eq!(sma.update(12.0), Some(9.0));
+    }
+
+    #[test]
+    fn test_macd_signal_and_histogram() {
+        let mut macd = Macd::new();
+        let closes: Vec<f64> = (0..40).map(|i| 100.0 + i as f64).collect();
+        for (i, close) in closes.iter().enumerate() {
+            let line = macd.update(*close);
+            assert_eq!(line.is_some(), i >= 25);
+            assert_eq!(macd.signal().is_some(), i >= 33);
+        }
+
+        // A linear trend converges to a constant MACD line, so the signal catches up
+        let line = macd.value().unwrap();
+        let histogram = macd.histogram().unwrap();
+        assert!(line > 0.0);
+        assert!((histogram - (line - macd.signal().unwrap())).abs() < 1e-12);
+        assert!(histogram.abs() < line);
+    }
+
+    #[test]
+    fn test_atr_uses_true_range() {
+        let mut atr = Atr::new(2);
+        assert_eq!(atr.update(&candle(10.0)), None);
+        // Gap up: true range is high - previous close = 21 - 10
+        assert_eq!(atr.update(&candle(20.0)), Some((2.0 + 11.0) / 2.0));
+    }
+
+    #[test]
+    fn test_calculators_warm_up() {
+        let mut calc_3m = Indicators3mCalculator::new();
+        let mut calc_4h = Indicators4hCalculator::new();
+        let mut first_3m = None;
+        let mut first_4h = None;
+        for i in 0..60 {
+            let c = candle(100.0 + (i as f64 * 0.3).sin());
+            if calc_3m.update(&c).is_some() && first_3m.is_none() {
+                first_3m = Some(i);
+            }
+            if calc_4h.update(&c).is_some() && first_4h.is_none() {
+                first_4h = Some(i);
+            }
+        }
+        // EMA(50) is the slowest to seed
+        assert_eq!(first_3m, Some(49));
+        assert_eq!(first_4h, Some(49));
+
+        let candles: Vec<(i64, Candle)> = (0..60).map(|i| (i * 180_000, candle(100.0 + (i as f64 * 0.3).sin()))).collect();
+        let computed = Indicators3mCalculator::compute(&candles);
+        assert_eq!(computed.len(), 11);
+        assert_eq!(computed[0].0, 49 * 180_000);
+        assert_eq!(computed[0].1.avg_volume_20, Some(1.0));
+    }
+
+    #[test]
+    fn test_calculators_average_volume() {
+        let candles: Vec<(i64, Candle)> = (0..60)
+            .map(|i| (i, Candle { volume: (i + 1) as f64, ..candle(100.0 + (i as f64 * 0.3).sin()) }))
+            .collect();
+        let expected = (41..=60).sum::<i64>() as f64 / 20.0;
+        assert_eq!(Indicators3mCalculator::compute(&candles).last().unwrap().1.avg_volume_20, Some(expected));
+        assert_eq!(Indicators4hCalculator::compute(&candles).last().unwrap().1.avg_volume_20, Some(expected));
+    }
+}
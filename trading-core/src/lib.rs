@@ -1,4 +1,14 @@
+pub mod features;
+pub mod indicators;
+pub mod regime;
 pub mod types;
 
 // Re-export common types
-pub use types::{CryptoFuturesSymbol, MarketStateSnapshot, TimestampMS};
+pub use features::{Divergence, FeatureSet};
+pub use regime::{MarketRegime, PositioningRegime, RegimeClassifier, TrendRegime, VolatilityRegime};
+pub use types::{
+    Candle, CryptoFuturesSymbol, ExitReason, ExitRule, FundingRateRecord, HorizonOutcome,
+    Indicators3m, Indicators4h, LiquidationRecord, MarketStateSnapshot, OpenInterestRecord,
+    OrderBookRecord, OutcomeHorizon, OutcomeSpec, ReferenceContext, Timeframe, TimeframeContext,
+    TimestampMS, TradeFlowRecord, TradeOutcome, TradeSide,
+};
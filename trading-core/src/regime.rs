@@ -0,0 +1,320 @@
+//! Market regime labels for snapshots
+//!
+//! The labels are stored as keyword payload fields so historical patterns can
+//! be restricted to the same regime with exact-match filters.
+
+use crate::types::MarketStateSnapshot;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Trend regime from the 4h EMA(20)/EMA(50) ratio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendRegime {
+    StrongUptrend,
+    Uptrend,
+    Sideways,
+    Downtrend,
+    StrongDowntrend,
+}
+
+/// Volatility regime from the 4h ATR(3)/ATR(14) ratio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolatilityRegime {
+    Compressed,
+    Normal,
+    Elevated,
+}
+
+/// Funding/positioning regime from the funding rate and open interest change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositioningRegime {
+    CrowdedLong,   // Funding strongly positive (longs paying shorts)
+    CrowdedShort,  // Funding strongly negative (shorts paying longs)
+    Leveraging,    // Neutral funding, open interest rising vs its 24h average
+    Deleveraging,  // Neutral funding, open interest falling vs its 24h average
+    Neutral,
+}
+
+impl TrendRegime {
+    /// Every trend regime, most bullish first
+    pub const ALL: [TrendRegime; 5] = [
+        TrendRegime::StrongUptrend,
+        TrendRegime::Uptrend,
+        TrendRegime::Sideways,
+        TrendRegime::Downtrend,
+        TrendRegime::StrongDowntrend,
+    ];
+
+    /// Keyword label stored in payloads, e.g. "strong_uptrend"
+    pub fn label(self) -> &'static str {
+        match self {
+            TrendRegime::StrongUptrend => "strong_uptrend",
+            TrendRegime::Uptrend => "uptrend",
+            TrendRegime::Sideways => "sideways",
+            TrendRegime::Downtrend => "downtrend",
+            TrendRegime::StrongDowntrend => "strong_downtrend",
+        }
+    }
+
+    /// Plain description for embedding text, e.g. "strong uptrend"
+    pub fn description(self) -> String {
+        self.label().replace('_', " ")
+    }
+}
+
+impl VolatilityRegime {
+    /// Every volatility regime, calmest first
+    pub const ALL: [VolatilityRegime; 3] = [
+        VolatilityRegime::Compressed,
+        VolatilityRegime::Normal,
+        VolatilityRegime::Elevated,
+    ];
+
+    /// Keyword label stored in payloads, e.g. "elevated"
+    pub fn label(self) -> &'static str {
+        match self {
+            VolatilityRegime::Compressed => "compressed",
+            VolatilityRegime::Normal => "normal",
+            VolatilityRegime::Elevated => "elevated",
+        }
+    }
+}
+
+impl PositioningRegime {
+    /// Every positioning regime
+    pub const ALL: [PositioningRegime; 5] = [
+        PositioningRegime::CrowdedLong,
+        PositioningRegime::CrowdedShort,
+        PositioningRegime::Leveraging,
+        PositioningRegime::Deleveraging,
+        PositioningRegime::Neutral,
+    ];
+
+    /// Keyword label stored in payloads, e.g. "crowded_long"
+    pub fn label(self) -> &'static str {
+        match self {
+            PositioningRegime::CrowdedLong => "crowded_long",
+            PositioningRegime::CrowdedShort => "crowded_short",
+            PositioningRegime::Leveraging => "leveraging",
+            PositioningRegime::Deleveraging => "deleveraging",
+            PositioningRegime::Neutral => "neutral",
+        }
+    }
+}
+
+/// Find the regime with a label, naming the valid labels on error
+fn parse_label<T: Copy>(s: &str, all: &[T], label: impl Fn(T) -> &'static str, kind: &str) -> Result<T> {
+    let s = s.trim().to_lowercase();
+    all.iter().copied().find(|regime| label(*regime) == s).ok_or_else(|| {
+        let labels: Vec<&str> = all.iter().map(|regime| label(*regime)).collect();
+        anyhow!("Invalid {} regime '{}'. Must be one of {}", kind, s, labels.join(", "))
+    })
+}
+
+impl FromStr for TrendRegime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_label(s, &Self::ALL, Self::label, "trend")
+    }
+}
+
+impl FromStr for VolatilityRegime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_label(s, &Self::ALL, Self::label, "volatility")
+    }
+}
+
+impl FromStr for PositioningRegime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_label(s, &Self::ALL, Self::label, "positioning")
+    }
+}
+
+impl fmt::Display for TrendRegime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl fmt::Display for VolatilityRegime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl fmt::Display for PositioningRegime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Regime labels of one snapshot
+///
+/// Volatility is `None` without 4h ATRs, positioning is `None` when neither
+/// funding nor open interest data was available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketRegime {
+    pub trend: TrendRegime,
+    pub volatility: Option<VolatilityRegime>,
+    pub positioning: Option<PositioningRegime>,
+}
+
+/// Labels snapshots with trend, volatility and positioning regimes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegimeClassifier {
+    pub trend_ratio: f64,            // EMA ratio distance from 1.0 for a trend
+    pub strong_trend_ratio: f64,     // EMA ratio distance from 1.0 for a strong trend
+    pub elevated_volatility: f64,    // ATR(3)/ATR(14) above this is elevated
+    pub compressed_volatility: f64,  // ATR(3)/ATR(14) below this is compressed
+    pub crowded_funding: f64,        // |funding rate| above this is crowded
+    pub oi_shift_pct: f64,           // |OI delta %| above this is (de)leveraging
+}
+
+impl Default for RegimeClassifier {
+    fn default() -> Self {
+        Self {
+            trend_ratio: 0.005,
+            strong_trend_ratio: 0.02,
+            elevated_volatility: 1.5,
+            compressed_volatility: 0.75,
+            crowded_funding: 0.0005,
+            oi_shift_pct: 5.0,
+        }
+    }
+}
+
+impl RegimeClassifier {
+    /// Label a snapshot with all three regimes
+    pub fn classify(&self, snapshot: &MarketStateSnapshot) -> MarketRegime {
+        MarketRegime {
+            trend: self.trend(snapshot.ema_ratio_20_50()),
+            volatility: self.volatility(snapshot.atr_3_4h, snapshot.atr_14_4h),
+            positioning: self.positioning(snapshot),
+        }
+    }
+
+    /// Trend regime for an EMA(20)/EMA(50) ratio
+    pub fn trend(&self, ema_ratio: f64) -> TrendRegime {
+        let distance = ema_ratio - 1.0;
+        if distance > self.strong_trend_ratio {
+            TrendRegime::StrongUptrend
+        } else if distance > self.trend_ratio {
+            TrendRegime::Uptrend
+        } else if distance < -self.strong_trend_ratio {
+            TrendRegime::StrongDowntrend
+        } else if distance < -self.trend_ratio {
+            TrendRegime::Downtrend
+        } else {
+            TrendRegime::Sideways
+        }
+    }
+
+    /// Volatility regime from short and standard ATR (None unless both are positive)
+    pub fn volatility(&self, atr_short: f64, atr_standard: f64) -> Option<VolatilityRegime> {
+        if atr_short <= 0.0 || atr_standard <= 0.0 {
+            return None;
+        }
+
+        let ratio = atr_short / atr_standard;
+        Some(if ratio > self.elevated_volatility {
+            VolatilityRegime::Elevated
+        } else if ratio < self.compressed_volatility {
+            VolatilityRegime::Compressed
+        } else {
+            VolatilityRegime::Normal
+        })
+    }
+
+    /// Positioning regime: funding extremes first, then open interest shifts
+    pub fn positioning(&self, snapshot: &MarketStateSnapshot) -> Option<PositioningRegime> {
+        if !snapshot.has_funding_rate && !snapshot.has_open_interest {
+            return None;
+        }
+
+        if snapshot.has_funding_rate {
+            if snapshot.funding_rate > self.crowded_funding {
+                return Some(PositioningRegime::CrowdedLong);
+            }
+            if snapshot.funding_rate < -self.crowded_funding {
+                return Some(PositioningRegime::CrowdedShort);
+            }
+        }
+
+        if snapshot.has_open_interest {
+            let oi_delta = snapshot.oi_delta_pct();
+            if oi_delta > self.oi_shift_pct {
+                return Some(PositioningRegime::Leveraging);
+            }
+            if oi_delta < -self.oi_shift_pct {
+                return Some(PositioningRegime::Deleveraging);
+            }
+        }
+
+        Some(PositioningRegime::Neutral)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_snapshot() {
+        let classifier = RegimeClassifier::default();
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.ema_20_4h = 51500.0;
+        snapshot.ema_50_4h = 50000.0;
+        snapshot.atr_3_4h = 900.0;
+        snapshot.atr_14_4h = 500.0;
+        snapshot.funding_rate = 0.0001;
+        snapshot.open_interest_latest = 93.0;
+        snapshot.open_interest_avg_24h = 100.0;
+
+        let regime = classifier.classify(&snapshot);
+        assert_eq!(regime.trend, TrendRegime::StrongUptrend);
+        assert_eq!(regime.volatility, Some(VolatilityRegime::Elevated));
+        assert_eq!(regime.positioning, Some(PositioningRegime::Deleveraging));
+
+        snapshot.funding_rate = -0.001;
+        assert_eq!(classifier.positioning(&snapshot), Some(PositioningRegime::CrowdedShort));
+
+        // Unavailable data leaves the regime unset
+        snapshot.has_funding_rate = false;
+        snapshot.has_open_interest = false;
+        snapshot.atr_14_4h = 0.0;
+        let regime = classifier.classify(&snapshot);
+        assert_eq!(regime.volatility, None);
+        assert_eq!(regime.positioning, None);
+    }
+
+    #[test]
+    fn test_trend_thresholds() {
+        let classifier = RegimeClassifier::default();
+        assert_eq!(classifier.trend(1.03), TrendRegime::StrongUptrend);
+        assert_eq!(classifier.trend(1.01), TrendRegime::Uptrend);
+        assert_eq!(classifier.trend(1.0), TrendRegime::Sideways);
+        assert_eq!(classifier.trend(0.99), TrendRegime::Downtrend);
+        assert_eq!(classifier.trend(0.97), TrendRegime::StrongDowntrend);
+    }
+
+    #[test]
+    fn test_labels_round_trip() {
+        for regime in TrendRegime::ALL {
+            assert_eq!(regime.label().parse::<TrendRegime>().unwrap(), regime);
+            assert_eq!(serde_json::to_string(&regime).unwrap(), format!("\"{}\"", regime));
+        }
+        assert_eq!(TrendRegime::StrongDowntrend.description(), "strong downtrend");
+        assert_eq!("Crowded_Long".parse::<PositioningRegime>().unwrap(), PositioningRegime::CrowdedLong);
+        assert!("calm".parse::<VolatilityRegime>().is_err());
+    }
+}
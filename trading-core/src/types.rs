@@ -1,7 +1,20 @@
+pub mod market_data;
 pub mod market_snapshot;
+pub mod outcome;
+pub mod reference;
+pub mod timeframe;
 
 // Re-export common types
+pub use market_data::{
+    Candle, FundingRateRecord, Indicators3m, Indicators4h, LiquidationRecord, OpenInterestRecord,
+    OrderBookRecord, TradeFlowRecord,
+};
 pub use market_snapshot::MarketStateSnapshot;
+pub use outcome::{
+    ExitReason, ExitRule, HorizonOutcome, OutcomeHorizon, OutcomeSpec, TradeOutcome, TradeSide,
+};
+pub use reference::ReferenceContext;
+pub use timeframe::{Timeframe, TimeframeContext};
 
 /// Timestamp in milliseconds since Unix epoch
 pub type TimestampMS = u64;
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// OHLCV candle as stored by llm-trader-data (candles_3m / candles_4h)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    #[serde(default)]
+    pub trades: u64, // Number of trades (0 when the source does not record it)
+}
+
+/// 3-minute technical indicators (indicators_3m)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Indicators3m {
+    pub ema_20: f64,
+    pub ema_50: f64,
+    pub macd: f64,   // MACD line only (EMA12 - EMA26)
+    pub rsi_7: f64,  // 7-period RSI (Wilder's)
+    pub rsi_14: f64, // 14-period RSI (Wilder's)
+    pub atr_14: f64,
+    #[serde(default)]
+    pub avg_volume_20: Option<f64>, // SMA(20) of volume (None when the source does not record it)
+}
+
+/// 4-hour technical indicators (indicators_4h)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Indicators4h {
+    pub ema_20: f64,
+    pub ema_50: f64,
+    pub macd: f64,
+    pub rsi_14: f64,
+    pub atr_3: f64,  // Short-term volatility
+    pub atr_14: f64, // Standard volatility
+    #[serde(default)]
+    pub avg_volume_20: Option<f64>, // SMA(20) of volume (None when the source does not record it)
+}
+
+/// Open interest record (open_interest)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OpenInterestRecord {
+    pub open_interest: f64,
+}
+
+/// Perpetual funding rate record (funding_rate)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FundingRateRecord {
+    pub funding_rate: f64,
+}
+
+/// Top of the order book (order_book)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OrderBookRecord {
+    pub bid_price: f64,
+    pub bid_size: f64,
+    pub ask_price: f64,
+    pub ask_size: f64,
+}
+
+impl OrderBookRecord {
+    /// Bid/ask spread in basis points of the mid price
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid = (self.bid_price + self.ask_price) / 2.0;
+        (mid > 0.0).then(|| (self.ask_price - self.bid_price) / mid * 10_000.0)
+    }
+
+    /// Top-of-book size imbalance in -1..=1 (positive when bids outweigh asks)
+    pub fn imbalance(&self) -> Option<f64> {
+        let total = self.bid_size + self.ask_size;
+        (total > 0.0).then(|| (self.bid_size - self.ask_size) / total)
+    }
+}
+
+/// Taker volume split by aggressor side over one 3m candle (trade_flow)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TradeFlowRecord {
+    pub taker_buy_volume: f64,
+    pub taker_sell_volume: f64,
+}
+
+/// Liquidated volume over one 3m candle (liquidations)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationRecord {
+    pub long_liquidations: f64,  // Longs force-closed (sell orders)
+    pub short_liquidations: f64, // Shorts force-closed (buy orders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candle_trade_count_defaults_to_zero() {
+        let candle: Candle = serde_json::from_str(
+            r#"{"open": 1.0, "high": 2.0, "low": 0.5, "close": 1.5, "volume": 10.0}"#,
+        )
+        .unwrap();
+        assert_eq!(candle.close, 1.5);
+        assert_eq!(candle.trades, 0);
+    }
+
+    #[test]
+    fn test_missing_indicator_field_is_named() {
+        let err = serde_json::from_str::<Indicators4h>(
+            r#"{"ema_20": 1.0, "ema_50": 1.0, "macd": 0.0, "rsi_14": 50.0, "atr_14": 1.0}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing field `atr_3`"));
+    }
+
+    #[test]
+    fn test_order_book_spread_and_imbalance() {
+        let book = OrderBookRecord { bid_price: 99.99, bid_size: 30.0, ask_price: 100.01, ask_size: 10.0 };
+        assert!((book.spread_bps().unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(book.imbalance(), Some(0.5));
+
+        let empty = OrderBookRecord { bid_price: 0.0, bid_size: 0.0, ask_price: 0.0, ask_size: 0.0 };
+        assert_eq!(empty.spread_bps(), None);
+        assert_eq!(empty.imbalance(), None);
+    }
+}
@@ -1,6 +1,13 @@
+use crate::features::{self, FeatureSet};
+use crate::regime::{MarketRegime, RegimeClassifier};
+use crate::types::market_data::Candle;
+use crate::types::outcome::{HorizonOutcome, OutcomeSpec, TradeOutcome, TradeSide};
+use crate::types::reference::ReferenceContext;
+use crate::types::timeframe::{Timeframe, TimeframeContext};
 use crate::types::TimestampMS;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Market state snapshot capturing all indicators, time series, and outcomes
 /// for a specific point in time. This is the primary data structure for RAG.
@@ -42,6 +49,14 @@ pub struct MarketStateSnapshot {
     pub macd_4h_values: Vec<f64>,   // Last 10 × 4h
     pub rsi_14_4h_values: Vec<f64>, // Last 10 × 4h
 
+    // ═══════════════════════════════════════════════════
+    // HIGHER TIMEFRAME CONTEXT (None when the source has no such candles)
+    // ═══════════════════════════════════════════════════
+    #[serde(default)]
+    pub context_1h: Option<TimeframeContext>, // As of the last closed 1h candle
+    #[serde(default)]
+    pub context_1d: Option<TimeframeContext>, // As of the last closed daily candle
+
     // ═══════════════════════════════════════════════════
     // MARKET MICROSTRUCTURE (Futures-specific)
     // ═══════════════════════════════════════════════════
@@ -50,6 +65,48 @@ pub struct MarketStateSnapshot {
     pub funding_rate: f64,      // Current perpetual funding rate (%)
     pub price_change_1h: f64,   // % change
     pub price_change_4h: f64,   // % change
+    #[serde(default = "default_true")]
+    pub has_open_interest: bool, // False when OI data was unavailable (fields are 0.0)
+    #[serde(default = "default_true")]
+    pub has_funding_rate: bool,  // False when funding data was unavailable (field is 0.0)
+    #[serde(default = "default_true")]
+    pub has_price_change_1h: bool, // False when the 1h-old candle was missing (field is 0.0)
+    #[serde(default = "default_true")]
+    pub has_price_change_4h: bool, // False when the 4h-old candle was missing (field is 0.0)
+    #[serde(default)]
+    pub spread_bps: Option<f64>,               // Bid/ask spread (bps of mid)
+    #[serde(default)]
+    pub book_imbalance: Option<f64>,           // Top-of-book (bid - ask) / (bid + ask) size, -1..1
+    #[serde(default)]
+    pub taker_buy_sell_ratio_30m: Option<f64>, // Taker buy / taker sell volume, last 30m
+    #[serde(default)]
+    pub cvd_30m: Option<f64>,                  // Cumulative volume delta (taker buy - sell), last 30m
+    #[serde(default)]
+    pub long_liquidations_30m: Option<f64>,    // Long liquidation volume, last 30m
+    #[serde(default)]
+    pub short_liquidations_30m: Option<f64>,   // Short liquidation volume, last 30m
+
+    // ═══════════════════════════════════════════════════
+    // CROSS-ASSET CONTEXT (None for the reference symbol itself)
+    // ═══════════════════════════════════════════════════
+    #[serde(default)]
+    pub reference: Option<ReferenceContext>, // Reference asset (e.g. BTCUSDT) at the same time
+
+    // ═══════════════════════════════════════════════════
+    // REGIME (labelled by RegimeClassifier at extraction)
+    // ═══════════════════════════════════════════════════
+    #[serde(default)]
+    pub regime: Option<MarketRegime>,
+
+    // ═══════════════════════════════════════════════════
+    // DATA QUALITY
+    // ═══════════════════════════════════════════════════
+    #[serde(default)]
+    pub missing_points_3m: Vec<usize>, // 3m series indices that were filled (0 = oldest)
+    #[serde(default)]
+    pub missing_points_4h: Vec<usize>, // 4h series indices that were filled (0 = oldest)
+    #[serde(default = "default_data_quality")]
+    pub data_quality: f64,             // Share of series points present in the source (0.0-1.0)
 
     // ═══════════════════════════════════════════════════
     // OUTCOMES (Calculated from FUTURE data)
@@ -64,8 +121,16 @@ pub struct MarketStateSnapshot {
     // ═══════════════════════════════════════════════════
     pub max_drawdown_1h: Option<f64>,   // Worst intra-period drawdown (%)
     pub max_runup_1h: Option<f64>,      // Best intra-period runup (%)
-    pub hit_stop_loss: Option<bool>,    // Did price hit -2% stop?
-    pub hit_take_profit: Option<bool>,  // Did price hit +3% target?
+    pub hit_stop_loss: Option<bool>,    // Did 1h closes hit the long stop?
+    pub hit_take_profit: Option<bool>,  // Did 1h closes hit the long target?
+    #[serde(default)]
+    pub outcomes: BTreeMap<String, HorizonOutcome>, // Per-horizon outcomes from the OutcomeSpec
+    #[serde(default)]
+    pub long_trade: Option<TradeOutcome>,  // Simulated long from the 3m high/low path
+    #[serde(default)]
+    pub short_trade: Option<TradeOutcome>, // Simulated short from the 3m high/low path
+    #[serde(default)]
+    pub outcomes_pending: bool,         // Horizon not yet elapsed (backfill later)
 }
 
 impl MarketStateSnapshot {
@@ -92,11 +157,28 @@ impl MarketStateSnapshot {
             avg_volume_4h: 0.0,
             macd_4h_values: Vec::new(),
             rsi_14_4h_values: Vec::new(),
+            context_1h: None,
+            context_1d: None,
             open_interest_latest: 0.0,
             open_interest_avg_24h: 0.0,
             funding_rate: 0.0,
             price_change_1h: 0.0,
             price_change_4h: 0.0,
+            has_open_interest: true,
+            has_funding_rate: true,
+            has_price_change_1h: true,
+            has_price_change_4h: true,
+            spread_bps: None,
+            book_imbalance: None,
+            taker_buy_sell_ratio_30m: None,
+            cvd_30m: None,
+            long_liquidations_30m: None,
+            short_liquidations_30m: None,
+            reference: None,
+            regime: None,
+            missing_points_3m: Vec::new(),
+            missing_points_4h: Vec::new(),
+            data_quality: 1.0,
             outcome_15m: None,
             outcome_1h: None,
             outcome_4h: None,
@@ -105,6 +187,10 @@ impl MarketStateSnapshot {
             max_runup_1h: None,
             hit_stop_loss: None,
             hit_take_profit: None,
+            outcomes: BTreeMap::new(),
+            long_trade: None,
+            short_trade: None,
+            outcomes_pending: false,
         }
     }
 
@@ -117,6 +203,13 @@ impl MarketStateSnapshot {
         }
     }
 
+    /// Higher timeframe blocks that are present, shortest timeframe first
+    pub fn timeframe_contexts(&self) -> impl Iterator<Item = (Timeframe, &TimeframeContext)> {
+        [(Timeframe::H1, &self.context_1h), (Timeframe::D1, &self.context_1d)]
+            .into_iter()
+            .filter_map(|(timeframe, context)| context.as_ref().map(|c| (timeframe, c)))
+    }
+
     /// Calculate OI delta percentage
     pub fn oi_delta_pct(&self) -> f64 {
         if self.open_interest_avg_24h.abs() > 1e-10 {
@@ -128,33 +221,27 @@ impl MarketStateSnapshot {
         }
     }
 
-    /// Calculate slope from a series of values using simple linear regression
-    pub fn calculate_slope(values: &[f64]) -> f64 {
-        if values.len() < 2 {
-            return 0.0;
-        }
-
-        let n = values.len() as f64;
-        let x_mean = (values.len() - 1) as f64 / 2.0;
-        let y_mean = values.iter().sum::<f64>() / n;
+    /// Regime labels, classified with the default thresholds when none are stored
+    pub fn regime_labels(&self) -> MarketRegime {
+        self.regime.unwrap_or_else(|| RegimeClassifier::default().classify(self))
+    }
 
-        let numerator: f64 = values
-            .iter()
-            .enumerate()
-            .map(|(i, &y)| (i as f64 - x_mean) * (y - y_mean))
-            .sum();
+    /// Total liquidation volume over the last 30m (None without liquidation data)
+    pub fn liquidations_30m(&self) -> Option<f64> {
+        match (self.long_liquidations_30m, self.short_liquidations_30m) {
+            (Some(long), Some(short)) => Some(long + short),
+            _ => None,
+        }
+    }
 
-        let denominator: f64 = values
-            .iter()
-            .enumerate()
-            .map(|(i, _)| (i as f64 - x_mean).powi(2))
-            .sum();
+    /// Derived features (slopes, z-scores, EMA distances, divergences, ...)
+    pub fn features(&self) -> FeatureSet {
+        FeatureSet::compute(self)
+    }
 
-        if denominator.abs() < 1e-10 {
-            0.0
-        } else {
-            numerator / denominator
-        }
+    /// Calculate slope from a series of values using simple linear regression
+    pub fn calculate_slope(values: &[f64]) -> f64 {
+        features::slope(values)
     }
 
     /// Calculate RSI slope from the time series
@@ -168,7 +255,8 @@ impl MarketStateSnapshot {
     }
 
     /// Calculate outcomes by looking at future price data
-    /// Note: This is a placeholder. Real implementation would query LMDB for future prices.
+    ///
+    /// Horizons whose price is `None` (not yet elapsed or missing) are left as `None`.
     pub fn calculate_outcomes_from_future_prices(
         &mut self,
         price_15m: Option<f64>,
@@ -198,6 +286,37 @@ impl MarketStateSnapshot {
         Ok(())
     }
 
+    /// Calculate outcomes for every horizon of an [`OutcomeSpec`]
+    ///
+    /// Stores the per-horizon results (from closes) in `outcomes` and mirrors the
+    /// horizons labelled "15m", "1h", "4h" and "24h" into the legacy outcome
+    /// fields (the 1h entry supplies runup/drawdown and stop/target hits, seen
+    /// from the long side). Long and short trades are simulated on the candle
+    /// highs/lows.
+    ///
+    /// # Arguments
+    /// * `spec` - Horizons and stop/target rules (ATR rules use `atr_14_4h`)
+    /// * `candles` - Future candles as `(offset_ms, candle)`, offsets ascending
+    /// * `elapsed_ms` - How far past the snapshot data exists
+    pub fn calculate_outcomes(&mut self, spec: &OutcomeSpec, candles: &[(i64, Candle)], elapsed_ms: i64) {
+        let closes: Vec<(i64, f64)> = candles.iter().map(|(offset, c)| (*offset, c.close)).collect();
+        self.outcomes = spec.evaluate(self.price, self.atr_14_4h, &closes, elapsed_ms);
+        self.long_trade = spec.simulate_trade(TradeSide::Long, self.price, self.atr_14_4h, candles, elapsed_ms);
+        self.short_trade = spec.simulate_trade(TradeSide::Short, self.price, self.atr_14_4h, candles, elapsed_ms);
+
+        let outcome = |label: &str| self.outcomes.get(label).copied().unwrap_or_default();
+        self.outcome_15m = outcome("15m").return_pct;
+        self.outcome_1h = outcome("1h").return_pct;
+        self.outcome_4h = outcome("4h").return_pct;
+        self.outcome_24h = outcome("24h").return_pct;
+
+        let hour = outcome("1h");
+        self.max_runup_1h = hour.max_runup_pct;
+        self.max_drawdown_1h = hour.max_drawdown_pct;
+        self.hit_stop_loss = hour.hit_stop_loss;
+        self.hit_take_profit = hour.hit_take_profit;
+    }
+
     /// Calculate max runup, max drawdown, and stop/target hits
     fn calculate_intraperiod_metrics(&mut self, prices: &[f64], base_price: f64) {
         let mut max_runup = 0.0f64;
@@ -229,6 +348,14 @@ impl MarketStateSnapshot {
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_data_quality() -> f64 {
+    1.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +400,43 @@ mod tests {
         assert_eq!(snapshot.hit_stop_loss, Some(true));
     }
 
+    #[test]
+    fn test_outcomes_from_spec() {
+        use crate::types::outcome::ExitRule;
+
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.atr_14_4h = 500.0;
+        let spec = OutcomeSpec::default().with_stop(ExitRule::AtrMultiple(1.0));
+
+        // 3m closes for the first hour, then the 4h close
+        let candle = |close: f64| Candle { open: close, high: close, low: close, close, volume: 1.0, trades: 1 };
+        let mut path: Vec<(i64, Candle)> = (1..=20)
+            .map(|i| (i * 180_000, candle(50000.0 - i as f64 * 30.0)))
+            .collect();
+        path.push((4 * 3_600_000, candle(51500.0)));
+
+        snapshot.calculate_outcomes(&spec, &path, 4 * 3_600_000);
+
+        assert_eq!(snapshot.outcomes.len(), 4);
+        assert_eq!(snapshot.outcome_4h, Some(3.0));
+        assert_eq!(snapshot.outcome_24h, None);
+        assert_eq!(snapshot.outcomes["24h"], HorizonOutcome::default());
+        // -1.2% drawdown hits the 1 ATR (1%) stop but not the 2% one
+        assert!((snapshot.max_drawdown_1h.unwrap() + 1.2).abs() < 1e-9);
+        assert_eq!(snapshot.hit_stop_loss, Some(true));
+        assert_eq!(snapshot.outcomes["4h"].hit_take_profit, Some(true));
+
+        // The 1% stop closes the long in the first hour and the short at 4h
+        use crate::types::outcome::ExitReason;
+        let long = snapshot.long_trade.unwrap();
+        assert_eq!(long.exit_reason, ExitReason::StopLoss);
+        assert_eq!(long.exit_after_ms, 17 * 180_000);
+        let short = snapshot.short_trade.unwrap();
+        assert_eq!(short.exit_reason, ExitReason::StopLoss);
+        assert_eq!(short.exit_after_ms, 4 * 3_600_000);
+        assert!((short.mfe_pct - 1.2).abs() < 1e-9);
+    }
+
     #[test]
     fn test_derived_features() {
         let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
@@ -287,4 +451,31 @@ mod tests {
         let oi_delta = snapshot.oi_delta_pct();
         assert_eq!(oi_delta, 10.0);
     }
+
+    #[test]
+    fn test_timeframe_contexts_optional() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        assert_eq!(snapshot.timeframe_contexts().count(), 0);
+
+        // Snapshots serialized before the 1h/1d blocks existed still load
+        let mut json = serde_json::to_value(&snapshot).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("context_1h");
+        fields.remove("context_1d");
+        let restored: MarketStateSnapshot = serde_json::from_value(json).unwrap();
+        assert!(restored.context_1h.is_none() && restored.context_1d.is_none());
+
+        snapshot.context_1d = Some(TimeframeContext {
+            ema_20: 49000.0,
+            ema_50: 48000.0,
+            macd: 10.0,
+            rsi_14: 60.0,
+            atr_14: 900.0,
+            prev_high: 50500.0,
+            prev_low: 49200.0,
+            prev_close: 50100.0,
+        });
+        let present: Vec<Timeframe> = snapshot.timeframe_contexts().map(|(tf, _)| tf).collect();
+        assert_eq!(present, vec![Timeframe::D1]);
+    }
 }
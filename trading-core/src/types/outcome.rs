@@ -0,0 +1,515 @@
+use crate::types::market_data::Candle;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Longest gap between a horizon and the last close at or before it for that
+/// close to stand in as the horizon's return (capped at a quarter of the horizon)
+const RETURN_TOLERANCE_MS: i64 = 15 * MINUTE_MS;
+
+/// Distance from the entry price at which a stop or target triggers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExitRule {
+    /// Fixed percentage of the entry price
+    Percent(f64),
+    /// Multiple of the snapshot's 4h ATR(14)
+    AtrMultiple(f64),
+}
+
+impl ExitRule {
+    /// Distance from the entry price in percent
+    ///
+    /// Returns `None` for ATR rules when the ATR or price is not positive.
+    pub fn distance_pct(&self, price: f64, atr: f64) -> Option<f64> {
+        match *self {
+            ExitRule::Percent(pct) => Some(pct),
+            ExitRule::AtrMultiple(multiple) if atr > 0.0 && price > 0.0 => {
+                Some(multiple * atr / price * 100.0)
+            }
+            ExitRule::AtrMultiple(_) => None,
+        }
+    }
+}
+
+impl FromStr for ExitRule {
+    type Err = anyhow::Error;
+
+    /// Parse "2%" or "2" (percent) and "1.5atr" (ATR multiple)
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let (number, rule): (&str, fn(f64) -> ExitRule) = match s.strip_suffix("atr") {
+            Some(multiple) => (multiple, ExitRule::AtrMultiple),
+            None => (s.strip_suffix('%').unwrap_or(&s), ExitRule::Percent),
+        };
+
+        let value: f64 = number
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Invalid exit rule '{}'. Expected e.g. '2%' or '1.5atr'", s))?;
+        if !(value > 0.0 && value.is_finite()) {
+            return Err(anyhow!("Exit rule '{}' must be positive", s));
+        }
+
+        Ok(rule(value))
+    }
+}
+
+impl fmt::Display for ExitRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitRule::Percent(pct) => write!(f, "{}%", pct),
+            ExitRule::AtrMultiple(multiple) => write!(f, "{}atr", multiple),
+        }
+    }
+}
+
+/// A lookahead horizon, labelled like "15m", "4h" or "24h"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeHorizon {
+    pub label: String,
+    pub duration_ms: i64,
+}
+
+impl OutcomeHorizon {
+    /// Create a horizon from whole minutes
+    ///
+    /// The label uses hours when the duration is a whole number of hours.
+    pub fn minutes(minutes: i64) -> Self {
+        let label = if minutes % 60 == 0 {
+            format!("{}h", minutes / 60)
+        } else {
+            format!("{}m", minutes)
+        };
+        Self {
+            label,
+            duration_ms: minutes * MINUTE_MS,
+        }
+    }
+}
+
+impl FromStr for OutcomeHorizon {
+    type Err = anyhow::Error;
+
+    /// Parse "15m", "8h" or "1d" (days are labelled in hours, e.g. "24h")
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let invalid = || anyhow!("Invalid horizon '{}'. Expected e.g. '15m', '8h' or '1d'", s);
+
+        let unit_minutes = match s.chars().last() {
+            Some('m') => 1,
+            Some('h') => 60,
+            Some('d') => 24 * 60,
+            _ => return Err(invalid()),
+        };
+        let count: i64 = s[..s.len() - 1].parse().map_err(|_| invalid())?;
+        if count <= 0 {
+            return Err(invalid());
+        }
+
+        Ok(Self::minutes(count * unit_minutes))
+    }
+}
+
+/// Which outcomes the snapshot extractor records
+///
+/// Every horizon gets a return, max runup/drawdown and stop/target hits
+/// evaluated over the price path up to that horizon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutcomeSpec {
+    pub horizons: Vec<OutcomeHorizon>,
+    pub stop: ExitRule,
+    pub target: ExitRule,
+}
+
+impl OutcomeSpec {
+    /// Create a spec with the given horizons and exit rules
+    ///
+    /// # Arguments
+    /// * `horizons` - Lookahead horizons (sorted shortest first)
+    /// * `stop` - Adverse move that counts as a stop loss
+    /// * `target` - Favourable move that counts as a take profit
+    pub fn new(mut horizons: Vec<OutcomeHorizon>, stop: ExitRule, target: ExitRule) -> Self {
+        horizons.sort_by_key(|h| h.duration_ms);
+        horizons.dedup_by_key(|h| h.duration_ms);
+        Self { horizons, stop, target }
+    }
+
+    /// Replace the horizons
+    pub fn with_horizons(self, horizons: Vec<OutcomeHorizon>) -> Self {
+        Self::new(horizons, self.stop, self.target)
+    }
+
+    /// Set the stop loss rule
+    pub fn with_stop(mut self, stop: ExitRule) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Set the take profit rule
+    pub fn with_target(mut self, target: ExitRule) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Longest horizon in milliseconds (0 without horizons)
+    pub fn max_horizon_ms(&self) -> i64 {
+        self.horizons.iter().map(|h| h.duration_ms).max().unwrap_or(0)
+    }
+
+    /// Evaluate every horizon against a future price path
+    ///
+    /// The return at a horizon is taken from the last close at or before it,
+    /// as long as that close is within `RETURN_TOLERANCE_MS` (or a quarter of
+    /// the horizon, if shorter); a wider gap leaves the return unset.
+    ///
+    /// # Arguments
+    /// * `price` - Entry price
+    /// * `atr` - ATR used by ATR-multiple exit rules
+    /// * `path` - Future closes as `(offset_ms, close)`, offsets ascending and positive
+    /// * `elapsed_ms` - How far past the entry data exists; longer horizons are pending
+    ///
+    /// # Returns
+    /// One outcome per horizon label; pending horizons have every field unset
+    pub fn evaluate(
+        &self,
+        price: f64,
+        atr: f64,
+        path: &[(i64, f64)],
+        elapsed_ms: i64,
+    ) -> BTreeMap<String, HorizonOutcome> {
+        let stop_pct = self.stop.distance_pct(price, atr);
+        let target_pct = self.target.distance_pct(price, atr);
+        let pct_change = |close: f64| (close - price) / price * 100.0;
+
+        self.horizons
+            .iter()
+            .map(|horizon| {
+                if horizon.duration_ms > elapsed_ms {
+                    return (horizon.label.clone(), HorizonOutcome::default());
+                }
+
+                let changes: Vec<(i64, f64)> = path
+                    .iter()
+                    .take_while(|(offset, _)| *offset <= horizon.duration_ms)
+                    .map(|(offset, close)| (*offset, pct_change(*close)))
+                    .collect();
+                let tolerance_ms = RETURN_TOLERANCE_MS.min(horizon.duration_ms / 4);
+                let return_pct = changes
+                    .last()
+                    .filter(|(offset, _)| *offset >= horizon.duration_ms - tolerance_ms)
+                    .map(|(_, change)| *change);
+
+                let mut outcome = HorizonOutcome {
+                    return_pct,
+                    ..Default::default()
+                };
+                if !changes.is_empty() {
+                    let runup = changes.iter().fold(0.0f64, |acc, (_, c)| acc.max(*c));
+                    let drawdown = changes.iter().fold(0.0f64, |acc, (_, c)| acc.min(*c));
+                    outcome.max_runup_pct = Some(runup);
+                    outcome.max_drawdown_pct = Some(drawdown);
+                    outcome.hit_stop_loss = stop_pct.map(|stop| drawdown <= -stop);
+                    outcome.hit_take_profit = target_pct.map(|target| runup >= target);
+                }
+
+                (horizon.label.clone(), outcome)
+            })
+            .collect()
+    }
+
+    /// Simulate a trade entered at `price` and held until the stop, the target
+    /// or the longest horizon, whichever comes first
+    ///
+    /// Stops and targets are checked against candle lows/highs. When both are
+    /// reached within one candle the stop is assumed to come first, and a
+    /// candle opening beyond a level exits at its open.
+    ///
+    /// # Arguments
+    /// * `side` - Long or short
+    /// * `price` - Entry price
+    /// * `atr` - ATR used by ATR-multiple exit rules
+    /// * `candles` - Future candles as `(offset_ms, candle)`, offset of the candle close, ascending
+    /// * `elapsed_ms` - How far past the entry data exists
+    ///
+    /// # Returns
+    /// The trade result, or `None` while it is still open (or without candles)
+    pub fn simulate_trade(
+        &self,
+        side: TradeSide,
+        price: f64,
+        atr: f64,
+        candles: &[(i64, Candle)],
+        elapsed_ms: i64,
+    ) -> Option<TradeOutcome> {
+        let horizon_ms = self.max_horizon_ms();
+        let stop_pct = self.stop.distance_pct(price, atr);
+        let target_pct = self.target.distance_pct(price, atr);
+
+        let mut mae = 0.0f64;
+        let mut mfe = 0.0f64;
+        let mut last_close = None;
+
+        for (offset, candle) in candles.iter().take_while(|(offset, _)| *offset <= horizon_ms) {
+            let moves = side.moves(price, candle);
+
+            if let Some(stop) = stop_pct.filter(|stop| moves.worst <= -stop) {
+                let pnl_pct = moves.open.min(-stop);
+                return Some(TradeOutcome {
+                    exit_reason: ExitReason::StopLoss,
+                    exit_after_ms: *offset,
+                    pnl_pct,
+                    mae_pct: mae.min(pnl_pct),
+                    mfe_pct: mfe.max(moves.open),
+                });
+            }
+            if let Some(target) = target_pct.filter(|target| moves.best >= *target) {
+                let pnl_pct = moves.open.max(target);
+                return Some(TradeOutcome {
+                    exit_reason: ExitReason::TakeProfit,
+                    exit_after_ms: *offset,
+                    pnl_pct,
+                    mae_pct: mae.min(moves.worst),
+                    mfe_pct: mfe.max(pnl_pct),
+                });
+            }
+
+            mae = mae.min(moves.worst);
+            mfe = mfe.max(moves.best);
+            last_close = Some((*offset, moves.close));
+        }
+
+        if elapsed_ms < horizon_ms {
+            return None;
+        }
+        last_close.map(|(offset, pnl_pct)| TradeOutcome {
+            exit_reason: ExitReason::Horizon,
+            exit_after_ms: offset,
+            pnl_pct,
+            mae_pct: mae,
+            mfe_pct: mfe,
+        })
+    }
+}
+
+impl Default for OutcomeSpec {
+    /// 15m/1h/4h/24h horizons with a 2% stop and 3% target
+    fn default() -> Self {
+        Self::new(
+            vec![
+                OutcomeHorizon::minutes(15),
+                OutcomeHorizon::minutes(60),
+                OutcomeHorizon::minutes(4 * 60),
+                OutcomeHorizon::minutes(24 * 60),
+            ],
+            ExitRule::Percent(2.0),
+            ExitRule::Percent(3.0),
+        )
+    }
+}
+
+/// Result of one outcome horizon (all fields unset while pending)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct HorizonOutcome {
+    pub return_pct: Option<f64>,       // Price % change at the horizon
+    pub max_runup_pct: Option<f64>,    // Best move up to the horizon (%)
+    pub max_drawdown_pct: Option<f64>, // Worst move up to the horizon (%)
+    pub hit_stop_loss: Option<bool>,   // Did price reach the stop?
+    pub hit_take_profit: Option<bool>, // Did price reach the target?
+}
+
+/// Direction of a simulated trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Long,
+    Short,
+}
+
+impl TradeSide {
+    /// Candle moves relative to `price` in percent, positive in the trade's favour
+    fn moves(self, price: f64, candle: &Candle) -> CandleMoves {
+        let pct = |value: f64| (value - price) / price * 100.0;
+        match self {
+            TradeSide::Long => CandleMoves {
+                open: pct(candle.open),
+                best: pct(candle.high),
+                worst: pct(candle.low),
+                close: pct(candle.close),
+            },
+            TradeSide::Short => CandleMoves {
+                open: -pct(candle.open),
+                best: -pct(candle.low),
+                worst: -pct(candle.high),
+                close: -pct(candle.close),
+            },
+        }
+    }
+}
+
+/// One candle seen from a trade side
+struct CandleMoves {
+    open: f64,
+    best: f64,
+    worst: f64,
+    close: f64,
+}
+
+/// Why a simulated trade was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    StopLoss,
+    TakeProfit,
+    /// Neither level was reached before the longest horizon
+    Horizon,
+}
+
+/// Result of a simulated long or short trade
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TradeOutcome {
+    pub exit_reason: ExitReason,
+    pub exit_after_ms: i64, // Time from entry to exit
+    pub pnl_pct: f64,       // Realized PnL (%)
+    pub mae_pct: f64,       // Maximum adverse excursion (%, <= 0)
+    pub mfe_pct: f64,       // Maximum favourable excursion (%, >= 0)
+}
+
+impl TradeOutcome {
+    /// Whether the trade closed in profit
+    pub fn is_win(&self) -> bool {
+        self.pnl_pct > 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 60 * MINUTE_MS;
+
+    #[test]
+    fn test_parse_rules_and_horizons() {
+        assert_eq!("2%".parse::<ExitRule>().unwrap(), ExitRule::Percent(2.0));
+        assert_eq!("2.5".parse::<ExitRule>().unwrap(), ExitRule::Percent(2.5));
+        assert_eq!("1.5ATR".parse::<ExitRule>().unwrap(), ExitRule::AtrMultiple(1.5));
+        assert!("-1%".parse::<ExitRule>().is_err());
+        assert!("atr".parse::<ExitRule>().is_err());
+
+        let horizon: OutcomeHorizon = "8h".parse().unwrap();
+        assert_eq!(horizon.label, "8h");
+        assert_eq!(horizon.duration_ms, 8 * HOUR_MS);
+        assert_eq!("1d".parse::<OutcomeHorizon>().unwrap().label, "24h");
+        assert_eq!("90m".parse::<OutcomeHorizon>().unwrap().label, "90m");
+        assert!("0h".parse::<OutcomeHorizon>().is_err());
+        assert!("4".parse::<OutcomeHorizon>().is_err());
+    }
+
+    #[test]
+    fn test_evaluate_with_atr_stops() {
+        let spec = OutcomeSpec::default()
+            .with_horizons(vec!["8h".parse().unwrap(), "1h".parse().unwrap()])
+            .with_stop(ExitRule::AtrMultiple(1.0))
+            .with_target(ExitRule::AtrMultiple(2.0));
+        assert_eq!(spec.horizons[0].label, "1h");
+        assert_eq!(spec.max_horizon_ms(), 8 * HOUR_MS);
+
+        // ATR of 1000 on a 50000 entry: stop at -2%, target at +4%
+        let path = vec![
+            (HOUR_MS / 2, 49500.0),
+            (HOUR_MS, 50500.0),
+            (4 * HOUR_MS, 48900.0),
+            (8 * HOUR_MS, 52500.0),
+        ];
+
+        let outcomes = spec.evaluate(50000.0, 1000.0, &path, 8 * HOUR_MS);
+        let first = outcomes["1h"];
+        assert_eq!(first.return_pct, Some(1.0));
+        assert_eq!(first.max_drawdown_pct, Some(-1.0));
+        assert_eq!(first.hit_stop_loss, Some(false));
+
+        let last = outcomes["8h"];
+        assert_eq!(last.return_pct, Some(5.0));
+        assert_eq!(last.max_runup_pct, Some(5.0));
+        assert_eq!(last.hit_stop_loss, Some(true));
+        assert_eq!(last.hit_take_profit, Some(true));
+
+        // 8h not elapsed yet, and no ATR to size the exits
+        let pending = spec.evaluate(50000.0, 0.0, &path, 4 * HOUR_MS);
+        assert_eq!(pending["8h"], HorizonOutcome::default());
+        assert_eq!(pending["1h"].return_pct, Some(1.0));
+        assert_eq!(pending["1h"].hit_stop_loss, None);
+    }
+
+    #[test]
+    fn test_evaluate_with_gap_at_horizon() {
+        let spec = OutcomeSpec::default().with_horizons(vec!["1h".parse().unwrap(), "4h".parse().unwrap()]);
+
+        // The candles closing at 1h and 4h are missing
+        let path = vec![
+            (HOUR_MS - 6 * MINUTE_MS, 50500.0),
+            (2 * HOUR_MS, 49000.0),
+            (4 * HOUR_MS + 3 * MINUTE_MS, 52000.0),
+        ];
+
+        let outcomes = spec.evaluate(50000.0, 0.0, &path, 4 * HOUR_MS + 3 * MINUTE_MS);
+        // 6 minutes before the horizon is within the tolerance
+        assert_eq!(outcomes["1h"].return_pct, Some(1.0));
+        // The last close before 4h is 2h earlier: no return, but the path still counts
+        assert_eq!(outcomes["4h"].return_pct, None);
+        assert_eq!(outcomes["4h"].max_drawdown_pct, Some(-2.0));
+    }
+
+    fn candle(open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle { open, high, low, close, volume: 1.0, trades: 1 }
+    }
+
+    #[test]
+    fn test_simulate_long_and_short() {
+        let spec = OutcomeSpec::default().with_horizons(vec!["4h".parse().unwrap()]);
+
+        // Dips 1.5%, then rallies through +3% in the third hour
+        let candles = vec![
+            (HOUR_MS, candle(100.0, 100.5, 98.5, 99.0)),
+            (2 * HOUR_MS, candle(99.0, 101.0, 98.8, 100.8)),
+            (3 * HOUR_MS, candle(100.8, 103.5, 100.5, 103.2)),
+            (4 * HOUR_MS, candle(103.2, 104.0, 103.0, 103.8)),
+        ];
+
+        let long = spec.simulate_trade(TradeSide::Long, 100.0, 0.0, &candles, 4 * HOUR_MS).unwrap();
+        assert_eq!(long.exit_reason, ExitReason::TakeProfit);
+        assert_eq!(long.exit_after_ms, 3 * HOUR_MS);
+        assert!((long.pnl_pct - 3.0).abs() < 1e-9);
+        assert!((long.mae_pct + 1.5).abs() < 1e-9);
+        assert!(long.is_win());
+
+        // The short is stopped out by the same rally (stop 2% above entry)
+        let short = spec.simulate_trade(TradeSide::Short, 100.0, 0.0, &candles, 4 * HOUR_MS).unwrap();
+        assert_eq!(short.exit_reason, ExitReason::StopLoss);
+        assert!((short.pnl_pct + 2.0).abs() < 1e-9);
+        assert!((short.mfe_pct - 1.5).abs() < 1e-9);
+        assert!(!short.is_win());
+
+        // Exits are known early, but a time exit waits for the horizon
+        assert!(spec.simulate_trade(TradeSide::Long, 100.0, 0.0, &candles[..3], 3 * HOUR_MS).is_some());
+        assert!(spec.simulate_trade(TradeSide::Long, 100.0, 0.0, &candles[..2], 2 * HOUR_MS).is_none());
+
+        // Wide ATR levels: held to the horizon
+        let wide = spec
+            .clone()
+            .with_stop(ExitRule::AtrMultiple(5.0))
+            .with_target(ExitRule::AtrMultiple(5.0));
+        let held = wide.simulate_trade(TradeSide::Long, 100.0, 1.0, &candles, 4 * HOUR_MS).unwrap();
+        assert_eq!(held.exit_reason, ExitReason::Horizon);
+        assert!((held.pnl_pct - 3.8).abs() < 1e-9);
+        assert!((held.mfe_pct - 4.0).abs() < 1e-9);
+
+        // Gapping through the stop fills at the open
+        let gap = vec![(HOUR_MS, candle(97.0, 97.5, 96.0, 96.5))];
+        let stopped = spec.simulate_trade(TradeSide::Long, 100.0, 0.0, &gap, 4 * HOUR_MS).unwrap();
+        assert!((stopped.pnl_pct + 3.0).abs() < 1e-9);
+    }
+}
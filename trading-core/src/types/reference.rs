@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// Minimum number of paired returns for a meaningful correlation
+const MIN_CORRELATION_SAMPLES: usize = 10;
+
+/// State of a reference asset (usually BTCUSDT) at the time of a snapshot
+///
+/// Attached to snapshots of other symbols so their patterns can be matched
+/// in the same reference regime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceContext {
+    pub symbol: String,
+    #[serde(default)]
+    pub price_change_1h: Option<f64>, // % change (None when the 1h-old candle is missing)
+    #[serde(default)]
+    pub price_change_4h: Option<f64>, // % change (None when the 4h-old candle is missing)
+    pub rsi_14: f64,                  // 3m RSI(14) of the reference
+    pub correlation_4h: Option<f64>, // Correlation of 3m returns over the last 4h
+}
+
+impl ReferenceContext {
+    /// Pearson correlation of two equally long return series
+    ///
+    /// # Returns
+    /// `None` with fewer than 10 pairs, mismatched lengths or a flat series
+    pub fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+        if xs.len() != ys.len() || xs.len() < MIN_CORRELATION_SAMPLES {
+            return None;
+        }
+
+        let n = xs.len() as f64;
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = ys.iter().sum::<f64>() / n;
+
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for (x, y) in xs.iter().zip(ys) {
+            let (dx, dy) = (x - mean_x, y - mean_y);
+            cov += dx * dy;
+            var_x += dx * dx;
+            var_y += dy * dy;
+        }
+
+        if var_x < 1e-20 || var_y < 1e-20 {
+            return None;
+        }
+        Some((cov / (var_x.sqrt() * var_y.sqrt())).clamp(-1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlation() {
+        let xs: Vec<f64> = (0..20).map(|i| (i as f64 * 0.7).sin()).collect();
+        let doubled: Vec<f64> = xs.iter().map(|x| 2.0 * x + 0.1).collect();
+        let inverted: Vec<f64> = xs.iter().map(|x| -x).collect();
+
+        assert!((ReferenceContext::correlation(&xs, &doubled).unwrap() - 1.0).abs() < 1e-9);
+        assert!((ReferenceContext::correlation(&xs, &inverted).unwrap() + 1.0).abs() < 1e-9);
+        assert_eq!(ReferenceContext::correlation(&xs[..5], &doubled[..5]), None);
+        assert_eq!(ReferenceContext::correlation(&xs, &[0.0; 20]), None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::indicators::Indicators4hCalculator;
+use crate::types::market_data::Candle;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Candle timeframe, labelled as in the llm-trader-data databases (`candles_{label}`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Timeframe {
+    #[serde(rename = "3m")]
+    M3,
+    #[serde(rename = "15m")]
+    M15,
+    #[serde(rename = "1h")]
+    H1,
+    #[serde(rename = "4h")]
+    H4,
+    #[serde(rename = "1d")]
+    D1,
+}
+
+impl Timeframe {
+    /// Every timeframe, shortest first
+    pub const ALL: [Timeframe; 5] = [
+        Timeframe::M3,
+        Timeframe::M15,
+        Timeframe::H1,
+        Timeframe::H4,
+        Timeframe::D1,
+    ];
+
+    /// Short label, e.g. "3m" or "1d"
+    pub fn label(self) -> &'static str {
+        match self {
+            Timeframe::M3 => "3m",
+            Timeframe::M15 => "15m",
+            Timeframe::H1 => "1h",
+            Timeframe::H4 => "4h",
+            Timeframe::D1 => "1d",
+        }
+    }
+
+    /// Candle length in milliseconds
+    pub fn duration_ms(self) -> i64 {
+        match self {
+            Timeframe::M3 => 3 * MINUTE_MS,
+            Timeframe::M15 => 15 * MINUTE_MS,
+            Timeframe::H1 => 60 * MINUTE_MS,
+            Timeframe::H4 => 4 * 60 * MINUTE_MS,
+            Timeframe::D1 => 24 * 60 * MINUTE_MS,
+        }
+    }
+
+    /// Name of the LMDB database holding candles of this timeframe
+    pub fn candles_db(self) -> &'static str {
+        match self {
+            Timeframe::M3 => "candles_3m",
+            Timeframe::M15 => "candles_15m",
+            Timeframe::H1 => "candles_1h",
+            Timeframe::H4 => "candles_4h",
+            Timeframe::D1 => "candles_1d",
+        }
+    }
+}
+
+impl FromStr for Timeframe {
+    type Err = anyhow::Error;
+
+    /// Parse a label such as "1h" or "1d"
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        Timeframe::ALL
+            .into_iter()
+            .find(|tf| tf.label() == s)
+            .ok_or_else(|| anyhow!("Invalid timeframe '{}'. Must be one of 3m, 15m, 1h, 4h, 1d", s))
+    }
+}
+
+impl fmt::Display for Timeframe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Higher timeframe block of a snapshot (1h, 1d)
+///
+/// Indicators are those of the last closed candle of the timeframe, and the
+/// prior high/low/close are that candle's (the prior day's on 1d).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeframeContext {
+    pub ema_20: f64,
+    pub ema_50: f64,
+    pub macd: f64,
+    pub rsi_14: f64,
+    pub atr_14: f64,
+    pub prev_high: f64,
+    pub prev_low: f64,
+    pub prev_close: f64,
+}
+
+impl TimeframeContext {
+    /// Context after each candle of an ordered series, from the first seeded candle on
+    ///
+    /// Entries are keyed by the candle's open time.
+    pub fn compute(candles: &[(i64, Candle)]) -> Vec<(i64, TimeframeContext)> {
+        let mut calculator = Indicators4hCalculator::new();
+        candles
+            .iter()
+            .filter_map(|(ts, candle)| {
+                let indicators = calculator.update(candle)?;
+                Some((
+                    *ts,
+                    TimeframeContext {
+                        ema_20: indicators.ema_20,
+                        ema_50: indicators.ema_50,
+                        macd: indicators.macd,
+                        rsi_14: indicators.rsi_14,
+                        atr_14: indicators.atr_14,
+                        prev_high: candle.high,
+                        prev_low: candle.low,
+                        prev_close: candle.close,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// EMA(20)/EMA(50) ratio (1.0 when EMA(50) is zero)
+    pub fn ema_ratio(&self) -> f64 {
+        if self.ema_50.abs() > 1e-10 {
+            self.ema_20 / self.ema_50
+        } else {
+            1.0
+        }
+    }
+
+    /// Trend from the EMA ratio: "uptrend", "downtrend" or "sideways"
+    pub fn trend(&self) -> &'static str {
+        let ratio = self.ema_ratio();
+        if ratio > 1.005 {
+            "uptrend"
+        } else if ratio < 0.995 {
+            "downtrend"
+        } else {
+            "sideways"
+        }
+    }
+
+    /// Where a price sits relative to the two EMAs
+    pub fn ema_position(&self, price: f64) -> &'static str {
+        match (price > self.ema_20, price > self.ema_50) {
+            (true, true) => "above EMA20 and EMA50",
+            (false, false) => "below EMA20 and EMA50",
+            _ => "between EMA20 and EMA50",
+        }
+    }
+
+    /// % distance of a price from EMA(20)
+    pub fn pct_from_ema_20(&self, price: f64) -> f64 {
+        if self.ema_20.abs() > 1e-10 {
+            (price - self.ema_20) / self.ema_20 * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64) -> Candle {
+        Candle { open: close, high: close + 1.0, low: close - 1.0, close, volume: 1.0, trades: 1 }
+    }
+
+    #[test]
+    fn test_timeframe_labels_round_trip() {
+        for tf in Timeframe::ALL {
+            assert_eq!(tf.label().parse::<Timeframe>().unwrap(), tf);
+            assert_eq!(serde_json::to_string(&tf).unwrap(), format!("\"{}\"", tf.label()));
+        }
+        assert_eq!(Timeframe::D1.duration_ms(), 24 * 60 * 60 * 1000);
+        assert_eq!(Timeframe::H1.candles_db(), "candles_1h");
+        assert!("2h".parse::<Timeframe>().is_err());
+    }
+
+    #[test]
+    fn test_context_from_rising_candles() {
+        let candles: Vec<(i64, Candle)> = (0..60).map(|i| (i, candle(100.0 + i as f64))).collect();
+        let contexts = TimeframeContext::compute(&candles);
+        let (ts, last) = *contexts.last().unwrap();
+
+        assert_eq!(ts, 59);
+        assert_eq!(last.prev_high, 160.0);
+        assert_eq!(last.prev_low, 158.0);
+        assert_eq!(last.trend(), "uptrend");
+        assert_eq!(last.ema_position(last.prev_close), "above EMA20 and EMA50");
+        assert!(last.pct_from_ema_20(last.prev_close) > 0.0);
+        assert!(last.rsi_14 > 70.0);
+    }
+}
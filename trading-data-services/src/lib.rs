@@ -1,6 +1,12 @@
+// The snapshot payload json! literal exceeds the default macro recursion limit
+#![recursion_limit = "256"]
+
 pub mod rag;
 
 // Re-export commonly used items
 pub use rag::{
-    HistoricalIngestionPipeline, HistoricalSnapshotExtractor, SnapshotFormatter, VectorStore,
+    CsvDataSource, EmbedderKind, FeatureSnapshotEmbedder, FeatureVersion, GapFillPolicy, HistoricalIngestionPipeline,
+    HistoricalSnapshotExtractor, IngestCheckpoint, InMemoryDataSource, LiveSnapshotBuilder, LmdbReader, MarketDataSource, MockDataSource,
+    SnapshotEmbedder, SnapshotFormatter, SnapshotValidator, TextSnapshotEmbedder, ValidationRule, VectorStore, CURRENT_FEATURE_VERSION,
+    CURRENT_SCHEMA_VERSION,
 };
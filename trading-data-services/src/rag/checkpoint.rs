@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use trading_core::TimestampMS;
+
+/// Last snapshot timestamp committed to Qdrant per symbol, persisted as JSON
+///
+/// The ingestion pipeline commits after each upserted batch, so an
+/// interrupted run can resume after the last committed snapshot instead of
+/// starting the range over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestCheckpoint {
+    #[serde(skip)]
+    path: PathBuf,
+    collection: String,
+    symbols: BTreeMap<String, TimestampMS>,
+}
+
+impl IngestCheckpoint {
+    /// Load the checkpoint of a collection, or start an empty one if the file does not exist
+    ///
+    /// Fails if the file belongs to another collection.
+    pub fn load(path: impl Into<PathBuf>, collection: &str) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(Self {
+                path,
+                collection: collection.to_string(),
+                symbols: BTreeMap::new(),
+            });
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        let mut checkpoint: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid checkpoint {}", path.display()))?;
+        if checkpoint.collection != collection {
+            return Err(anyhow!(
+                "Checkpoint {} is for collection {}, not {}",
+                path.display(),
+                checkpoint.collection,
+                collection
+            ));
+        }
+        checkpoint.path = path;
+        Ok(checkpoint)
+    }
+
+    /// File the checkpoint is saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Timestamp of the last snapshot committed for a symbol
+    pub fn last_committed(&self, symbol: &str) -> Option<TimestampMS> {
+        self.symbols.get(symbol).copied()
+    }
+
+    /// Record that every snapshot of a symbol up to `timestamp` is stored, and save
+    ///
+    /// The checkpoint only moves forward: committing an earlier timestamp (e.g.
+    /// re-running an earlier range) keeps the later one. The file is replaced
+    /// atomically, so a crash never leaves it half written.
+    pub fn commit(&mut self, symbol: &str, timestamp: TimestampMS) -> Result<()> {
+        let committed = self.symbols.entry(symbol.to_string()).or_insert(timestamp);
+        *committed = (*committed).max(timestamp);
+
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write checkpoint {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace checkpoint {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let path = std::env::temp_dir().join(format!("rag_checkpoint_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut checkpoint = IngestCheckpoint::load(&path, "trading_patterns").unwrap();
+        assert_eq!(checkpoint.last_committed("BTCUSDT"), None);
+        checkpoint.commit("BTCUSDT", 1_000).unwrap();
+        checkpoint.commit("BTCUSDT", 2_000).unwrap();
+        checkpoint.commit("ETHUSDT", 1_500).unwrap();
+
+        let loaded = IngestCheckpoint::load(&path, "trading_patterns").unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.last_committed("BTCUSDT"), Some(2_000));
+        assert_eq!(loaded.last_committed("ETHUSDT"), Some(1_500));
+
+        // Re-running an earlier range never moves the checkpoint backwards
+        checkpoint.commit("BTCUSDT", 500).unwrap();
+        assert_eq!(checkpoint.last_committed("BTCUSDT"), Some(2_000));
+        let loaded = IngestCheckpoint::load(&path, "trading_patterns").unwrap();
+        assert_eq!(loaded.last_committed("BTCUSDT"), Some(2_000));
+
+        let err = IngestCheckpoint::load(&path, "other_patterns").unwrap_err();
+        assert!(err.to_string().contains("is for collection trading_patterns"), "{}", err);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,638 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use trading_core::indicators::Indicators3mCalculator;
+use trading_core::{Candle, Indicators3m, Indicators4h, Timeframe};
+use tracing;
+
+use super::market_data_source::{indicators_4h_as_of, InMemoryDataSource, MarketDataSource};
+
+/// Higher timeframes loaded for the snapshot's 1h/1d blocks
+const CONTEXT_TIMEFRAMES: [Timeframe; 2] = [Timeframe::H1, Timeframe::D1];
+
+/// Timeframes of the candle files read for a symbol
+const FILE_TIMEFRAMES: [&str; 4] = ["3m", "1h", "4h", "1d"];
+
+/// Path, modification time and size of a candle file
+#[derive(Debug, Clone, PartialEq)]
+struct FileStamp {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Loaded data of a symbol and the files it was loaded from
+struct SymbolCache {
+    files: Vec<FileStamp>,
+    data: Arc<InMemoryDataSource>,
+}
+
+/// Cache slot of one symbol, locked while the symbol loads
+type SymbolSlot = Arc<Mutex<Option<SymbolCache>>>;
+
+/// Candle data read from exchange-style OHLCV CSV files
+///
+/// Files are found anywhere under the candles directory and matched by name:
+/// `{SYMBOL}-{timeframe}.csv` or `{SYMBOL}-{timeframe}-{suffix}.csv` (timeframe
+/// 3m, 1h, 4h or 1d), which covers the
+/// Binance archive layout (e.g. `BTCUSDT-3m-2025-01.csv`). Rows may be
+/// headerless in Binance column order (open_time, open, high, low, close,
+/// volume, close_time, quote_volume, count, ...) or start with a header row
+/// naming the columns. Timestamps in seconds or microseconds are converted to
+/// milliseconds.
+///
+/// Indicators are computed from the candles when a symbol is first read:
+/// - 3m indicators at each 3m candle once every indicator is warmed up
+/// - 4h indicators at each 3m candle, from the last 4h candle that had closed
+///   by the end of that 3m candle (no lookahead)
+///
+/// A symbol is reloaded when any of its files is added, removed or changes
+/// modification time or size, so a follow run sees newly appended candles.
+/// Symbols load independently: a worker loading one symbol never blocks
+/// workers reading another.
+///
+/// 1h, 4h and 1d candles are resampled from the 3m candles when no files of
+/// that timeframe exist.
+/// Open interest and funding are not available from candle files. Parquet
+/// files are not read (they were an optional format); convert them to CSV.
+pub struct CsvDataSource {
+    candles_dir: PathBuf,
+    symbols: Mutex<HashMap<String, SymbolSlot>>,
+}
+
+impl CsvDataSource {
+    /// Create a CSV data source
+    ///
+    /// # Arguments
+    /// * `candles_dir` - Directory containing the candle CSV files
+    ///
+    /// # Returns
+    /// Data source that loads symbols lazily on first access
+    pub fn new(candles_dir: impl AsRef<Path>) -> Result<Self> {
+        let candles_dir = candles_dir.as_ref().to_path_buf();
+        if !candles_dir.is_dir() {
+            return Err(anyhow!(
+                "Candles directory does not exist: {}",
+                candles_dir.display()
+            ));
+        }
+
+        Ok(Self {
+            candles_dir,
+            symbols: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Data for a symbol, loading it on first access and after its files change
+    ///
+    /// Only the symbol's own slot stays locked while it loads.
+    fn symbol_data(&self, symbol: &str) -> Result<Arc<InMemoryDataSource>> {
+        let slot = {
+            let mut symbols = self
+                .symbols
+                .lock()
+                .map_err(|_| anyhow!("CSV data source cache is poisoned"))?;
+            Arc::clone(symbols.entry(symbol.to_string()).or_default())
+        };
+        let mut cached = slot
+            .lock()
+            .map_err(|_| anyhow!("CSV data source cache for {} is poisoned", symbol))?;
+
+        // Stamped before loading, so a file written during the load is seen next time
+        let files = self.file_stamps(symbol)?;
+        if let Some(cache) = cached.as_ref() {
+            if cache.files == files {
+                return Ok(Arc::clone(&cache.data));
+            }
+            tracing::info!("Candle files of {} changed, reloading", symbol);
+        }
+
+        let data = Arc::new(self.load_symbol(symbol)?);
+        *cached = Some(SymbolCache {
+            files,
+            data: Arc::clone(&data),
+        });
+        Ok(data)
+    }
+
+    /// Stamps of every candle file of a symbol, sorted by path
+    fn file_stamps(&self, symbol: &str) -> Result<Vec<FileStamp>> {
+        let mut files = Vec::new();
+        for timeframe in FILE_TIMEFRAMES {
+            find_candle_files(&self.candles_dir, symbol, timeframe, &mut files)?;
+        }
+        files.sort();
+
+        files
+            .into_iter()
+            .map(|path| {
+                let metadata = fs::metadata(&path)
+                    .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+                Ok(FileStamp {
+                    modified: metadata.modified().ok(),
+                    len: metadata.len(),
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// Read a symbol's candle files and compute its indicators
+    fn load_symbol(&self, symbol: &str) -> Result<InMemoryDataSource> {
+        let candles_3m = self.read_candles(symbol, "3m")?;
+        if candles_3m.is_empty() {
+            return Err(anyhow!(
+                "No 3m candle files for {} in {}",
+                symbol,
+                self.candles_dir.display()
+            ));
+        }
+
+        let mut candles_4h = self.read_candles(symbol, "4h")?;
+        if candles_4h.is_empty() {
+            tracing::warn!("No 4h candle files for {}, resampling from 3m candles", symbol);
+            candles_4h = resample(&candles_3m, Timeframe::H4);
+        }
+
+        let mut data = InMemoryDataSource::new();
+        for timeframe in CONTEXT_TIMEFRAMES {
+            let mut candles = self.read_candles(symbol, timeframe.label())?;
+            if candles.is_empty() {
+                tracing::debug!("No {} candle files for {}, resampling from 3m candles", timeframe, symbol);
+                candles = resample(&candles_3m, timeframe);
+            }
+            for (ts, candle) in candles {
+                data.insert_candle(symbol, timeframe, ts, candle);
+            }
+        }
+
+        let candles_3m: Vec<(i64, Candle)> = candles_3m.into_iter().collect();
+        let candles_4h: Vec<(i64, Candle)> = candles_4h.into_iter().collect();
+
+        for (ts, candle) in &candles_3m {
+            data.insert_candle_3m(symbol, *ts, *candle);
+        }
+        for (ts, candle) in &candles_4h {
+            data.insert_candle_4h(symbol, *ts, *candle);
+        }
+        for (ts, indicators) in Indicators3mCalculator::compute(&candles_3m) {
+            data.insert_indicators_3m(symbol, ts, indicators);
+        }
+        for (ts, indicators) in indicators_4h_as_of(&candles_4h, candles_3m.iter().map(|(ts, _)| *ts)) {
+            data.insert_indicators_4h(symbol, ts, indicators);
+        }
+
+        tracing::info!(
+            "Loaded {} 3m and {} 4h candles for {} from {}",
+            candles_3m.len(),
+            candles_4h.len(),
+            symbol,
+            self.candles_dir.display()
+        );
+
+        Ok(data)
+    }
+
+    /// Read all candle files of one timeframe for a symbol, keyed by open time
+    ///
+    /// Files are read in path order; a candle repeated across files keeps the
+    /// last value read.
+    fn read_candles(&self, symbol: &str, timeframe: &str) -> Result<BTreeMap<i64, Candle>> {
+        let mut files = Vec::new();
+        find_candle_files(&self.candles_dir, symbol, timeframe, &mut files)?;
+        files.sort();
+
+        let mut candles = BTreeMap::new();
+        for file in files {
+            let contents = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            candles.extend(
+                parse_candles(&contents)
+                    .with_context(|| format!("Invalid candle file {}", file.display()))?,
+            );
+        }
+
+        Ok(candles)
+    }
+}
+
+impl MarketDataSource for CsvDataSource {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn candles_3m(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        self.symbol_data(symbol)?.candles_3m(symbol, start_ms, end_ms)
+    }
+
+    fn candles_4h(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        self.symbol_data(symbol)?.candles_4h(symbol, start_ms, end_ms)
+    }
+
+    fn candles(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Candle)>> {
+        self.symbol_data(symbol)?.candles(symbol, timeframe, start_ms, end_ms)
+    }
+
+    fn indicators_3m(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators3m)>> {
+        self.symbol_data(symbol)?.indicators_3m(symbol, start_ms, end_ms)
+    }
+
+    fn indicators_4h(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators4h)>> {
+        self.symbol_data(symbol)?.indicators_4h(symbol, start_ms, end_ms)
+    }
+}
+
+/// Recursively collect the CSV files for a symbol and timeframe
+fn find_candle_files(dir: &Path, symbol: &str, timeframe: &str, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to list {}", dir.display()))?;
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_candle_files(&path, symbol, timeframe, files)?;
+        } else if is_candle_file(&path, symbol, timeframe) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a file name is `{symbol}-{timeframe}[-...].csv`
+fn is_candle_file(path: &Path, symbol: &str, timeframe: &str) -> bool {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+
+    let mut parts = stem.splitn(3, '-');
+    is_csv
+        && parts.next().is_some_and(|s| s.eq_ignore_ascii_case(symbol))
+        && parts.next() == Some(timeframe)
+}
+
+/// Column positions of the fields we read
+struct Columns {
+    open_time: usize,
+    open: usize,
+    high: usize,
+    low: usize,
+    close: usize,
+    volume: usize,
+    trades: Option<usize>,
+}
+
+impl Columns {
+    /// Binance kline column order
+    const BINANCE: Columns = Columns {
+        open_time: 0,
+        open: 1,
+        high: 2,
+        low: 3,
+        close: 4,
+        volume: 5,
+        trades: Some(8),
+    };
+
+    /// Locate columns from a header row
+    fn from_header(fields: &[&str]) -> Result<Self> {
+        let find = |names: &[&str]| {
+            fields
+                .iter()
+                .position(|field| names.iter().any(|name| field.eq_ignore_ascii_case(name)))
+        };
+        let require = |names: &[&str]| {
+            find(names).ok_or_else(|| anyhow!("Missing column '{}' in header", names[0]))
+        };
+
+        Ok(Self {
+            open_time: require(&["open_time", "timestamp", "time"])?,
+            open: require(&["open"])?,
+            high: require(&["high"])?,
+            low: require(&["low"])?,
+            close: require(&["close"])?,
+            volume: require(&["volume"])?,
+            trades: find(&["count", "trades", "number_of_trades"]),
+        })
+    }
+}
+
+/// Parse CSV candle rows into candles keyed by open time (milliseconds)
+fn parse_candles(contents: &str) -> Result<Vec<(i64, Candle)>> {
+    let mut columns = None;
+    let mut candles = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim().trim_matches('"')).collect();
+        let columns = match &columns {
+            Some(columns) => columns,
+            None => {
+                // A header row has a non-numeric first field
+                let header = fields[0].parse::<f64>().is_err();
+                let parsed = if header {
+                    Columns::from_header(&fields).with_context(|| format!("line {}", index + 1))?
+                } else {
+                    Columns::BINANCE
+                };
+                columns = Some(parsed);
+                if header {
+                    continue;
+                }
+                columns.as_ref().unwrap()
+            }
+        };
+
+        let candle = parse_row(&fields, columns).with_context(|| format!("line {}", index + 1))?;
+        candles.push(candle);
+    }
+
+    Ok(candles)
+}
+
+/// Parse one data row
+fn parse_row(fields: &[&str], columns: &Columns) -> Result<(i64, Candle)> {
+    let field = |column: usize, name: &str| {
+        fields
+            .get(column)
+            .copied()
+            .ok_or_else(|| anyhow!("Missing {} field", name))
+    };
+    let number = |column: usize, name: &str| -> Result<f64> {
+        let value = field(column, name)?;
+        value
+            .parse::<f64>()
+            .map_err(|_| anyhow!("Invalid {} value '{}'", name, value))
+    };
+
+    let open_time = field(columns.open_time, "open_time")?;
+    let open_time = open_time
+        .parse::<i64>()
+        .map_err(|_| anyhow!("Invalid open_time value '{}'", open_time))?;
+
+    let trades = match columns.trades {
+        Some(column) if column < fields.len() => number(column, "trades")? as u64,
+        _ => 0,
+    };
+
+    Ok((
+        to_millis(open_time),
+        Candle {
+            open: number(columns.open, "open")?,
+            high: number(columns.high, "high")?,
+            low: number(columns.low, "low")?,
+            close: number(columns.close, "close")?,
+            volume: number(columns.volume, "volume")?,
+            trades,
+        },
+    ))
+}
+
+/// Normalize a timestamp in seconds, milliseconds or microseconds to milliseconds
+fn to_millis(timestamp: i64) -> i64 {
+    if timestamp >= 100_000_000_000_000 {
+        timestamp / 1000
+    } else if timestamp < 100_000_000_000 {
+        timestamp * 1000
+    } else {
+        timestamp
+    }
+}
+
+/// Aggregate 3m candles into epoch-aligned candles of a longer timeframe
+///
+/// Only complete buckets are kept: a bucket missing any of its 3m candles
+/// (the partial first and last buckets of a file, the still open current
+/// bucket, or a gap in the data) would otherwise pass for a closed candle.
+fn resample(candles_3m: &BTreeMap<i64, Candle>, timeframe: Timeframe) -> BTreeMap<i64, Candle> {
+    let interval_ms = timeframe.duration_ms();
+    let expected_count = (interval_ms / Timeframe::M3.duration_ms()) as usize;
+    let mut resampled: BTreeMap<i64, (Candle, usize)> = BTreeMap::new();
+
+    for (ts, candle) in candles_3m {
+        let bucket = ts.div_euclid(interval_ms) * interval_ms;
+        resampled
+            .entry(bucket)
+            .and_modify(|(agg, count)| {
+                agg.high = agg.high.max(candle.high);
+                agg.low = agg.low.min(candle.low);
+                agg.close = candle.close;
+                agg.volume += candle.volume;
+                agg.trades += candle.trades;
+                *count += 1;
+            })
+            .or_insert((*candle, 1));
+    }
+
+    resampled
+        .into_iter()
+        .filter(|(_, (_, count))| *count == expected_count)
+        .map(|(bucket, (candle, _))| (bucket, candle))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trading_core::indicators::Indicators4hCalculator;
+    use crate::rag::snapshot_extractor::HistoricalSnapshotExtractor;
+    use std::fmt::Write as _;
+
+    const INTERVAL_3M_MS: i64 = 180_000;
+    const FOUR_HOURS_MS: i64 = 4 * 60 * 60 * 1000;
+    const BASE_TS: i64 = 1_700_006_400_000; // 4h boundary
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rag_csv_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn price(i: i64) -> f64 {
+        100.0 + (i as f64 * 0.37).sin() * 5.0 + i as f64 * 0.01
+    }
+
+    /// Headerless Binance rows starting at `start_ts`
+    fn binance_rows(start_ts: i64, interval_ms: i64, count: i64) -> String {
+        let mut rows = String::new();
+        for i in 0..count {
+            let open_time = start_ts + i * interval_ms;
+            let (open, close) = (price(i), price(i + 1));
+            writeln!(
+                rows,
+                "{},{},{},{},{},10.5,{},1000.0,42,5.0,500.0,0",
+                open_time,
+                open,
+                open.max(close) + 0.5,
+                open.min(close) - 0.5,
+                close,
+                open_time + interval_ms - 1
+            )
+            .unwrap();
+        }
+        rows
+    }
+
+    #[test]
+    fn test_parse_header_and_binance_rows() {
+        let header = "timestamp,open,high,low,close,volume\n1700000000,1,2,0.5,1.5,10\n";
+        let candles = parse_candles(header).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].0, 1_700_000_000_000);
+        assert_eq!(candles[0].1.close, 1.5);
+        assert_eq!(candles[0].1.trades, 0);
+
+        // Binance spot archives use microseconds since 2025
+        let binance = "1735689600000000,1,2,0.5,1.5,10,1735689779999999,15,7,5,7.5,0\n";
+        let candles = parse_candles(binance).unwrap();
+        assert_eq!(candles[0].0, 1_735_689_600_000);
+        assert_eq!(candles[0].1.trades, 7);
+
+        let err = parse_candles("1700000000000,1,2,x,1.5,10\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("line 1: Invalid low value 'x'"));
+    }
+
+    #[test]
+    fn test_indicators_from_candle_files() {
+        let dir = temp_dir("indicators");
+        fs::create_dir_all(dir.join("monthly")).unwrap();
+
+        // 60 closed 4h candles, then 3m candles starting where they end
+        let start_3m = BASE_TS + 60 * FOUR_HOURS_MS;
+        fs::write(
+            dir.join("BTCUSDT-4h.csv"),
+            format!("open_time,open,high,low,close,volume\n{}", binance_rows(BASE_TS, FOUR_HOURS_MS, 60)),
+        )
+        .unwrap();
+        fs::write(dir.join("monthly/BTCUSDT-3m-2023-11.csv"), binance_rows(start_3m, INTERVAL_3M_MS, 60)).unwrap();
+        fs::write(dir.join("ETHUSDT-3m.csv"), "not,a,candle\n").unwrap();
+
+        let source = CsvDataSource::new(&dir).unwrap();
+        let indicators_3m = source.indicators_3m("BTCUSDT", 0, i64::MAX).unwrap();
+        assert_eq!(indicators_3m.len(), 11);
+        assert_eq!(indicators_3m[0].0, start_3m + 49 * INTERVAL_3M_MS);
+
+        // Every 3m candle sees the 4h candle that closed at start_3m
+        let mut calculator = Indicators4hCalculator::new();
+        let expected = (0..60)
+            .filter_map(|i| {
+                let (open, close) = (price(i), price(i + 1));
+                calculator.update(&Candle {
+                    open,
+                    high: open.max(close) + 0.5,
+                    low: open.min(close) - 0.5,
+                    close,
+                    volume: 10.5,
+                    trades: 0,
+                })
+            })
+            .last()
+            .unwrap();
+        let indicators_4h = source.indicators_4h("BTCUSDT", start_3m, start_3m + 59 * INTERVAL_3M_MS).unwrap();
+        assert_eq!(indicators_4h.len(), 60);
+        assert!(indicators_4h.iter().all(|(_, ind)| *ind == expected));
+
+        // Snapshots build from the computed indicators
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+        let last_ts = (start_3m + 59 * INTERVAL_3M_MS) as u64;
+        let snapshots = extractor.extract_snapshots("BTCUSDT", last_ts, last_ts + 1, 3).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].ema_20_4h, expected.ema_20);
+        let source = extractor.source();
+
+        assert!(source.candles_3m("SOLUSDT", 0, i64::MAX).is_err());
+        assert!(source.candles_3m("ETHUSDT", 0, i64::MAX).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reloads_symbol_when_files_change() {
+        let dir = temp_dir("reload");
+        fs::write(dir.join("BTCUSDT-3m-1.csv"), binance_rows(BASE_TS, INTERVAL_3M_MS, 60)).unwrap();
+
+        let source = CsvDataSource::new(&dir).unwrap();
+        assert_eq!(source.candles_3m("BTCUSDT", 0, i64::MAX).unwrap().len(), 60);
+
+        // Appended candles and new files are picked up on the next read
+        fs::write(dir.join("BTCUSDT-3m-1.csv"), binance_rows(BASE_TS, INTERVAL_3M_MS, 70)).unwrap();
+        assert_eq!(source.candles_3m("BTCUSDT", 0, i64::MAX).unwrap().len(), 70);
+        fs::write(
+            dir.join("BTCUSDT-3m-2.csv"),
+            binance_rows(BASE_TS + 70 * INTERVAL_3M_MS, INTERVAL_3M_MS, 10),
+        )
+        .unwrap();
+        assert_eq!(source.candles_3m("BTCUSDT", 0, i64::MAX).unwrap().len(), 80);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resample_4h_aggregates_buckets() {
+        let mut candles_3m = BTreeMap::new();
+        for i in 0..160 {
+            let c = price(i);
+            candles_3m.insert(BASE_TS + i * INTERVAL_3M_MS, Candle { open: c, high: c + 1.0, low: c - 1.0, close: c, volume: 1.0, trades: 2 });
+        }
+
+        let candles_4h = resample(&candles_3m, Timeframe::H4);
+        assert_eq!(candles_4h.len(), 2);
+        let first = candles_4h[&BASE_TS];
+        assert_eq!(first.open, price(0));
+        assert_eq!(first.close, price(79));
+        assert_eq!(first.volume, 80.0);
+        assert_eq!(first.trades, 160);
+
+        // Same bucketing for the 1h context candles
+        let candles_1h = resample(&candles_3m, Timeframe::H1);
+        assert_eq!(candles_1h.len(), 8);
+        assert_eq!(candles_1h[&BASE_TS].close, price(19));
+    }
+
+    #[test]
+    fn test_resample_drops_partial_buckets() {
+        // Starts 10 candles before BASE_TS and ends 5 candles into the third 4h bucket
+        let mut candles_3m = BTreeMap::new();
+        for i in -10..165 {
+            let c = price(i);
+            candles_3m.insert(BASE_TS + i * INTERVAL_3M_MS, Candle { open: c, high: c + 1.0, low: c - 1.0, close: c, volume: 1.0, trades: 2 });
+        }
+        // A gap in the second 4h bucket
+        candles_3m.remove(&(BASE_TS + 100 * INTERVAL_3M_MS));
+
+        let candles_4h = resample(&candles_3m, Timeframe::H4);
+        assert_eq!(candles_4h.keys().copied().collect::<Vec<_>>(), vec![BASE_TS]);
+        assert_eq!(candles_4h[&BASE_TS].volume, 80.0);
+
+        // 8 complete hours, minus the one holding the gap
+        let candles_1h = resample(&candles_3m, Timeframe::H1);
+        assert_eq!(candles_1h.len(), 7);
+        assert!(!candles_1h.contains_key(&(BASE_TS + 5 * 60 * 60 * 1000)));
+    }
+}
@@ -0,0 +1,485 @@
+use anyhow::{anyhow, Context, Result};
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use trading_core::{FeatureSet, MarketStateSnapshot};
+
+use super::schema::{EMBEDDING_DIM, EMBEDDING_MODEL};
+use super::snapshot_formatter::SnapshotFormatter;
+
+/// Model name of the numeric feature embedder (bump when components change)
+pub const FEATURE_EMBEDDING_MODEL: &str = "snapshot-features-v1";
+
+/// Normalised components are clamped to this many scale units
+const MAX_COMPONENT: f64 = 3.0;
+
+/// How snapshots are turned into vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbedderKind {
+    /// BGE-small embedding of the natural language description
+    #[default]
+    Text,
+    /// Weighted, normalised vector of numeric snapshot features
+    Features,
+}
+
+impl EmbedderKind {
+    /// Every embedder kind
+    pub const ALL: [EmbedderKind; 2] = [EmbedderKind::Text, EmbedderKind::Features];
+
+    /// Label stored in collection metadata, e.g. "features"
+    pub fn label(self) -> &'static str {
+        match self {
+            EmbedderKind::Text => "text",
+            EmbedderKind::Features => "features",
+        }
+    }
+}
+
+impl FromStr for EmbedderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "features" => Ok(Self::Features),
+            _ => Err(anyhow!("Invalid embedder '{}'. Must be 'text' or 'features'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for EmbedderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Turns snapshots into vectors for storage and similarity search
+///
+/// The same embedder must be used to ingest a collection and to query it;
+/// `metadata()` is stored with the collection so retrieval can rebuild it.
+pub trait SnapshotEmbedder: Send + Sync {
+    /// Which kind of embedder this is
+    fn kind(&self) -> EmbedderKind;
+
+    /// Model name stored with every point (e.g. "bge-small-en-v1.5")
+    fn model_name(&self) -> &str;
+
+    /// Length of the produced vectors
+    fn dimension(&self) -> usize;
+
+    /// Model name plus any settings that change the vectors
+    ///
+    /// Part of every point's content hash, so changing a setting re-embeds
+    /// the points instead of skipping them as unchanged.
+    fn identity(&self) -> String {
+        self.model_name().to_string()
+    }
+
+    /// Embed a batch of snapshots, one vector per snapshot
+    fn embed(&self, snapshots: &[MarketStateSnapshot]) -> Result<Vec<Vec<f32>>>;
+
+    /// Load any model now rather than on the first embedding
+    fn load(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Embed a single snapshot
+    fn embed_one(&self, snapshot: &MarketStateSnapshot) -> Result<Vec<f32>> {
+        self.embed(std::slice::from_ref(snapshot))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Failed to generate embedding"))
+    }
+
+    /// Collection metadata describing this embedder
+    fn metadata(&self) -> Map<String, Value> {
+        base_metadata(self)
+    }
+}
+
+/// Embedder kind, model and dimension as collection metadata
+fn base_metadata<E: SnapshotEmbedder + ?Sized>(embedder: &E) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    metadata.insert("embedder".to_string(), Value::from(embedder.kind().label()));
+    metadata.insert("embedding_model".to_string(), Value::from(embedder.model_name()));
+    metadata.insert("embedding_dim".to_string(), Value::from(embedder.dimension()));
+    metadata
+}
+
+/// Embeds `to_embedding_text()` with BGE-small-en-v1.5
+///
+/// The model is loaded (and downloaded on first run) when first used, or
+/// eagerly with `load()`. Concurrent first uses load it once.
+#[derive(Default)]
+pub struct TextSnapshotEmbedder {
+    model: OnceLock<TextEmbedding>,
+    loading: Mutex<()>, // Held while the model loads
+}
+
+impl TextSnapshotEmbedder {
+    /// Create the embedder without loading the model
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn model(&self) -> Result<&TextEmbedding> {
+        if let Some(model) = self.model.get() {
+            return Ok(model);
+        }
+
+        // Workers embedding in parallel would otherwise each load the model
+        let _loading = self
+            .loading
+            .lock()
+            .map_err(|_| anyhow!("Embedding model loading is poisoned"))?;
+        if let Some(model) = self.model.get() {
+            return Ok(model);
+        }
+
+        tracing::info!("Loading embedding model (BGE-small-en-v1.5)...");
+        let model = TextEmbedding::try_new(
+            InitOptions::new(EmbeddingModel::BGESmallENV15).with_show_download_progress(true),
+        )?;
+        Ok(self.model.get_or_init(|| model))
+    }
+}
+
+impl SnapshotEmbedder for TextSnapshotEmbedder {
+    fn kind(&self) -> EmbedderKind {
+        EmbedderKind::Text
+    }
+
+    fn model_name(&self) -> &str {
+        EMBEDDING_MODEL
+    }
+
+    fn dimension(&self) -> usize {
+        EMBEDDING_DIM
+    }
+
+    fn load(&self) -> Result<()> {
+        self.model().map(|_| ())
+    }
+
+    fn embed(&self, snapshots: &[MarketStateSnapshot]) -> Result<Vec<Vec<f32>>> {
+        let texts: Vec<String> = snapshots.iter().map(|s| s.to_embedding_text()).collect();
+        self.model()?.embed(texts, None)
+    }
+}
+
+/// One normalised component of the feature vector
+struct FeatureComponent {
+    name: &'static str,
+    // Value in scale units (~1.0 for a typical move), None when unavailable
+    value: fn(&MarketStateSnapshot, &FeatureSet) -> Option<f64>,
+}
+
+/// Value as % of the snapshot price
+fn pct_of_price(snapshot: &MarketStateSnapshot, value: f64) -> Option<f64> {
+    (snapshot.price.abs() > 1e-10).then(|| value / snapshot.price * 100.0)
+}
+
+/// Components of the feature vector, in vector order
+const FEATURE_COMPONENTS: &[FeatureComponent] = &[
+    FeatureComponent { name: "rsi_7", value: |s, _| Some((s.rsi_7 - 50.0) / 25.0) },
+    FeatureComponent { name: "rsi_14", value: |s, _| Some((s.rsi_14 - 50.0) / 25.0) },
+    FeatureComponent { name: "macd_pct", value: |s, _| pct_of_price(s, s.macd).map(|m| m / 0.1) },
+    FeatureComponent { name: "ema_ratio", value: |_, f| Some((f.ema_ratio - 1.0) / 0.02) },
+    FeatureComponent { name: "ema_20_4h_distance_atr", value: |_, f| f.ema_20_4h_distance_atr.map(|d| d / 2.0) },
+    FeatureComponent { name: "ema_50_4h_distance_atr", value: |_, f| f.ema_50_4h_distance_atr.map(|d| d / 2.0) },
+    FeatureComponent { name: "price_slope_pct", value: |_, f| Some(f.price_slope_pct / 0.05) },
+    FeatureComponent { name: "price_curvature_pct", value: |_, f| Some(f.price_curvature_pct / 0.01) },
+    FeatureComponent { name: "rsi_7_slope", value: |_, f| Some(f.rsi_7_slope / 3.0) },
+    FeatureComponent { name: "macd_slope_pct", value: |s, f| pct_of_price(s, f.macd_slope).map(|m| m / 0.01) },
+    FeatureComponent { name: "rsi_14_4h_slope", value: |_, f| Some(f.rsi_14_4h_slope / 3.0) },
+    FeatureComponent { name: "price_zscore", value: |_, f| f.price_zscore.map(|z| z / 2.0) },
+    FeatureComponent { name: "rsi_7_zscore", value: |_, f| f.rsi_7_zscore.map(|z| z / 2.0) },
+    FeatureComponent {
+        name: "volume_ratio_4h",
+        value: |_, f| f.volume_ratio_4h.filter(|r| *r > 0.0).map(f64::log2),
+    },
+    FeatureComponent {
+        name: "volatility_ratio",
+        value: |_, f| (f.volatility_ratio > 0.0).then(|| f.volatility_ratio.log2()),
+    },
+    FeatureComponent { name: "oi_delta_pct", value: |_, f| f.oi_delta_pct.map(|d| d / 5.0) },
+    FeatureComponent {
+        name: "funding_rate",
+        value: |s, _| s.has_funding_rate.then(|| s.funding_rate / 0.0005),
+    },
+    FeatureComponent { name: "price_change_1h", value: |s, _| s.has_price_change_1h.then_some(s.price_change_1h) },
+    FeatureComponent { name: "price_change_4h", value: |s, _| s.has_price_change_4h.then(|| s.price_change_4h / 2.0) },
+    FeatureComponent { name: "rsi_divergence", value: |_, f| f.rsi_divergence.map(|d| d.signum()) },
+    FeatureComponent { name: "macd_divergence", value: |_, f| f.macd_divergence.map(|d| d.signum()) },
+];
+
+/// Embeds snapshots as a weighted, L2-normalised vector of numeric features
+///
+/// Each component is centred and scaled so a typical move is ~1.0 (clamped to
+/// ±3), multiplied by its weight (default 1.0) and the vector is normalised,
+/// so cosine similarity compares feature values directly instead of the words
+/// used to describe them. Unavailable features contribute 0.0.
+#[derive(Debug, Clone)]
+pub struct FeatureSnapshotEmbedder {
+    weights: Vec<f64>, // One per component, in FEATURE_COMPONENTS order
+}
+
+impl Default for FeatureSnapshotEmbedder {
+    fn default() -> Self {
+        Self { weights: vec![1.0; FEATURE_COMPONENTS.len()] }
+    }
+}
+
+impl FeatureSnapshotEmbedder {
+    /// Create the embedder with every weight at 1.0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names of the vector components, in vector order
+    pub fn component_names() -> impl Iterator<Item = &'static str> {
+        FEATURE_COMPONENTS.iter().map(|c| c.name)
+    }
+
+    /// Set the weight of one component (0.0 leaves it out)
+    pub fn with_weight(mut self, name: &str, weight: f64) -> Result<Self> {
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(anyhow!("Invalid weight {} for feature '{}'", weight, name));
+        }
+        let index = FEATURE_COMPONENTS.iter().position(|c| c.name == name).ok_or_else(|| {
+            anyhow!(
+                "Unknown feature '{}'. Must be one of {}",
+                name,
+                Self::component_names().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        self.weights[index] = weight;
+        Ok(self)
+    }
+
+    /// Set component weights from "name=weight" specs (e.g. "oi_delta_pct=2")
+    pub fn with_weight_specs(self, specs: &[String]) -> Result<Self> {
+        specs.iter().try_fold(self, |embedder, spec| {
+            let (name, weight) = spec
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid feature weight '{}'. Expected name=weight", spec))?;
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .with_context(|| format!("Invalid feature weight '{}'", spec))?;
+            embedder.with_weight(name.trim(), weight)
+        })
+    }
+
+    /// Weight of every component by name
+    pub fn weights(&self) -> BTreeMap<&'static str, f64> {
+        Self::component_names().zip(self.weights.iter().copied()).collect()
+    }
+
+    /// Embed one snapshot
+    pub fn vector(&self, snapshot: &MarketStateSnapshot) -> Vec<f32> {
+        let features = snapshot.features();
+        let values: Vec<f64> = FEATURE_COMPONENTS
+            .iter()
+            .zip(&self.weights)
+            .map(|(component, weight)| {
+                let value = (component.value)(snapshot, &features).unwrap_or(0.0);
+                value.clamp(-MAX_COMPONENT, MAX_COMPONENT) * weight
+            })
+            .collect();
+
+        let norm = values.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let scale = if norm > 1e-12 { 1.0 / norm } else { 0.0 };
+        values.into_iter().map(|v| (v * scale) as f32).collect()
+    }
+}
+
+impl SnapshotEmbedder for FeatureSnapshotEmbedder {
+    fn kind(&self) -> EmbedderKind {
+        EmbedderKind::Features
+    }
+
+    fn model_name(&self) -> &str {
+        FEATURE_EMBEDDING_MODEL
+    }
+
+    fn dimension(&self) -> usize {
+        FEATURE_COMPONENTS.len()
+    }
+
+    /// The model name, followed by the weights that differ from 1.0
+    fn identity(&self) -> String {
+        let mut identity = FEATURE_EMBEDDING_MODEL.to_string();
+        for (name, weight) in self.weights() {
+            if weight != 1.0 {
+                identity.push_str(&format!("|{}={}", name, weight));
+            }
+        }
+        identity
+    }
+
+    fn embed(&self, snapshots: &[MarketStateSnapshot]) -> Result<Vec<Vec<f32>>> {
+        Ok(snapshots.iter().map(|s| self.vector(s)).collect())
+    }
+
+    fn metadata(&self) -> Map<String, Value> {
+        let mut metadata = base_metadata(self);
+        let weights: Map<String, Value> =
+            self.weights().into_iter().map(|(name, weight)| (name.to_string(), Value::from(weight))).collect();
+        metadata.insert("feature_weights".to_string(), Value::Object(weights));
+        metadata
+    }
+}
+
+/// Rebuild the embedder a collection was ingested with from its metadata
+///
+/// Collections created before embedders were recorded have no metadata and
+/// were built with the text embedder.
+pub fn embedder_from_metadata(metadata: &Map<String, Value>) -> Result<Box<dyn SnapshotEmbedder>> {
+    let kind = match metadata.get("embedder").and_then(Value::as_str) {
+        Some(label) => label.parse()?,
+        None => EmbedderKind::Text,
+    };
+
+    let embedder: Box<dyn SnapshotEmbedder> = match kind {
+        EmbedderKind::Text => Box::new(TextSnapshotEmbedder::new()),
+        EmbedderKind::Features => {
+            let mut embedder = FeatureSnapshotEmbedder::new();
+            if let Some(weights) = metadata.get("feature_weights").and_then(Value::as_object) {
+                for (name, weight) in weights {
+                    let weight = weight
+                        .as_f64()
+                        .ok_or_else(|| anyhow!("Invalid stored weight for feature '{}'", name))?;
+                    embedder = embedder.with_weight(name, weight)?;
+                }
+            }
+            Box::new(embedder)
+        }
+    };
+
+    check_embedder_metadata(metadata, embedder.as_ref())?;
+    Ok(embedder)
+}
+
+/// Check that an embedder produces vectors compatible with a collection's metadata
+pub fn check_embedder_metadata(metadata: &Map<String, Value>, embedder: &dyn SnapshotEmbedder) -> Result<()> {
+    let model = metadata.get("embedding_model").and_then(Value::as_str).unwrap_or(EMBEDDING_MODEL);
+    if model != embedder.model_name() {
+        return Err(anyhow!(
+            "Collection was embedded with '{}' but the embedder is '{}'",
+            model,
+            embedder.model_name()
+        ));
+    }
+
+    if let Some(dim) = metadata.get("embedding_dim").and_then(Value::as_u64) {
+        if dim as usize != embedder.dimension() {
+            return Err(anyhow!(
+                "Collection vectors have {} dimensions but the embedder produces {}",
+                dim,
+                embedder.dimension()
+            ));
+        }
+    }
+
+    // Feature weights change every vector, so they must match as well
+    let stored = feature_weights(metadata);
+    let expected = feature_weights(&embedder.metadata());
+    if stored != expected {
+        return Err(anyhow!(
+            "Collection was embedded with feature weights {:?} but the embedder uses {:?}",
+            stored,
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// Feature weights recorded in embedder metadata, by name (empty for other embedders)
+fn feature_weights(metadata: &Map<String, Value>) -> BTreeMap<String, Option<f64>> {
+    metadata
+        .get("feature_weights")
+        .and_then(Value::as_object)
+        .map(|weights| weights.iter().map(|(name, weight)| (name.clone(), weight.as_f64())).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(rsi_7: f64, price_change_1h: f64) -> MarketStateSnapshot {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.rsi_7 = rsi_7;
+        snapshot.rsi_14 = rsi_7;
+        snapshot.ema_20_4h = 50500.0;
+        snapshot.ema_50_4h = 50000.0;
+        snapshot.price_change_1h = price_change_1h;
+        snapshot
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_feature_vectors_are_normalised_and_continuous() {
+        let embedder = FeatureSnapshotEmbedder::new();
+        let vector = embedder.embed_one(&snapshot(59.9, 0.5)).unwrap();
+        assert_eq!(vector.len(), embedder.dimension());
+        assert!((cosine(&vector, &vector) - 1.0).abs() < 1e-5);
+
+        // RSI 59.9 and 60.1 straddle a text threshold but are nearly identical vectors
+        let nearby = embedder.embed_one(&snapshot(60.1, 0.5)).unwrap();
+        let opposite = embedder.embed_one(&snapshot(25.0, -2.0)).unwrap();
+        assert!(cosine(&vector, &nearby) > 0.999);
+        assert!(cosine(&vector, &opposite) < cosine(&vector, &nearby));
+    }
+
+    #[test]
+    fn test_feature_weights() {
+        let embedder = FeatureSnapshotEmbedder::new()
+            .with_weight_specs(&["rsi_7=0".to_string(), "oi_delta_pct = 2.5".to_string()])
+            .unwrap();
+        assert_eq!(embedder.weights()["rsi_7"], 0.0);
+        assert_eq!(embedder.weights()["oi_delta_pct"], 2.5);
+        assert_eq!(embedder.vector(&snapshot(80.0, 0.0))[0], 0.0);
+
+        assert!(FeatureSnapshotEmbedder::new().with_weight("volume", 1.0).is_err());
+        assert!(FeatureSnapshotEmbedder::new().with_weight("rsi_7", -1.0).is_err());
+        assert!(FeatureSnapshotEmbedder::new().with_weight_specs(&["rsi_7".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_embedder_from_metadata() {
+        let embedder = FeatureSnapshotEmbedder::new().with_weight("funding_rate", 3.0).unwrap();
+        let rebuilt = embedder_from_metadata(&embedder.metadata()).unwrap();
+        assert_eq!(rebuilt.kind(), EmbedderKind::Features);
+        assert_eq!(rebuilt.embed_one(&snapshot(70.0, 1.0)).unwrap(), embedder.vector(&snapshot(70.0, 1.0)));
+
+        // Legacy collections without metadata use the text embedder
+        let legacy = embedder_from_metadata(&Map::new()).unwrap();
+        assert_eq!(legacy.kind(), EmbedderKind::Text);
+        assert_eq!(legacy.dimension(), EMBEDDING_DIM);
+
+        // A collection built by another model is rejected
+        let mut other = embedder.metadata();
+        other.insert("embedding_model".to_string(), Value::from("snapshot-features-v0"));
+        assert!(embedder_from_metadata(&other).is_err());
+        assert!(check_embedder_metadata(&embedder.metadata(), &TextSnapshotEmbedder::new()).is_err());
+
+        // So is one built with other feature weights
+        let err = check_embedder_metadata(&embedder.metadata(), &FeatureSnapshotEmbedder::new()).unwrap_err();
+        assert!(err.to_string().contains("feature weights"), "{}", err);
+        assert!(check_embedder_metadata(&embedder.metadata(), rebuilt.as_ref()).is_ok());
+
+        // Weights are part of the identity hashed into each point
+        assert_eq!(FeatureSnapshotEmbedder::new().identity(), FEATURE_EMBEDDING_MODEL);
+        assert_eq!(embedder.identity(), "snapshot-features-v1|funding_rate=3");
+        assert_eq!(TextSnapshotEmbedder::new().identity(), EMBEDDING_MODEL);
+        assert_eq!("Features".parse::<EmbedderKind>().unwrap(), EmbedderKind::Features);
+    }
+}
@@ -1,107 +1,358 @@
-use anyhow::Result;
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
-use std::sync::Arc;
-use trading_core::TimestampMS;
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, StreamExt};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use trading_core::{MarketStateSnapshot, OutcomeSpec, TimestampMS};
 use tracing;
 
-use super::snapshot_extractor::{DataSource, HistoricalSnapshotExtractor};
-use super::snapshot_formatter::SnapshotFormatter;
-use super::vector_store::{snapshot_to_point, VectorStore};
+use super::checkpoint::IngestCheckpoint;
+use super::csv_data_source::CsvDataSource;
+use super::embedder::{SnapshotEmbedder, TextSnapshotEmbedder};
+use super::lmdb_reader::LmdbReader;
+use super::market_data_source::MarketDataSource;
+use super::mock_data_source::MockDataSource;
+use super::snapshot_extractor::{GapFillPolicy, HistoricalSnapshotExtractor};
+use super::schema::{rebuild_payload, snapshot_from_payload};
+use super::snapshot_validator::{write_quarantine, SnapshotValidator};
+use super::vector_store::{
+    snapshot_content_hash, snapshot_point_id, snapshot_to_hybrid_point, snapshot_to_point, VectorStore,
+};
 
 /// Statistics from an ingestion run
 #[derive(Debug, Default, Clone)]
 pub struct IngestStats {
     pub snapshots_created: usize,
+    pub snapshots_low_quality: usize, // Skipped for a data quality below the minimum
+    pub snapshots_rejected: usize,    // Failed validation (written to the quarantine file)
+    pub snapshots_unchanged: usize,   // Already stored with the same content (not re-embedded)
     pub embeddings_generated: usize,
     pub points_uploaded: usize,
 }
 
+/// Statistics from an outcome backfill
+#[derive(Debug, Default, Clone)]
+pub struct OutcomeBackfillStats {
+    pub points_pending: usize, // Stored with outcomes pending
+    pub points_updated: usize, // Payload rewritten with newly matured outcomes
+    pub points_matured: usize, // Updated with every horizon known (no longer pending)
+    pub points_failed: usize,  // Snapshot or outcome data could not be read (left unchanged)
+}
+
+/// Pending points read per scroll request during an outcome backfill
+const BACKFILL_PAGE_SIZE: u32 = 256;
+
+/// Snapshots embedded and upserted per batch by default
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Symbols ingested concurrently by default
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// Grid the extractor aligns snapshot timestamps to
+const THREE_MINUTES_MS: u64 = 180_000;
+
+/// A batch upsert running in the background
+struct PendingUpsert {
+    task: JoinHandle<Result<()>>,
+    points: usize,
+    last_timestamp: TimestampMS,
+}
+
 /// Historical ingestion pipeline that:
-/// 1. Extracts snapshots from LMDB
-/// 2. Converts to natural language
-/// 3. Generates embeddings
-/// 4. Uploads to Qdrant
-pub struct HistoricalIngestionPipeline {
-    snapshot_extractor: Arc<HistoricalSnapshotExtractor>,
-    embedding_model: TextEmbedding,
+/// 1. Extracts snapshots from a market data source (LMDB, mock, ...)
+/// 2. Validates them, quarantining snapshots that fail sanity rules
+/// 3. Embeds them (natural language text by default, or numeric features)
+/// 4. Upserts them into Qdrant
+///
+/// Points are keyed by `snapshot_point_id`, so re-running a range is an
+/// idempotent upsert: snapshots already stored with the same content hash are
+/// skipped, and only new or changed ones are embedded and uploaded.
+///
+/// Embedding and upserting are pipelined in bounded batches: the next batch
+/// is embedded while the previous one is upserted, with at most one upsert in
+/// flight. With a checkpoint, each upserted batch commits its last timestamp
+/// so an interrupted run can resume from there.
+///
+/// Several symbols are ingested concurrently by a pool of workers. Snapshot
+/// extraction and embedding run on blocking threads, so they never stall the
+/// async runtime.
+pub struct HistoricalIngestionPipeline<S> {
+    snapshot_extractor: Arc<HistoricalSnapshotExtractor<S>>,
+    embedder: Arc<dyn SnapshotEmbedder>,
     vector_store: Arc<VectorStore>,
+    sparse_vectors: OnceCell<bool>, // Set once the collection is created or checked against the embedder
+    min_data_quality: f64,
+    validator: SnapshotValidator,
+    quarantine_path: Option<PathBuf>,
+    batch_size: usize,
+    workers: usize,
+    checkpoint: Option<Mutex<IngestCheckpoint>>,
+    resume: bool, // Start each symbol after its last committed snapshot
 }
 
-impl HistoricalIngestionPipeline {
+impl HistoricalIngestionPipeline<MockDataSource> {
     /// Create a new ingestion pipeline with mock data
     pub async fn new(qdrant_url: &str, collection_name: String) -> Result<Self> {
-        Self::new_with_data_source(qdrant_url, collection_name, DataSource::Mock, None).await
+        tracing::info!("Using mock data source for testing");
+        Self::with_source(qdrant_url, collection_name, MockDataSource::new()).await
     }
+}
 
+impl HistoricalIngestionPipeline<LmdbReader> {
     /// Create a new ingestion pipeline with LMDB data source
     pub async fn with_lmdb(
         qdrant_url: &str,
         collection_name: String,
         lmdb_path: &str,
     ) -> Result<Self> {
-        Self::new_with_data_source(
+        tracing::info!("Using LMDB data source at: {}", lmdb_path);
+        let extractor = HistoricalSnapshotExtractor::with_lmdb(lmdb_path)?;
+        Self::with_extractor(qdrant_url, collection_name, extractor).await
+    }
+}
+
+impl HistoricalIngestionPipeline<CsvDataSource> {
+    /// Create a new ingestion pipeline reading candle CSV files
+    pub async fn with_csv(
+        qdrant_url: &str,
+        collection_name: String,
+        candles_dir: &str,
+    ) -> Result<Self> {
+        tracing::info!("Using CSV candle files in: {}", candles_dir);
+        Self::with_source(qdrant_url, collection_name, CsvDataSource::new(candles_dir)?).await
+    }
+}
+
+impl<S: MarketDataSource + 'static> HistoricalIngestionPipeline<S> {
+    /// Create a new ingestion pipeline reading from the given data source
+    pub async fn with_source(qdrant_url: &str, collection_name: String, source: S) -> Result<Self> {
+        Self::with_extractor(
             qdrant_url,
             collection_name,
-            DataSource::Lmdb,
-            Some(lmdb_path.to_string()),
+            HistoricalSnapshotExtractor::with_source(source),
         )
         .await
     }
 
-    /// Create a new ingestion pipeline with specified data source
-    async fn new_with_data_source(
+    /// Create a new ingestion pipeline around a configured extractor
+    async fn with_extractor(
         qdrant_url: &str,
         collection_name: String,
-        data_source: DataSource,
-        lmdb_path: Option<String>,
+        snapshot_extractor: HistoricalSnapshotExtractor<S>,
     ) -> Result<Self> {
-        tracing::info!("Initializing ingestion pipeline with data source: {:?}", data_source);
-
-        // Initialize embedding model (downloads BGE model on first run)
-        tracing::info!("Loading embedding model (BGE-small-en-v1.5)...");
-        let embedding_model = TextEmbedding::try_new(
-            InitOptions::new(EmbeddingModel::BGESmallENV15).with_show_download_progress(true),
-        )?;
-
-        // Initialize snapshot extractor based on data source
-        let snapshot_extractor = match data_source {
-            DataSource::Mock => {
-                tracing::info!("Using mock data source for testing");
-                Arc::new(HistoricalSnapshotExtractor::new())
-            }
-            DataSource::Lmdb => {
-                let path = lmdb_path
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("LMDB path required for LMDB data source"))?;
-                tracing::info!("Using LMDB data source at: {}", path);
-                Arc::new(HistoricalSnapshotExtractor::with_lmdb(path)?)
-            }
-        };
+        tracing::info!(
+            "Initializing ingestion pipeline with data source: {}",
+            snapshot_extractor.source().name()
+        );
 
-        // Initialize vector store
+        // Initialize vector store (the collection is created for the embedder on first ingest)
         let vector_store = Arc::new(VectorStore::new(qdrant_url, collection_name).await?);
 
-        // Create collection if it doesn't exist (BGE-small uses 384 dimensions)
-        vector_store.create_collection_if_not_exists(384).await?;
-
         tracing::info!("Ingestion pipeline initialized successfully");
 
         Ok(Self {
-            snapshot_extractor,
-            embedding_model,
+            snapshot_extractor: Arc::new(snapshot_extractor),
+            embedder: Arc::new(TextSnapshotEmbedder::new()),
             vector_store,
+            sparse_vectors: OnceCell::new(),
+            min_data_quality: 0.0,
+            validator: SnapshotValidator::new(),
+            quarantine_path: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            workers: DEFAULT_WORKERS,
+            checkpoint: None,
+            resume: false,
         })
     }
 
+    /// Reconfigure the extractor
+    ///
+    /// Fails while an extraction task still holds the extractor: blocking
+    /// tasks keep running after an ingest future is dropped, so a pipeline
+    /// can be reconfigured before they finish.
+    fn map_extractor(
+        self,
+        configure: impl FnOnce(HistoricalSnapshotExtractor<S>) -> HistoricalSnapshotExtractor<S>,
+    ) -> Result<Self> {
+        let extractor = Arc::try_unwrap(self.snapshot_extractor)
+            .map_err(|_| anyhow!("Snapshot extractor is still in use by an extraction task"))?;
+        Ok(Self {
+            snapshot_extractor: Arc::new(configure(extractor)),
+            ..self
+        })
+    }
+
+    /// Set how missing time series points are handled during extraction
+    ///
+    /// Fails while an extraction task is still running (see `map_extractor`).
+    pub fn with_gap_policy(self, gap_policy: GapFillPolicy) -> Result<Self> {
+        self.map_extractor(|extractor| extractor.with_gap_policy(gap_policy))
+    }
+
+    /// Set the outcome horizons and stop/target rules recorded for each snapshot
+    ///
+    /// Fails while an extraction task is still running (see `map_extractor`).
+    pub fn with_outcome_spec(self, outcome_spec: OutcomeSpec) -> Result<Self> {
+        self.map_extractor(|extractor| extractor.with_outcome_spec(outcome_spec))
+    }
+
+    /// Attach the state of a reference symbol (e.g. "BTCUSDT") to snapshots of other symbols
+    ///
+    /// Fails while an extraction task is still running (see `map_extractor`).
+    pub fn with_reference_symbol(self, symbol: impl Into<String>) -> Result<Self> {
+        self.map_extractor(|extractor| extractor.with_reference_symbol(symbol))
+    }
+
+    /// Replace the embedder (the BGE text embedder by default)
+    ///
+    /// The collection records the embedder when created; ingesting into an
+    /// existing collection built with another embedder fails.
+    pub fn with_embedder(mut self, embedder: Box<dyn SnapshotEmbedder>) -> Self {
+        self.embedder = Arc::from(embedder);
+        self.sparse_vectors = OnceCell::new();
+        self
+    }
+
+    /// Replace the validator run before embedding
+    pub fn with_validator(mut self, validator: SnapshotValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Append snapshots that fail validation to a JSONL file
+    ///
+    /// Without a quarantine file rejected snapshots are only logged.
+    pub fn with_quarantine_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.quarantine_path = Some(path.into());
+        self
+    }
+
+    /// Skip snapshots whose data quality is below `min_data_quality` (0.0-1.0)
+    pub fn with_min_data_quality(mut self, min_data_quality: f64) -> Self {
+        self.min_data_quality = min_data_quality;
+        self
+    }
+
+    /// Set the number of snapshots embedded and upserted per batch
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the number of symbols ingested concurrently by `ingest_multiple_symbols`
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Commit the last upserted timestamp of each symbol to a checkpoint
+    pub fn with_checkpoint(mut self, checkpoint: IngestCheckpoint) -> Self {
+        self.checkpoint = Some(Mutex::new(checkpoint));
+        self
+    }
+
+    /// Start each symbol after the last snapshot committed to the checkpoint
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Timestamp of the last snapshot committed to the checkpoint for a symbol
+    pub fn last_committed(&self, symbol: &str) -> Result<Option<TimestampMS>> {
+        match &self.checkpoint {
+            Some(checkpoint) => Ok(lock_checkpoint(checkpoint)?.last_committed(symbol)),
+            None => Ok(None),
+        }
+    }
+
+    /// The embedder snapshots are embedded with
+    pub fn embedder(&self) -> &dyn SnapshotEmbedder {
+        self.embedder.as_ref()
+    }
+
     /// Ingest all historical data for a symbol
+    ///
+    /// The range is extracted in chunks of about `batch_size` snapshots, each
+    /// upserted (and committed to the checkpoint) before the next is read, so
+    /// memory stays bounded and an interrupted run resumes after the last
+    /// stored chunk.
     pub async fn ingest_symbol_history(
-        &mut self,
+        &self,
         symbol: &str,
         start_timestamp: TimestampMS,
         end_timestamp: TimestampMS,
         snapshot_interval_minutes: u64,
     ) -> Result<IngestStats> {
         let mut stats = IngestStats::default();
+        let start_timestamp = self.start_extraction(symbol, start_timestamp, end_timestamp, snapshot_interval_minutes)?;
+
+        for (chunk_start, chunk_end) in self.extraction_chunks(start_timestamp, end_timestamp, snapshot_interval_minutes) {
+            let snapshots = self
+                .extract_chunk(symbol, chunk_start, chunk_end, snapshot_interval_minutes, &mut stats)
+                .await?;
+            if !snapshots.is_empty() {
+                self.upsert_snapshots(symbol, &snapshots, &mut stats).await?;
+            }
+        }
+        if stats.embeddings_generated + stats.snapshots_unchanged == 0 {
+            tracing::warn!("No snapshots to ingest for {}", symbol);
+        }
+
+        tracing::info!("Ingestion complete for {}: {:?}", symbol, stats);
+        Ok(stats)
+    }
+
+    /// Extract, filter and validate the snapshots of a symbol (steps 1 and 2)
+    ///
+    /// Touches neither Qdrant nor the embedder, so it also serves dry runs.
+    ///
+    /// # Returns
+    /// The snapshots that would be embedded, and the extraction stats
+    pub async fn extract_validated_snapshots(
+        &self,
+        symbol: &str,
+        start_timestamp: TimestampMS,
+        end_timestamp: TimestampMS,
+        snapshot_interval_minutes: u64,
+    ) -> Result<(Vec<MarketStateSnapshot>, IngestStats)> {
+        let mut stats = IngestStats::default();
+        let start_timestamp = self.start_extraction(symbol, start_timestamp, end_timestamp, snapshot_interval_minutes)?;
+
+        let mut snapshots = Vec::new();
+        for (chunk_start, chunk_end) in self.extraction_chunks(start_timestamp, end_timestamp, snapshot_interval_minutes) {
+            snapshots.extend(
+                self.extract_chunk(symbol, chunk_start, chunk_end, snapshot_interval_minutes, &mut stats)
+                    .await?,
+            );
+        }
+
+        Ok((snapshots, stats))
+    }
+
+    /// Log the start of a symbol's extraction
+    ///
+    /// # Returns
+    /// The first timestamp to extract: `start_timestamp`, or the next one on
+    /// the interval grid after the last committed snapshot when resuming
+    fn start_extraction(
+        &self,
+        symbol: &str,
+        start_timestamp: TimestampMS,
+        end_timestamp: TimestampMS,
+        snapshot_interval_minutes: u64,
+    ) -> Result<TimestampMS> {
+        // Resume after the last committed snapshot, staying on the interval grid
+        let mut start_timestamp = start_timestamp;
+        if let Some(last) = self.resume_after(symbol)? {
+            let next = last + snapshot_interval_minutes * 60_000;
+            if next > start_timestamp {
+                tracing::info!("Resuming {} after committed snapshot at {}", symbol, last);
+                start_timestamp = next;
+            }
+        }
 
         let start_date = chrono::DateTime::from_timestamp_millis(start_timestamp as i64)
             .map(|dt| dt.to_rfc3339())
@@ -119,101 +370,397 @@ impl HistoricalIngestionPipeline {
             snapshot_interval_minutes
         );
 
-        // Step 1: Extract snapshots from LMDB
-        let snapshots = self.snapshot_extractor.extract_snapshots(
-            symbol,
-            start_timestamp,
-            end_timestamp,
-            snapshot_interval_minutes,
-        )?;
+        Ok(start_timestamp)
+    }
+
+    /// Split a range into consecutive chunks of `batch_size` snapshot intervals
+    fn extraction_chunks(
+        &self,
+        start_timestamp: TimestampMS,
+        end_timestamp: TimestampMS,
+        snapshot_interval_minutes: u64,
+    ) -> impl Iterator<Item = (TimestampMS, TimestampMS)> {
+        let chunk_ms = (self.batch_size as u64 * snapshot_interval_minutes * 60_000).max(1);
+        // Snapshots stay on the grid the extractor aligns the start to (the next 3m boundary)
+        let start = start_timestamp.div_ceil(THREE_MINUTES_MS) * THREE_MINUTES_MS;
+        (0..)
+            .map(move |i| start + i * chunk_ms)
+            .take_while(move |&chunk_start| chunk_start < end_timestamp)
+            .map(move |chunk_start| (chunk_start, (chunk_start + chunk_ms).min(end_timestamp)))
+    }
+
+    /// Extract, filter and validate the snapshots of one chunk, adding to `stats`
+    async fn extract_chunk(
+        &self,
+        symbol: &str,
+        start_timestamp: TimestampMS,
+        end_timestamp: TimestampMS,
+        snapshot_interval_minutes: u64,
+        stats: &mut IngestStats,
+    ) -> Result<Vec<MarketStateSnapshot>> {
+        // Step 1: Extract snapshots from the data source (blocking reads)
+        let extractor = Arc::clone(&self.snapshot_extractor);
+        let extract_symbol = symbol.to_string();
+        let snapshots = tokio::task::spawn_blocking(move || {
+            extractor.extract_snapshots(&extract_symbol, start_timestamp, end_timestamp, snapshot_interval_minutes)
+        })
+        .await
+        .context("Snapshot extraction task failed")??;
+
+        let created = snapshots.len();
+        stats.snapshots_created += created;
+        tracing::info!("Created {} snapshots for {}", created, symbol);
+
+        let snapshots: Vec<_> = snapshots
+            .into_iter()
+            .filter(|s| s.data_quality >= self.min_data_quality)
+            .collect();
+        let low_quality = created - snapshots.len();
+        stats.snapshots_low_quality += low_quality;
+        if low_quality > 0 {
+            tracing::info!(
+                "Skipped {} snapshots for {} with data quality below {}",
+                low_quality,
+                symbol,
+                self.min_data_quality
+            );
+        }
 
-        stats.snapshots_created = snapshots.len();
-        tracing::info!("Created {} snapshots for {}", snapshots.len(), symbol);
+        // Step 2: Validate before embedding
+        let (snapshots, rejected) = self.validator.validate_all(snapshots);
+        stats.snapshots_rejected += rejected.len();
+        if !rejected.is_empty() {
+            for entry in rejected.iter().take(5) {
+                tracing::warn!(
+                    "Rejected snapshot for {} at {}: {}",
+                    entry.symbol,
+                    entry.timestamp,
+                    entry.reasons.join("; ")
+                );
+            }
+            match &self.quarantine_path {
+                Some(path) => {
+                    write_quarantine(path, &rejected)?;
+                    tracing::warn!(
+                        "Quarantined {} invalid snapshots for {} to {}",
+                        rejected.len(),
+                        symbol,
+                        path.display()
+                    );
+                }
+                None => tracing::warn!("Dropped {} invalid snapshots for {}", rejected.len(), symbol),
+            }
+        }
 
+        Ok(snapshots)
+    }
+
+    /// Embed and upsert validated snapshots of a symbol, in timestamp order (steps 3 and 4)
+    ///
+    /// Adds the embedding and upload counts to `stats`.
+    pub async fn upsert_snapshots(
+        &self,
+        symbol: &str,
+        snapshots: &[MarketStateSnapshot],
+        stats: &mut IngestStats,
+    ) -> Result<()> {
         if snapshots.is_empty() {
-            tracing::warn!("No snapshots created for {}", symbol);
-            return Ok(stats);
+            tracing::warn!("No snapshots to ingest for {}", symbol);
+            return Ok(());
         }
 
-        // Step 2: Generate embeddings in batches
-        const BATCH_SIZE: usize = 100;
-        let mut all_points = Vec::new();
-        let mut point_id = 0u64;
-
-        for batch in snapshots.chunks(BATCH_SIZE) {
-            // Convert to text
-            let texts: Vec<String> = batch.iter().map(|s| s.to_embedding_text()).collect();
+        let sparse_vectors = self.prepare_collection().await?;
+
+        // Step 3: Generate embeddings in batches, skipping unchanged points,
+        // while the previous batch is upserted (step 4)
+        let identity = self.embedder.identity();
+        let mut pending: Option<PendingUpsert> = None;
+        let mut unchanged = 0;
+
+        for batch in snapshots.chunks(self.batch_size) {
+            let last_timestamp = batch[batch.len() - 1].timestamp;
+            let point_ids: Vec<u64> = batch
+                .iter()
+                .map(|s| snapshot_point_id(&s.symbol, s.timestamp))
+                .collect();
+            let stored_hashes = self.vector_store.content_hashes(&point_ids).await?;
+
+            let mut changed = Vec::new();
+            let mut changed_ids = Vec::new();
+            for (snapshot, point_id) in batch.iter().zip(point_ids) {
+                if stored_hashes.get(&point_id) != Some(&snapshot_content_hash(snapshot, &identity)?) {
+                    changed.push(snapshot.clone());
+                    changed_ids.push(point_id);
+                }
+            }
+            unchanged += batch.len() - changed.len();
+            if changed.is_empty() {
+                self.finish_upsert(symbol, pending.take(), stats).await?;
+                self.commit_checkpoint(symbol, last_timestamp)?;
+                continue;
+            }
 
             tracing::info!(
                 "Generating embeddings for batch of {} snapshots...",
-                texts.len()
+                changed.len()
             );
 
-            // Generate embeddings (much faster in batch)
-            let embeddings = self.embedding_model.embed(texts, None)?;
-            stats.embeddings_generated += embeddings.len();
+            // Generate embeddings (much faster in batch) on a blocking thread
+            let embedder = Arc::clone(&self.embedder);
+            let (changed, embeddings) = tokio::task::spawn_blocking(move || {
+                let embeddings = embedder.embed(&changed)?;
+                Ok::<_, anyhow::Error>((changed, embeddings))
+            })
+            .await
+            .context("Embedding task failed")??;
+            let embedded = embeddings.len();
+            stats.embeddings_generated += embedded;
 
             // Create Qdrant points
-            for (snapshot, embedding) in batch.iter().zip(embeddings.iter()) {
-                let point = snapshot_to_point(snapshot, embedding.clone(), point_id);
-                all_points.push(point);
-                point_id += 1;
-            }
+            let points = changed
+                .iter()
+                .zip(embeddings)
+                .zip(changed_ids)
+                .map(|((snapshot, embedding), point_id)| {
+                    if sparse_vectors {
+                        snapshot_to_hybrid_point(snapshot, embedding, point_id, self.embedder.as_ref())
+                    } else {
+                        snapshot_to_point(snapshot, embedding, point_id, self.embedder.as_ref())
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
 
             tracing::info!(
                 "Processed {} embeddings (total: {})",
-                embeddings.len(),
+                embedded,
                 stats.embeddings_generated
             );
+
+            // Step 4: Upload to Qdrant once the previous batch is stored (backpressure)
+            self.finish_upsert(symbol, pending.take(), stats).await?;
+            let vector_store = Arc::clone(&self.vector_store);
+            pending = Some(PendingUpsert {
+                points: points.len(),
+                last_timestamp,
+                task: tokio::spawn(async move { vector_store.upsert_points(points).await }),
+            });
         }
+        self.finish_upsert(symbol, pending.take(), stats).await?;
 
-        // Step 3: Upload to Qdrant
-        if !all_points.is_empty() {
-            tracing::info!("Uploading {} points to Qdrant...", all_points.len());
-            self.vector_store.upsert_points(all_points).await?;
-            stats.points_uploaded = point_id as usize;
-            tracing::info!("Uploaded {} points to Qdrant", stats.points_uploaded);
+        // Counts for these snapshots only; the totals go into the final summary
+        stats.snapshots_unchanged += unchanged;
+        if unchanged > 0 {
+            tracing::info!(
+                "Skipped {} unchanged of {} snapshots for {}",
+                unchanged,
+                snapshots.len(),
+                symbol
+            );
         }
 
-        tracing::info!("Ingestion complete for {}: {:?}", symbol, stats);
+        Ok(())
+    }
+
+    /// Create or check the collection once per pipeline
+    ///
+    /// Fails when the collection was embedded by another embedder (model,
+    /// dimension or feature weights) or holds legacy sequential point IDs.
+    ///
+    /// # Returns
+    /// Whether the collection stores sparse vectors next to the dense ones
+    async fn prepare_collection(&self) -> Result<bool> {
+        let sparse_vectors = self
+            .sparse_vectors
+            .get_or_try_init(|| async {
+                self.vector_store.create_collection_if_not_exists(self.embedder.as_ref()).await?;
+                // Upserting next to sequential IDs would store every snapshot twice
+                if self.vector_store.has_legacy_point_ids().await? {
+                    return Err(anyhow!(
+                        "Collection holds points with sequential IDs from an older ingestion; ingest into a \
+                         new collection, or delete them with `rag-ingest --purge-legacy-points` and re-ingest"
+                    ));
+                }
+                self.vector_store.has_sparse_vectors().await
+            })
+            .await?;
+        Ok(*sparse_vectors)
+    }
+
+    /// Backfill the outcomes of stored points whose horizons have passed
+    ///
+    /// Points upserted with pending outcomes are re-evaluated against the
+    /// data source with `now_ms` as the present, and their payloads rewritten
+    /// when any horizon matured. The symbol's candles are read once, on a
+    /// blocking thread, for all of its pending points. Vectors are left
+    /// untouched, since only the outcome fields change.
+    pub async fn backfill_outcomes(&self, symbol: &str, now_ms: i64) -> Result<OutcomeBackfillStats> {
+        let mut stats = OutcomeBackfillStats::default();
+        // Content hashes use this embedder's identity, which must be the collection's
+        self.prepare_collection().await?;
+        let identity = self.embedder.identity();
+        let filter = Filter::must([
+            Condition::matches("symbol", symbol.to_string()),
+            Condition::matches("outcomes_pending", true),
+        ]);
+        // Collect the pending points (at most one horizon's worth per symbol)
+        let mut pending = Vec::new();
+        let mut offset = None;
+        loop {
+            let (points, next_offset) = self
+                .vector_store
+                .scroll_payloads_matching(Some(filter.clone()), offset, BACKFILL_PAGE_SIZE)
+                .await?;
+
+            for (point_id, payload) in points {
+                stats.points_pending += 1;
+                match snapshot_from_payload(&payload) {
+                    Ok(snapshot) => {
+                        let stored_hash = snapshot_content_hash(&snapshot, &identity)?;
+                        pending.push((point_id, payload, snapshot, stored_hash));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Skipping point {:?}: {:#}", point_id, e);
+                        stats.points_failed += 1;
+                    }
+                }
+            }
+
+            match next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        // Refresh them all from one candle read (blocking)
+        let extractor = Arc::clone(&self.snapshot_extractor);
+        let refresh_symbol = symbol.to_string();
+        let mut snapshots: Vec<MarketStateSnapshot> = pending.iter().map(|(_, _, s, _)| s.clone()).collect();
+        let refreshed = tokio::task::spawn_blocking(move || {
+            extractor.refresh_outcomes(&refresh_symbol, &mut snapshots, now_ms)?;
+            Ok::<_, anyhow::Error>(snapshots)
+        })
+        .await
+        .context("Outcome refresh task failed")?;
+        let refreshed = match refreshed {
+            Ok(refreshed) => refreshed,
+            Err(e) => {
+                tracing::warn!("Failed to refresh outcomes of {}: {:#}", symbol, e);
+                stats.points_failed += pending.len();
+                return Ok(stats);
+            }
+        };
+
+        for ((point_id, payload, _, stored_hash), snapshot) in pending.into_iter().zip(refreshed) {
+            let content_hash = snapshot_content_hash(&snapshot, &identity)?;
+            if content_hash == stored_hash {
+                continue;
+            }
+
+            let mut updated = rebuild_payload(&snapshot, &payload);
+            updated.insert("content_hash".to_string(), Value::from(content_hash));
+            self.vector_store.overwrite_payload(point_id, updated).await?;
+            stats.points_updated += 1;
+            if !snapshot.outcomes_pending {
+                stats.points_matured += 1;
+            }
+        }
+
+        if stats.points_updated > 0 || stats.points_failed > 0 {
+            tracing::info!("Outcome backfill for {}: {:?}", symbol, stats);
+        }
         Ok(stats)
     }
 
-    /// Ingest multiple symbols
+    /// Last committed timestamp of a symbol when resuming
+    fn resume_after(&self, symbol: &str) -> Result<Option<TimestampMS>> {
+        if !self.resume {
+            return Ok(None);
+        }
+        self.last_committed(symbol)
+    }
+
+    /// Wait for an in-flight upsert, then count its points and commit its last timestamp
+    async fn finish_upsert(
+        &self,
+        symbol: &str,
+        pending: Option<PendingUpsert>,
+        stats: &mut IngestStats,
+    ) -> Result<()> {
+        let Some(pending) = pending else {
+            return Ok(());
+        };
+
+        pending
+            .task
+            .await
+            .context("Upsert task failed")?
+            .with_context(|| format!("Failed to upsert {} points for {}", pending.points, symbol))?;
+        stats.points_uploaded += pending.points;
+        tracing::info!("Uploaded {} points to Qdrant (total: {})", pending.points, stats.points_uploaded);
+
+        self.commit_checkpoint(symbol, pending.last_timestamp)
+    }
+
+    /// Record that a symbol is stored up to `timestamp` (no-op without a checkpoint)
+    fn commit_checkpoint(&self, symbol: &str, timestamp: TimestampMS) -> Result<()> {
+        match &self.checkpoint {
+            Some(checkpoint) => lock_checkpoint(checkpoint)?.commit(symbol, timestamp),
+            None => Ok(()),
+        }
+    }
+
+    /// Ingest multiple symbols concurrently (up to the configured number of workers)
+    ///
+    /// A failing symbol does not stop the others.
+    ///
+    /// # Returns
+    /// The stats or error of every symbol, in the order given
     pub async fn ingest_multiple_symbols(
-        &mut self,
+        &self,
         symbols: Vec<&str>,
         start_timestamp: TimestampMS,
         end_timestamp: TimestampMS,
         snapshot_interval_minutes: u64,
-    ) -> Result<Vec<(String, IngestStats)>> {
-        let mut results = Vec::new();
-
-        for symbol in symbols {
-            tracing::info!("Processing symbol: {}", symbol);
-            let stats = self
-                .ingest_symbol_history(
-                    symbol,
-                    start_timestamp,
-                    end_timestamp,
-                    snapshot_interval_minutes,
-                )
-                .await?;
-            results.push((symbol.to_string(), stats));
-        }
-
-        Ok(results)
+    ) -> Vec<(String, Result<IngestStats>)> {
+        stream::iter(symbols)
+            .map(|symbol| async move {
+                tracing::info!("Processing symbol: {}", symbol);
+                let result = self
+                    .ingest_symbol_history(
+                        symbol,
+                        start_timestamp,
+                        end_timestamp,
+                        snapshot_interval_minutes,
+                    )
+                    .await;
+                if let Err(e) = &result {
+                    tracing::error!("Ingestion failed for {}: {:#}", symbol, e);
+                }
+                (symbol.to_string(), result)
+            })
+            .buffered(self.workers)
+            .collect()
+            .await
     }
 }
 
+/// Lock the checkpoint shared by the symbol workers
+fn lock_checkpoint(checkpoint: &Mutex<IngestCheckpoint>) -> Result<std::sync::MutexGuard<'_, IngestCheckpoint>> {
+    checkpoint
+        .lock()
+        .map_err(|_| anyhow!("Ingestion checkpoint is poisoned"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const ONE_HOUR_MS: u64 = 3_600_000;
+
     #[tokio::test]
     #[ignore] // Requires Qdrant running
     async fn test_ingestion_pipeline() {
-        let mut pipeline = HistoricalIngestionPipeline::new(
+        let pipeline = HistoricalIngestionPipeline::new(
             "http://localhost:6333",
             "test_trading_patterns".to_string(),
         )
@@ -229,7 +776,53 @@ mod tests {
             .unwrap();
 
         assert!(stats.snapshots_created > 0);
-        assert_eq!(stats.embeddings_generated, stats.snapshots_created);
-        assert_eq!(stats.points_uploaded, stats.snapshots_created);
+        assert_eq!(
+            stats.embeddings_generated + stats.snapshots_unchanged,
+            stats.snapshots_created
+        );
+        assert_eq!(stats.points_uploaded, stats.embeddings_generated);
+
+        // Re-ingesting the same range touches nothing
+        let rerun = pipeline
+            .ingest_symbol_history("BTCUSDT", start, end, 15)
+            .await
+            .unwrap();
+        assert_eq!(rerun.snapshots_unchanged, rerun.snapshots_created);
+        assert_eq!(rerun.points_uploaded, 0);
+    }
+
+    #[tokio::test]
+    async fn test_extraction_chunks_follow_the_snapshot_grid() {
+        let pipeline = HistoricalIngestionPipeline::new("http://localhost:6334", "test".to_string())
+            .await
+            .unwrap()
+            .with_batch_size(4);
+        let start = 1_700_000_000_000; // Extracted from the next 3m boundary
+        let chunks: Vec<_> = pipeline.extraction_chunks(start, start + 2 * ONE_HOUR_MS, 15).collect();
+
+        let first = 1_700_000_100_000;
+        assert_eq!(
+            chunks,
+            vec![
+                (first, first + ONE_HOUR_MS),
+                (first + ONE_HOUR_MS, start + 2 * ONE_HOUR_MS),
+            ]
+        );
+        assert_eq!(pipeline.extraction_chunks(start, start, 15).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_while_extracting_fails() {
+        let pipeline = HistoricalIngestionPipeline::new("http://localhost:6334", "test".to_string())
+            .await
+            .unwrap()
+            .with_gap_policy(GapFillPolicy::Reject)
+            .unwrap();
+
+        // An extraction task outliving its ingest future still holds the extractor
+        let task_handle = Arc::clone(&pipeline.snapshot_extractor);
+        let err = pipeline.with_reference_symbol("BTCUSDT").err().unwrap();
+        assert!(err.to_string().contains("still in use"), "{}", err);
+        drop(task_handle);
     }
 }
@@ -0,0 +1,257 @@
+use anyhow::{anyhow, Context, Result};
+use trading_core::{
+    Candle, Indicators3m, Indicators4h, MarketStateSnapshot, OrderBookRecord, RegimeClassifier,
+    Timeframe, TimestampMS,
+};
+
+use super::market_data_source::InMemoryDataSource;
+use super::snapshot_extractor::{GapFillPolicy, HistoricalSnapshotExtractor, INDICATOR_WARMUP};
+
+/// Candles kept per timeframe: the indicator warmup plus the series lookback
+const CANDLE_RETENTION: i64 = INDICATOR_WARMUP + 12;
+
+/// Indicator, derivatives and microstructure records kept (covers the 36h
+/// 4h series lookback and the 24h open interest average)
+const RECORD_RETENTION_MS: i64 = 48 * 60 * 60_000;
+
+/// Builds fully populated snapshots from a live stream of candle and indicator updates
+///
+/// Updates go into rolling in-memory buffers, and each 3m close is turned into
+/// a snapshot by the same extractor used for historical ingestion, so live
+/// snapshots carry the same series, slopes, volume and context blocks as the
+/// stored patterns. Indicators that are not supplied are recomputed from the
+/// buffered candles. Buffers are trimmed to what a snapshot reads.
+pub struct LiveSnapshotBuilder {
+    symbol: String,
+    extractor: HistoricalSnapshotExtractor<InMemoryDataSource>,
+    indicators_4h: Option<Indicators4h>,
+    open_interest: Option<f64>,
+    funding_rate: Option<f64>,
+    last_close_ts: Option<i64>,
+}
+
+impl LiveSnapshotBuilder {
+    /// Create a builder for one symbol with empty buffers
+    pub fn new(symbol: impl Into<String>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            extractor: HistoricalSnapshotExtractor::with_source(InMemoryDataSource::new()),
+            indicators_4h: None,
+            open_interest: None,
+            funding_rate: None,
+            last_close_ts: None,
+        }
+    }
+
+    /// Set how missing time series points are handled
+    pub fn with_gap_policy(mut self, gap_policy: GapFillPolicy) -> Self {
+        self.extractor = self.extractor.with_gap_policy(gap_policy);
+        self
+    }
+
+    /// Set the thresholds snapshots are labelled with
+    pub fn with_regime_classifier(mut self, regime_classifier: RegimeClassifier) -> Self {
+        self.extractor = self.extractor.with_regime_classifier(regime_classifier);
+        self
+    }
+
+    /// The symbol snapshots are built for
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// The buffered market data
+    pub fn source(&self) -> &InMemoryDataSource {
+        self.extractor.source()
+    }
+
+    /// Record a 4h candle (keyed by open time) and, if known, the indicators after it
+    ///
+    /// The indicators apply to every following 3m close until the next update.
+    /// Without them, 4h indicators are recomputed from the buffered 4h candles.
+    pub fn update_4h(&mut self, open_ts: i64, candle: Candle, indicators: Option<Indicators4h>) {
+        self.update_candle(Timeframe::H4, open_ts, candle);
+        if indicators.is_some() {
+            self.indicators_4h = indicators;
+        }
+    }
+
+    /// Record a candle of any timeframe, e.g. 1h or 1d for the context blocks
+    pub fn update_candle(&mut self, timeframe: Timeframe, open_ts: i64, candle: Candle) {
+        let symbol = self.symbol.clone();
+        self.extractor.source_mut().insert_candle(&symbol, timeframe, open_ts, candle);
+    }
+
+    /// Record the latest open interest (applies to following 3m closes)
+    pub fn update_open_interest(&mut self, open_interest: f64) {
+        self.open_interest = Some(open_interest);
+    }
+
+    /// Record the latest funding rate (applies to following 3m closes)
+    pub fn update_funding_rate(&mut self, funding_rate: f64) {
+        self.funding_rate = Some(funding_rate);
+    }
+
+    /// Record a top-of-book snapshot
+    pub fn update_order_book(&mut self, timestamp_ms: i64, order_book: OrderBookRecord) {
+        let symbol = self.symbol.clone();
+        self.extractor.source_mut().insert_order_book(&symbol, timestamp_ms, order_book);
+    }
+
+    /// Record the taker volumes of a 3m candle (keyed by open time)
+    pub fn update_trade_flow(&mut self, open_ts: i64, taker_buy_volume: f64, taker_sell_volume: f64) {
+        let symbol = self.symbol.clone();
+        self.extractor
+            .source_mut()
+            .insert_trade_flow(&symbol, open_ts, taker_buy_volume, taker_sell_volume);
+    }
+
+    /// Record the liquidated volumes of a 3m candle (keyed by open time)
+    pub fn update_liquidations(&mut self, open_ts: i64, long_liquidations: f64, short_liquidations: f64) {
+        let symbol = self.symbol.clone();
+        self.extractor
+            .source_mut()
+            .insert_liquidations(&symbol, open_ts, long_liquidations, short_liquidations);
+    }
+
+    /// Record a closed 3m candle and build the snapshot for it
+    ///
+    /// # Arguments
+    /// * `open_ts` - Open time of the candle (the snapshot timestamp)
+    /// * `candle` - The closed candle
+    /// * `indicators` - 3m indicators after the candle, or `None` to recompute them
+    ///
+    /// # Returns
+    /// The snapshot at `open_ts` with outcomes pending. Fails for a close that
+    /// is not after the previous one, or while the buffers are too short for
+    /// the indicators (e.g. right after startup).
+    pub fn close_3m(
+        &mut self,
+        open_ts: i64,
+        candle: Candle,
+        indicators: Option<Indicators3m>,
+    ) -> Result<MarketStateSnapshot> {
+        if let Some(last_ts) = self.last_close_ts.filter(|last_ts| open_ts <= *last_ts) {
+            return Err(anyhow!(
+                "3m close at {} for {} is not after the previous close at {}",
+                open_ts,
+                self.symbol,
+                last_ts
+            ));
+        }
+        self.last_close_ts = Some(open_ts);
+
+        let symbol = self.symbol.clone();
+        let source = self.extractor.source_mut();
+        source.insert_candle_3m(&symbol, open_ts, candle);
+        if let Some(indicators) = indicators {
+            source.insert_indicators_3m(&symbol, open_ts, indicators);
+        }
+        // Latest values carried onto the 3m grid, as stored historically
+        if let Some(indicators_4h) = self.indicators_4h {
+            source.insert_indicators_4h(&symbol, open_ts, indicators_4h);
+        }
+        if let Some(open_interest) = self.open_interest {
+            source.insert_open_interest(&symbol, open_ts, open_interest);
+        }
+        if let Some(funding_rate) = self.funding_rate {
+            source.insert_funding_rate(&symbol, open_ts, funding_rate);
+        }
+
+        for timeframe in Timeframe::ALL {
+            source.prune_candles(&symbol, timeframe, open_ts - CANDLE_RETENTION * timeframe.duration_ms());
+        }
+        source.prune_records(&symbol, open_ts - RECORD_RETENTION_MS);
+
+        self.extractor
+            .extract_snapshot_at(&symbol, open_ts as TimestampMS, open_ts)
+            .with_context(|| format!("Failed to build live snapshot for {} at {}", symbol, open_ts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::market_data_source::MarketDataSource;
+
+    const INTERVAL_3M_MS: i64 = 180_000;
+    const FOUR_HOURS_MS: i64 = 4 * 60 * 60_000;
+
+    fn candle(close: f64, volume: f64) -> Candle {
+        Candle { open: close - 0.5, high: close + 1.0, low: close - 1.0, close, volume, trades: 10 }
+    }
+
+    #[test]
+    fn test_live_snapshots_match_historical_extraction() {
+        let base_ts = 1_700_006_400_000i64; // 4h aligned
+        let mut builder = LiveSnapshotBuilder::new("BTCUSDT");
+        let mut history = InMemoryDataSource::new();
+
+        // 60 closed 4h candles, then 12h of 3m candles
+        for i in 0..60i64 {
+            let c = candle(100.0 + (i as f64 * 0.3).sin() * 5.0 + i as f64 * 0.1, 1000.0 + i as f64);
+            builder.update_4h(base_ts + i * FOUR_HOURS_MS, c, None);
+            history.insert_candle_4h("BTCUSDT", base_ts + i * FOUR_HOURS_MS, c);
+        }
+        builder.update_open_interest(5_000.0);
+
+        let start_3m = base_ts + 57 * FOUR_HOURS_MS;
+        let mut snapshot = None;
+        for i in 0..240i64 {
+            let ts = start_3m + i * INTERVAL_3M_MS;
+            let c = candle(105.0 + (i as f64 * 0.2).cos() * 2.0, 10.0 + i as f64);
+            history.insert_candle_3m("BTCUSDT", ts, c);
+            match builder.close_3m(ts, c, None) {
+                Ok(built) => snapshot = Some(built),
+                // Not enough candles yet for the 3m indicators
+                Err(_) => assert!(i < 50, "close {} failed", i),
+            }
+        }
+        let snapshot = snapshot.unwrap();
+        let last_ts = start_3m + 239 * INTERVAL_3M_MS;
+
+        assert_eq!(snapshot.timestamp, last_ts as u64);
+        assert_eq!(snapshot.mid_prices.len(), 10);
+        assert_eq!(snapshot.rsi_14_4h_values.len(), 10);
+        assert!(snapshot.missing_points_3m.is_empty() && snapshot.missing_points_4h.is_empty());
+        assert!(snapshot.macd_slope() != 0.0);
+        assert!(snapshot.avg_volume_4h > 0.0);
+        assert!(snapshot.price_change_4h != 0.0);
+        assert!(snapshot.outcomes_pending);
+        assert!(snapshot.has_open_interest && !snapshot.has_funding_rate);
+        assert!(snapshot.regime.is_some());
+
+        // Same indicators and series as extracting the full history
+        let expected = HistoricalSnapshotExtractor::with_source(history)
+            .extract_snapshot_at("BTCUSDT", last_ts as u64, last_ts)
+            .unwrap();
+        assert_eq!(snapshot.mid_prices, expected.mid_prices);
+        assert_eq!(snapshot.rsi_7_values, expected.rsi_7_values);
+        assert_eq!(snapshot.macd_4h_values, expected.macd_4h_values);
+        assert_eq!(snapshot.ema_20_4h, expected.ema_20_4h);
+        assert_eq!(snapshot.avg_volume_4h, expected.avg_volume_4h);
+
+        // Buffers stay bounded
+        let candles_3m = builder.source().candles_3m("BTCUSDT", 0, i64::MAX).unwrap();
+        assert!(candles_3m.len() as i64 <= CANDLE_RETENTION + 1);
+        assert_eq!(candles_3m.last().unwrap().0, last_ts);
+    }
+
+    #[test]
+    fn test_out_of_order_close_rejected() {
+        let mut builder = LiveSnapshotBuilder::new("ETHUSDT");
+        let indicators = Indicators3m { ema_20: 100.0, ema_50: 100.0, macd: 0.0, rsi_7: 50.0, rsi_14: 50.0, atr_14: 1.0, avg_volume_20: None };
+        builder.update_4h(
+            0,
+            candle(100.0, 1.0),
+            Some(Indicators4h { ema_20: 100.0, ema_50: 100.0, macd: 0.0, rsi_14: 50.0, atr_3: 1.0, atr_14: 2.0, avg_volume_20: None }),
+        );
+
+        let snapshot = builder.close_3m(1_800_000, candle(100.0, 1.0), Some(indicators)).unwrap();
+        assert_eq!(snapshot.rsi_14, 50.0);
+        assert_eq!(snapshot.ema_20_4h, 100.0);
+
+        let err = builder.close_3m(1_800_000, candle(100.0, 1.0), Some(indicators)).unwrap_err();
+        assert!(err.to_string().contains("not after the previous close"), "{}", err);
+    }
+}
@@ -1,8 +1,16 @@
 use anyhow::{anyhow, Context, Result};
-use lmdb::{Database, Environment, Transaction};
-use serde_json::Value;
+use lmdb::{Cursor, Database, Environment, RoCursor, RoTransaction, Transaction};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
 use std::path::Path;
 use tracing;
+use trading_core::{
+    Candle, FundingRateRecord, Indicators3m, Indicators4h, LiquidationRecord, OpenInterestRecord,
+    OrderBookRecord, Timeframe, TradeFlowRecord,
+};
+
+use super::market_data_source::MarketDataSource;
 
 /// LMDB reader for historical market data from llm-trader-data
 ///
@@ -15,25 +23,78 @@ use tracing;
 /// - indicators_3m: 3-minute technical indicators
 /// - indicators_4h: 4-hour technical indicators
 ///
+/// Candle databases of other timeframes (`candles_{timeframe}`, e.g.
+/// candles_1h, candles_1d) are configurable, see
+/// [`LmdbReader::with_timeframes`], and opened when present.
+///
+/// Optional derivatives databases (opened when present):
+/// - open_interest: `{"open_interest": f64}` on the 3m grid
+/// - funding_rate: `{"funding_rate": f64}` on the 3m grid
+///
+/// Optional microstructure databases (opened when present):
+/// - order_book: `{"bid_price", "bid_size", "ask_price", "ask_size"}` top of book
+/// - trade_flow: `{"taker_buy_volume", "taker_sell_volume"}` per 3m candle
+/// - liquidations: `{"long_liquidations", "short_liquidations"}` per 3m candle
+///
 /// Key format: {symbol}:{timestamp_ms}
-/// Value format: JSON serialized dict
+/// Value format: JSON serialized dict, decoded into the typed records from
+/// `trading_core` ([`Candle`], [`Indicators3m`], [`Indicators4h`], ...)
+///
+/// Keys sort lexicographically, so timestamps of different decimal lengths
+/// interleave. Range reads go through [`RangeScan`], which accounts for this.
+#[derive(Debug)]
 pub struct LmdbReader {
     env: Environment,
-    db_candles_3m: Database,
-    db_candles_4h: Database,
+    candle_dbs: BTreeMap<Timeframe, Database>,
     db_indicators_3m: Database,
     db_indicators_4h: Database,
+    db_open_interest: Option<Database>,
+    db_funding_rate: Option<Database>,
+    db_order_book: Option<Database>,
+    db_trade_flow: Option<Database>,
+    db_liquidations: Option<Database>,
 }
 
+/// Candle timeframes opened by [`LmdbReader::new`]
+pub const DEFAULT_CANDLE_TIMEFRAMES: &[Timeframe] = &[Timeframe::M3, Timeframe::H1, Timeframe::H4, Timeframe::D1];
+
+/// Candle timeframes every llm-trader-data deployment writes
+const REQUIRED_CANDLE_TIMEFRAMES: [Timeframe; 2] = [Timeframe::M3, Timeframe::H4];
+
+const INDICATORS_3M: &str = "indicators_3m";
+const INDICATORS_4H: &str = "indicators_4h";
+const OPEN_INTEREST: &str = "open_interest";
+const FUNDING_RATE: &str = "funding_rate";
+const ORDER_BOOK: &str = "order_book";
+const TRADE_FLOW: &str = "trade_flow";
+const LIQUIDATIONS: &str = "liquidations";
+
 impl LmdbReader {
     /// Open LMDB environment in read-only mode
     ///
+    /// Opens the candle databases of [`DEFAULT_CANDLE_TIMEFRAMES`].
+    ///
     /// # Arguments
     /// * `db_path` - Path to LMDB directory (shared with llm-trader-data)
     ///
     /// # Returns
     /// LMDB reader instance with all databases opened
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        Self::with_timeframes(db_path, DEFAULT_CANDLE_TIMEFRAMES)
+    }
+
+    /// Open LMDB environment in read-only mode with a chosen set of candle timeframes
+    ///
+    /// The 3m and 4h candle databases are always opened and must exist. Other
+    /// timeframes are opened when present; reads of a missing one return nothing.
+    ///
+    /// # Arguments
+    /// * `db_path` - Path to LMDB directory (shared with llm-trader-data)
+    /// * `timeframes` - Extra candle timeframes to open (`candles_{timeframe}`)
+    ///
+    /// # Returns
+    /// LMDB reader instance with all databases opened
+    pub fn with_timeframes<P: AsRef<Path>>(db_path: P, timeframes: &[Timeframe]) -> Result<Self> {
         let db_path = db_path.as_ref();
 
         // Verify path exists
@@ -48,39 +109,128 @@ impl LmdbReader {
 
         // Open environment in read-only mode
         let env = Environment::new()
-            .set_max_dbs(10)
+            .set_max_dbs(16)
             .set_flags(lmdb::EnvironmentFlags::READ_ONLY)
             .open(db_path)
             .context("Failed to open LMDB environment")?;
 
         // Open all named databases
-        let db_candles_3m = env
-            .open_db(Some("candles_3m"))
-            .context("Failed to open candles_3m database")?;
-
-        let db_candles_4h = env
-            .open_db(Some("candles_4h"))
-            .context("Failed to open candles_4h database")?;
+        let mut candle_dbs = BTreeMap::new();
+        for timeframe in REQUIRED_CANDLE_TIMEFRAMES {
+            let db = env
+                .open_db(Some(timeframe.candles_db()))
+                .with_context(|| format!("Failed to open {} database", timeframe.candles_db()))?;
+            candle_dbs.insert(timeframe, db);
+        }
 
         let db_indicators_3m = env
-            .open_db(Some("indicators_3m"))
+            .open_db(Some(INDICATORS_3M))
             .context("Failed to open indicators_3m database")?;
 
         let db_indicators_4h = env
-            .open_db(Some("indicators_4h"))
+            .open_db(Some(INDICATORS_4H))
             .context("Failed to open indicators_4h database")?;
 
-        tracing::info!("Successfully opened all 4 LMDB databases");
+        // Other candle timeframes are optional
+        for timeframe in timeframes {
+            if candle_dbs.contains_key(timeframe) {
+                continue;
+            }
+            if let Some(db) = Self::open_optional_db(&env, timeframe.candles_db())? {
+                candle_dbs.insert(*timeframe, db);
+            }
+        }
+
+        // Derivatives databases are optional (not every llm-trader-data deployment writes them)
+        let db_open_interest = Self::open_optional_db(&env, OPEN_INTEREST)?;
+        let db_funding_rate = Self::open_optional_db(&env, FUNDING_RATE)?;
+
+        // Microstructure feeds are newer still
+        let db_order_book = Self::open_optional_db(&env, ORDER_BOOK)?;
+        let db_trade_flow = Self::open_optional_db(&env, TRADE_FLOW)?;
+        let db_liquidations = Self::open_optional_db(&env, LIQUIDATIONS)?;
+
+        let mut opened: Vec<&str> = candle_dbs.keys().map(|timeframe| timeframe.candles_db()).collect();
+        opened.extend([INDICATORS_3M, INDICATORS_4H]);
+        for (name, db) in [
+            (OPEN_INTEREST, &db_open_interest),
+            (FUNDING_RATE, &db_funding_rate),
+            (ORDER_BOOK, &db_order_book),
+            (TRADE_FLOW, &db_trade_flow),
+            (LIQUIDATIONS, &db_liquidations),
+        ] {
+            if db.is_some() {
+                opened.push(name);
+            }
+        }
+        tracing::info!("Opened {} LMDB databases: {}", opened.len(), opened.join(", "));
 
         Ok(Self {
             env,
-            db_candles_3m,
-            db_candles_4h,
+            candle_dbs,
             db_indicators_3m,
             db_indicators_4h,
+            db_open_interest,
+            db_funding_rate,
+            db_order_book,
+            db_trade_flow,
+            db_liquidations,
         })
     }
 
+    /// Open a named database that may not exist
+    ///
+    /// # Returns
+    /// `None` if the database is absent, error for any other LMDB failure
+    fn open_optional_db(env: &Environment, name: &str) -> Result<Option<Database>> {
+        match env.open_db(Some(name)) {
+            Ok(db) => Ok(Some(db)),
+            Err(lmdb::Error::NotFound) => {
+                tracing::warn!(
+                    "Optional LMDB database {} not found; its fields will be marked unavailable",
+                    name
+                );
+                Ok(None)
+            }
+            Err(e) => Err(anyhow!("Failed to open {} database: {}", name, e)),
+        }
+    }
+
+    /// Candle timeframes whose database is available, shortest first
+    pub fn timeframes(&self) -> impl Iterator<Item = Timeframe> + '_ {
+        self.candle_dbs.keys().copied()
+    }
+
+    /// Whether the candle database of a timeframe is available
+    pub fn has_timeframe(&self, timeframe: Timeframe) -> bool {
+        self.candle_dbs.contains_key(&timeframe)
+    }
+
+    /// Whether the open interest database is available
+    pub fn has_open_interest(&self) -> bool {
+        self.db_open_interest.is_some()
+    }
+
+    /// Whether the funding rate database is available
+    pub fn has_funding_rate(&self) -> bool {
+        self.db_funding_rate.is_some()
+    }
+
+    /// Whether the order book database is available
+    pub fn has_order_book(&self) -> bool {
+        self.db_order_book.is_some()
+    }
+
+    /// Whether the trade flow database is available
+    pub fn has_trade_flow(&self) -> bool {
+        self.db_trade_flow.is_some()
+    }
+
+    /// Whether the liquidations database is available
+    pub fn has_liquidations(&self) -> bool {
+        self.db_liquidations.is_some()
+    }
+
     /// Generate LMDB key from symbol and timestamp
     ///
     /// # Arguments
@@ -93,30 +243,42 @@ impl LmdbReader {
         format!("{}:{}", symbol, timestamp_ms)
     }
 
-    /// Read indicators from specified database
+    /// Warn about every expected grid point in `[from_ms, until_ms)`
+    fn warn_missing(timeframe: &str, symbol: &str, from_ms: i64, until_ms: i64, interval_ms: i64) {
+        let mut timestamp = from_ms;
+        while timestamp < until_ms {
+            tracing::warn!(
+                "Missing {} indicator data for {} at timestamp {}",
+                timeframe,
+                symbol,
+                timestamp
+            );
+            timestamp += interval_ms;
+        }
+    }
+
+    /// Read one record from specified database
     ///
     /// # Arguments
     /// * `db` - Database to query
+    /// * `db_name` - Database name, used in error messages
     /// * `symbol` - Trading pair symbol
     /// * `timestamp_ms` - Unix timestamp in milliseconds
     ///
     /// # Returns
-    /// JSON value with indicators, or None if not found
-    fn read_indicators(
+    /// Decoded record, or None if not found
+    fn read_record<T: DeserializeOwned>(
         &self,
         db: Database,
+        db_name: &str,
         symbol: &str,
         timestamp_ms: i64,
-    ) -> Result<Option<Value>> {
+    ) -> Result<Option<T>> {
         let txn = self.env.begin_ro_txn().context("Failed to begin read transaction")?;
         let key = Self::make_key(symbol, timestamp_ms);
 
         match txn.get(db, &key) {
-            Ok(bytes) => {
-                let json: Value = serde_json::from_slice(bytes)
-                    .context("Failed to deserialize indicator JSON")?;
-                Ok(Some(json))
-            }
+            Ok(bytes) => decode_record(db_name, key.as_bytes(), bytes).map(Some),
             Err(lmdb::Error::NotFound) => Ok(None),
             Err(e) => Err(anyhow!("LMDB read error: {}", e)),
         }
@@ -129,9 +291,9 @@ impl LmdbReader {
     /// * `timestamp_ms` - Unix timestamp in milliseconds
     ///
     /// # Returns
-    /// JSON object with fields: ema_20, ema_50, macd, rsi_7, rsi_14, atr_14
-    pub fn read_indicators_3m(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Value>> {
-        self.read_indicators(self.db_indicators_3m, symbol, timestamp_ms)
+    /// Indicators with fields: ema_20, ema_50, macd, rsi_7, rsi_14, atr_14
+    pub fn read_indicators_3m(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators3m>> {
+        self.read_record(self.db_indicators_3m, INDICATORS_3M, symbol, timestamp_ms)
     }
 
     /// Read 4-hour indicators for a specific timestamp
@@ -141,9 +303,26 @@ impl LmdbReader {
     /// * `timestamp_ms` - Unix timestamp in milliseconds
     ///
     /// # Returns
-    /// JSON object with fields: ema_20, ema_50, macd, rsi_14, atr_3, atr_14
-    pub fn read_indicators_4h(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Value>> {
-        self.read_indicators(self.db_indicators_4h, symbol, timestamp_ms)
+    /// Indicators with fields: ema_20, ema_50, macd, rsi_14, atr_3, atr_14
+    pub fn read_indicators_4h(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators4h>> {
+        self.read_record(self.db_indicators_4h, INDICATORS_4H, symbol, timestamp_ms)
+    }
+
+    /// Read one candle of any timeframe
+    ///
+    /// # Arguments
+    /// * `timeframe` - Candle timeframe
+    /// * `symbol` - Trading pair symbol
+    /// * `timestamp_ms` - Candle open time in milliseconds
+    ///
+    /// # Returns
+    /// Candle with OHLCV data and trade count, or None if not found or the
+    /// timeframe's database is unavailable (see [`LmdbReader::has_timeframe`])
+    pub fn read_candle(&self, timeframe: Timeframe, symbol: &str, timestamp_ms: i64) -> Result<Option<Candle>> {
+        match self.candle_dbs.get(&timeframe) {
+            Some(db) => self.read_record(*db, timeframe.candles_db(), symbol, timestamp_ms),
+            None => Ok(None),
+        }
     }
 
     /// Read 3-minute candle data
@@ -153,9 +332,9 @@ impl LmdbReader {
     /// * `timestamp_ms` - Unix timestamp in milliseconds
     ///
     /// # Returns
-    /// JSON object with OHLCV data
-    pub fn read_candles_3m(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Value>> {
-        self.read_indicators(self.db_candles_3m, symbol, timestamp_ms)
+    /// Candle with OHLCV data and trade count
+    pub fn read_candles_3m(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Candle>> {
+        self.read_candle(Timeframe::M3, symbol, timestamp_ms)
     }
 
     /// Read 4-hour candle data
@@ -165,9 +344,222 @@ impl LmdbReader {
     /// * `timestamp_ms` - Unix timestamp in milliseconds
     ///
     /// # Returns
-    /// JSON object with OHLCV data
-    pub fn read_candles_4h(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Value>> {
-        self.read_indicators(self.db_candles_4h, symbol, timestamp_ms)
+    /// Candle with OHLCV data and trade count
+    pub fn read_candles_4h(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Candle>> {
+        self.read_candle(Timeframe::H4, symbol, timestamp_ms)
+    }
+
+    /// Read open interest for a specific timestamp
+    ///
+    /// # Returns
+    /// Open interest record, or None if not found or the database is
+    /// unavailable (see [`LmdbReader::has_open_interest`])
+    pub fn read_open_interest(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<OpenInterestRecord>> {
+        match self.db_open_interest {
+            Some(db) => self.read_record(db, OPEN_INTEREST, symbol, timestamp_ms),
+            None => Ok(None),
+        }
+    }
+
+    /// Read funding rate for a specific timestamp
+    ///
+    /// # Returns
+    /// Funding rate record, or None if not found or the database is
+    /// unavailable (see [`LmdbReader::has_funding_rate`])
+    pub fn read_funding_rate(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<FundingRateRecord>> {
+        match self.db_funding_rate {
+            Some(db) => self.read_record(db, FUNDING_RATE, symbol, timestamp_ms),
+            None => Ok(None),
+        }
+    }
+
+    /// Begin a read-only transaction for range scans
+    ///
+    /// All `scan_*` iterators created from the same transaction see one
+    /// consistent view of the database.
+    pub fn begin_read(&self) -> Result<RoTransaction<'_>> {
+        self.env.begin_ro_txn().context("Failed to begin read transaction")
+    }
+
+    /// Scan all candles of a timeframe for a symbol in a time range
+    ///
+    /// # Arguments
+    /// * `txn` - Transaction from [`LmdbReader::begin_read`]
+    /// * `timeframe` - Candle timeframe
+    /// * `symbol` - Trading pair symbol
+    /// * `start_ms` - Start timestamp (inclusive)
+    /// * `end_ms` - End timestamp (inclusive)
+    ///
+    /// # Returns
+    /// Iterator of (timestamp, candle) tuples, ordered from oldest to newest.
+    /// Records that fail to decode are yielded as errors naming the key.
+    /// Yields nothing when the timeframe's database is unavailable.
+    pub fn scan_candles<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        timeframe: Timeframe,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, Candle>> {
+        let db = self.candle_dbs.get(&timeframe).copied();
+        RangeScan::new(txn, db, timeframe.candles_db(), symbol, start_ms, end_ms)
+    }
+
+    /// Scan all 3-minute candles for a symbol in a time range
+    ///
+    /// See [`LmdbReader::scan_candles`].
+    pub fn scan_candles_3m<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, Candle>> {
+        self.scan_candles(txn, Timeframe::M3, symbol, start_ms, end_ms)
+    }
+
+    /// Scan all 4-hour candles for a symbol in a time range
+    ///
+    /// See [`LmdbReader::scan_candles`].
+    pub fn scan_candles_4h<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, Candle>> {
+        self.scan_candles(txn, Timeframe::H4, symbol, start_ms, end_ms)
+    }
+
+    /// Scan all 3-minute indicators for a symbol in a time range
+    ///
+    /// See [`LmdbReader::scan_candles_3m`].
+    pub fn scan_indicators_3m<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, Indicators3m>> {
+        RangeScan::new(txn, Some(self.db_indicators_3m), INDICATORS_3M, symbol, start_ms, end_ms)
+    }
+
+    /// Scan all 4-hour indicators for a symbol in a time range
+    ///
+    /// See [`LmdbReader::scan_candles_3m`].
+    pub fn scan_indicators_4h<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, Indicators4h>> {
+        RangeScan::new(txn, Some(self.db_indicators_4h), INDICATORS_4H, symbol, start_ms, end_ms)
+    }
+
+    /// Scan open interest for a symbol in a time range
+    ///
+    /// Yields nothing when the database is unavailable.
+    pub fn scan_open_interest<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, OpenInterestRecord>> {
+        RangeScan::new(txn, self.db_open_interest, OPEN_INTEREST, symbol, start_ms, end_ms)
+    }
+
+    /// Scan funding rates for a symbol in a time range
+    ///
+    /// Yields nothing when the database is unavailable.
+    pub fn scan_funding_rate<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, FundingRateRecord>> {
+        RangeScan::new(txn, self.db_funding_rate, FUNDING_RATE, symbol, start_ms, end_ms)
+    }
+
+    /// Scan top-of-book snapshots for a symbol in a time range
+    ///
+    /// Yields nothing when the database is unavailable.
+    pub fn scan_order_book<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, OrderBookRecord>> {
+        RangeScan::new(txn, self.db_order_book, ORDER_BOOK, symbol, start_ms, end_ms)
+    }
+
+    /// Scan taker buy/sell volumes for a symbol in a time range
+    ///
+    /// Yields nothing when the database is unavailable.
+    pub fn scan_trade_flow<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, TradeFlowRecord>> {
+        RangeScan::new(txn, self.db_trade_flow, TRADE_FLOW, symbol, start_ms, end_ms)
+    }
+
+    /// Scan liquidated volumes for a symbol in a time range
+    ///
+    /// Yields nothing when the database is unavailable.
+    pub fn scan_liquidations<'txn>(
+        &self,
+        txn: &'txn RoTransaction<'_>,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<RangeScan<'txn, LiquidationRecord>> {
+        RangeScan::new(txn, self.db_liquidations, LIQUIDATIONS, symbol, start_ms, end_ms)
+    }
+
+    /// Average open interest over a trailing window
+    ///
+    /// # Arguments
+    /// * `symbol` - Trading pair symbol
+    /// * `end_timestamp_ms` - End timestamp (inclusive)
+    /// * `window_ms` - Window length (e.g., 86_400_000 for 24h)
+    /// * `interval_ms` - Interval between data points (e.g., 180_000 for 3m)
+    ///
+    /// # Returns
+    /// Mean of the available points, or None if no points were found
+    pub fn read_open_interest_avg(
+        &self,
+        symbol: &str,
+        end_timestamp_ms: i64,
+        window_ms: i64,
+        interval_ms: i64,
+    ) -> Result<Option<f64>> {
+        if self.db_open_interest.is_none() || interval_ms <= 0 {
+            return Ok(None);
+        }
+
+        let txn = self.begin_read()?;
+        let mut sum = 0.0;
+        let mut count = 0usize;
+        let start_ms = end_timestamp_ms - window_ms + interval_ms;
+
+        for entry in self.scan_open_interest(&txn, symbol, start_ms, end_timestamp_ms)? {
+            let (_, record) = entry?;
+            sum += record.open_interest;
+            count += 1;
+        }
+
+        if count == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(sum / count as f64))
+        }
     }
 
     /// Read time series of 3-minute indicators
@@ -188,23 +580,27 @@ impl LmdbReader {
         end_timestamp_ms: i64,
         interval_ms: i64,
         count: usize,
-    ) -> Result<Vec<(i64, Value)>> {
-        let mut results = Vec::with_capacity(count);
+    ) -> Result<Vec<(i64, Indicators3m)>> {
+        if count == 0 || interval_ms <= 0 {
+            return Ok(Vec::new());
+        }
 
-        for i in (0..count).rev() {
-            let offset = i as i64 * interval_ms;
-            let timestamp = end_timestamp_ms - offset;
+        let txn = self.begin_read()?;
+        let start_ms = end_timestamp_ms - (count as i64 - 1) * interval_ms;
+        let mut results = Vec::with_capacity(count);
+        let mut expected = start_ms;
 
-            if let Some(data) = self.read_indicators_3m(symbol, timestamp)? {
-                results.push((timestamp, data));
-            } else {
-                tracing::warn!(
-                    "Missing 3m indicator data for {} at timestamp {}",
-                    symbol,
-                    timestamp
-                );
+        // Keep only the points on the expected grid, warning for each gap
+        for entry in self.scan_indicators_3m(&txn, symbol, start_ms, end_timestamp_ms)? {
+            let (timestamp, data) = entry?;
+            if (timestamp - start_ms) % interval_ms != 0 {
+                continue;
             }
+            Self::warn_missing("3m", symbol, expected, timestamp, interval_ms);
+            results.push((timestamp, data));
+            expected = timestamp + interval_ms;
         }
+        Self::warn_missing("3m", symbol, expected, end_timestamp_ms + interval_ms, interval_ms);
 
         Ok(results)
     }
@@ -225,40 +621,45 @@ impl LmdbReader {
         end_timestamp_ms: i64,
         interval_ms: i64,
         count: usize,
-    ) -> Result<Vec<(i64, Value)>> {
-        let mut results = Vec::with_capacity(count);
+    ) -> Result<Vec<(i64, Indicators4h)>> {
+        if count == 0 || interval_ms <= 0 {
+            return Ok(Vec::new());
+        }
 
-        for i in (0..count).rev() {
-            let offset = i as i64 * interval_ms;
-            let timestamp = end_timestamp_ms - offset;
+        let txn = self.begin_read()?;
+        let start_ms = end_timestamp_ms - (count as i64 - 1) * interval_ms;
+        let mut results = Vec::with_capacity(count);
+        let mut expected = start_ms;
 
-            if let Some(data) = self.read_indicators_4h(symbol, timestamp)? {
-                results.push((timestamp, data));
-            } else {
-                tracing::warn!(
-                    "Missing 4h indicator data for {} at timestamp {}",
-                    symbol,
-                    timestamp
-                );
+        // Keep only the points on the expected grid, warning for each gap
+        for entry in self.scan_indicators_4h(&txn, symbol, start_ms, end_timestamp_ms)? {
+            let (timestamp, data) = entry?;
+            if (timestamp - start_ms) % interval_ms != 0 {
+                continue;
             }
+            Self::warn_missing("4h", symbol, expected, timestamp, interval_ms);
+            results.push((timestamp, data));
+            expected = timestamp + interval_ms;
         }
+        Self::warn_missing("4h", symbol, expected, end_timestamp_ms + interval_ms, interval_ms);
 
         Ok(results)
     }
 
-    /// Generate timestamps for a time range based on interval
+    /// List timestamps with 3-minute indicator data in a time range
     ///
-    /// This generates expected timestamps and filters to those with data available.
-    /// More efficient than scanning the entire database.
+    /// Scans the range with a cursor, so records off the expected grid are
+    /// found as well. Timestamps closer than `interval_ms` to the previously
+    /// returned one are skipped; pass 0 to return every record.
     ///
     /// # Arguments
     /// * `symbol` - Trading pair symbol
     /// * `start_ms` - Start timestamp (inclusive)
     /// * `end_ms` - End timestamp (inclusive)
-    /// * `interval_ms` - Interval in milliseconds (e.g., 180_000 for 3m)
+    /// * `interval_ms` - Minimum spacing in milliseconds (e.g., 180_000 for 3m)
     ///
     /// # Returns
-    /// Vector of timestamps with available data
+    /// Vector of timestamps with available data, in ascending order
     pub fn query_timestamps_3m(
         &self,
         symbol: &str,
@@ -266,15 +667,15 @@ impl LmdbReader {
         end_ms: i64,
         interval_ms: i64,
     ) -> Result<Vec<i64>> {
-        let mut timestamps = Vec::new();
-        let mut current = start_ms;
+        let txn = self.begin_read()?;
+        let mut timestamps: Vec<i64> = Vec::new();
 
-        while current <= end_ms {
-            // Check if data exists at this timestamp
-            if self.read_indicators_3m(symbol, current)?.is_some() {
-                timestamps.push(current);
+        for entry in self.scan_indicators_3m(&txn, symbol, start_ms, end_ms)? {
+            let (timestamp, _) = entry?;
+            if timestamps.last().is_some_and(|last| timestamp - last < interval_ms) {
+                continue;
             }
-            current += interval_ms;
+            timestamps.push(timestamp);
         }
 
         tracing::debug!(
@@ -290,6 +691,273 @@ impl LmdbReader {
     }
 }
 
+impl MarketDataSource for LmdbReader {
+    fn name(&self) -> &str {
+        "lmdb"
+    }
+
+    fn candles_3m(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_candles_3m(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn candles_4h(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_candles_4h(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn candles(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Candle)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_candles(&txn, timeframe, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn indicators_3m(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators3m)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_indicators_3m(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn indicators_4h(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators4h)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_indicators_4h(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn open_interest(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OpenInterestRecord)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_open_interest(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn funding_rate(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, FundingRateRecord)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_funding_rate(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn order_book(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OrderBookRecord)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_order_book(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn trade_flow(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, TradeFlowRecord)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_trade_flow(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn liquidations(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, LiquidationRecord)>> {
+        let txn = self.begin_read()?;
+        let records = self.scan_liquidations(&txn, symbol, start_ms, end_ms)?.collect();
+        records
+    }
+
+    fn indicators_3m_at(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators3m>> {
+        self.read_indicators_3m(symbol, timestamp_ms)
+    }
+
+    fn indicators_4h_at(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators4h>> {
+        self.read_indicators_4h(symbol, timestamp_ms)
+    }
+}
+
+/// Decode a JSON record, naming the database and key on schema errors
+fn decode_record<T: DeserializeOwned>(db_name: &str, key: &[u8], bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| {
+        anyhow!(
+            "Invalid {} record at key {}: {}",
+            db_name,
+            String::from_utf8_lossy(key),
+            e
+        )
+    })
+}
+
+/// Number of decimal digits in a non-negative timestamp
+fn decimal_digits(value: i64) -> usize {
+    value.max(0).to_string().len()
+}
+
+/// Cursor-based scan over `{symbol}:{timestamp_ms}` keys in a time range
+///
+/// Keys compare as strings, so `BTCUSDT:999` sorts after `BTCUSDT:1000`. The
+/// scan walks one decimal length at a time: for each length it seeks to the
+/// lower bound and stops once keys pass the upper bound of that length, which
+/// keeps the output in numeric order. Negative timestamps are never matched.
+///
+/// Created by the `scan_*` methods on [`LmdbReader`].
+#[derive(Debug)]
+pub struct RangeScan<'txn, T> {
+    cursor: Option<RoCursor<'txn>>,
+    db_name: &'static str,
+    prefix: String,
+    start_ms: i64,
+    end_ms: i64,
+    digits: usize,
+    max_digits: usize,
+    upper: String,
+    positioned: bool,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<'txn, T: DeserializeOwned> RangeScan<'txn, T> {
+    fn new(
+        txn: &'txn RoTransaction<'_>,
+        db: Option<Database>,
+        db_name: &'static str,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Self> {
+        let start_ms = start_ms.max(0);
+        let cursor = match db {
+            Some(db) if end_ms >= start_ms => Some(
+                txn.open_ro_cursor(db)
+                    .context("Failed to open LMDB cursor")?,
+            ),
+            _ => None,
+        };
+
+        let mut scan = Self {
+            cursor,
+            db_name,
+            prefix: format!("{}:", symbol),
+            start_ms,
+            end_ms,
+            digits: decimal_digits(start_ms),
+            max_digits: decimal_digits(end_ms),
+            upper: String::new(),
+            positioned: false,
+            _record: PhantomData,
+        };
+        scan.upper = scan.upper_bound();
+        Ok(scan)
+    }
+
+    /// Largest timestamp string of the current length that is still in range
+    fn upper_bound(&self) -> String {
+        let largest = 10i64
+            .checked_pow(self.digits as u32)
+            .map_or(i64::MAX, |limit| limit - 1);
+        self.end_ms.min(largest).to_string()
+    }
+
+    /// Smallest timestamp of the current length that is still in range
+    fn lower_bound(&self) -> i64 {
+        let smallest = if self.digits <= 1 {
+            0
+        } else {
+            10i64.pow(self.digits as u32 - 1)
+        };
+        self.start_ms.max(smallest)
+    }
+
+    /// Move on to keys with one more decimal digit
+    fn next_length(&mut self) {
+        self.digits += 1;
+        self.positioned = false;
+        self.upper = self.upper_bound();
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for RangeScan<'_, T> {
+    type Item = Result<(i64, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.digits > self.max_digits {
+                return None;
+            }
+            let cursor = self.cursor.as_ref()?;
+
+            let result = if self.positioned {
+                cursor.get(None, None, lmdb_sys::MDB_NEXT)
+            } else {
+                self.positioned = true;
+                let seek = format!("{}{}", self.prefix, self.lower_bound());
+                cursor.get(Some(seek.as_bytes()), None, lmdb_sys::MDB_SET_RANGE)
+            };
+
+            let (key, data) = match result {
+                Ok((Some(key), data)) => (key, data),
+                Ok((None, _)) | Err(lmdb::Error::NotFound) => {
+                    self.next_length();
+                    continue;
+                }
+                Err(e) => return Some(Err(anyhow!("LMDB cursor error: {}", e))),
+            };
+
+            let suffix = match key.strip_prefix(self.prefix.as_bytes()) {
+                Some(suffix) if suffix <= self.upper.as_bytes() => suffix,
+                // Past this symbol or past the range for this length
+                _ => {
+                    self.next_length();
+                    continue;
+                }
+            };
+
+            // Timestamps of other lengths interleave here; they get their own pass
+            if suffix.len() != self.digits {
+                continue;
+            }
+            let timestamp = match std::str::from_utf8(suffix).ok().and_then(|s| s.parse::<i64>().ok()) {
+                Some(timestamp) => timestamp,
+                None => {
+                    tracing::debug!("Skipping LMDB key with non-numeric timestamp: {}", String::from_utf8_lossy(key));
+                    continue;
+                }
+            };
+
+            return Some(decode_record(self.db_name, key, data).map(|record| (timestamp, record)));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -306,6 +974,177 @@ mod tests {
         assert_eq!(key, "ETHUSDT:1234567890000");
     }
 
+    /// Write indicators_3m records under the given keys (other databases stay empty)
+    ///
+    /// Keys listed in `invalid` get a record with missing fields.
+    fn write_keys(path: &std::path::Path, keys: &[&str], invalid: &[&str]) {
+        use lmdb::{DatabaseFlags, WriteFlags};
+
+        std::fs::create_dir_all(path).unwrap();
+        let env = Environment::new().set_max_dbs(10).open(path).unwrap();
+        for name in ["candles_3m", "candles_4h", "indicators_4h"] {
+            env.create_db(Some(name), DatabaseFlags::empty()).unwrap();
+        }
+        let db = env.create_db(Some("indicators_3m"), DatabaseFlags::empty()).unwrap();
+
+        let record = r#"{"ema_20": 1.0, "ema_50": 1.0, "macd": 0.0, "rsi_7": 50.0, "rsi_14": 50.0, "atr_14": 1.0}"#;
+        let mut txn = env.begin_rw_txn().unwrap();
+        for key in keys {
+            let value = if invalid.contains(key) { r#"{"ema_20": 1.0}"# } else { record };
+            txn.put(db, key, &value, WriteFlags::empty()).unwrap();
+        }
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_scan_orders_mixed_length_timestamps() {
+        let path = std::env::temp_dir().join(format!("rag_reader_scan_{}", std::process::id()));
+        // Lexicographic order: 1000, 10000, 1001, 1500, 5, 900, 999
+        write_keys(&path, &[
+            "BTCUSDT:5", "BTCUSDT:900", "BTCUSDT:999", "BTCUSDT:1000", "BTCUSDT:1001",
+            "BTCUSDT:1500", "BTCUSDT:10000", "BTCUSDTX:950", "ETHUSDT:1000",
+        ], &[]);
+
+        let reader = LmdbReader::new(&path).unwrap();
+        let txn = reader.begin_read().unwrap();
+        let scan = |start, end| -> Vec<i64> {
+            reader
+                .scan_indicators_3m(&txn, "BTCUSDT", start, end)
+                .unwrap()
+                .map(|entry| entry.unwrap().0)
+                .collect()
+        };
+
+        assert_eq!(scan(0, 20_000), vec![5, 900, 999, 1000, 1001, 1500, 10000]);
+        assert_eq!(scan(950, 1200), vec![999, 1000, 1001]);
+        assert_eq!(scan(-100, 900), vec![5, 900]);
+        assert_eq!(scan(1001, 1001), vec![1001]);
+        assert!(scan(2000, 1000).is_empty());
+        assert!(scan(1600, 9999).is_empty());
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_range_reads_find_off_grid_records() {
+        let path = std::env::temp_dir().join(format!("rag_reader_grid_{}", std::process::id()));
+        write_keys(&path, &[
+            "BTCUSDT:1700000000000", "BTCUSDT:1700000090000",
+            "BTCUSDT:1700000180000", "BTCUSDT:1700000540000",
+        ], &[]);
+        let reader = LmdbReader::new(&path).unwrap();
+
+        // Every record is found, including the one between grid points
+        let timestamps = reader
+            .query_timestamps_3m("BTCUSDT", 1_700_000_000_000, 1_700_000_600_000, 0)
+            .unwrap();
+        assert_eq!(timestamps, vec![1_700_000_000_000, 1_700_000_090_000, 1_700_000_180_000, 1_700_000_540_000]);
+
+        // A minimum spacing thins out dense records
+        let timestamps = reader
+            .query_timestamps_3m("BTCUSDT", 1_700_000_000_000, 1_700_000_600_000, 180_000)
+            .unwrap();
+        assert_eq!(timestamps, vec![1_700_000_000_000, 1_700_000_180_000, 1_700_000_540_000]);
+
+        // Series stay on the grid ending at the requested timestamp
+        let series = reader
+            .read_indicators_3m_series("BTCUSDT", 1_700_000_540_000, 180_000, 4)
+            .unwrap();
+        let series_ts: Vec<i64> = series.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(series_ts, vec![1_700_000_000_000, 1_700_000_180_000, 1_700_000_540_000]);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_schema_error_names_field_key_and_database() {
+        let path = std::env::temp_dir().join(format!("rag_reader_schema_{}", std::process::id()));
+        write_keys(&path, &["BTCUSDT:1000", "BTCUSDT:1180"], &["BTCUSDT:1180"]);
+        let reader = LmdbReader::new(&path).unwrap();
+
+        assert!(reader.read_indicators_3m("BTCUSDT", 1000).unwrap().is_some());
+
+        let err = reader.read_indicators_3m("BTCUSDT", 1180).unwrap_err().to_string();
+        assert!(err.contains("indicators_3m"), "{}", err);
+        assert!(err.contains("BTCUSDT:1180"), "{}", err);
+        assert!(err.contains("missing field `ema_50`"), "{}", err);
+
+        // Range scans surface the same error for the bad record
+        let txn = reader.begin_read().unwrap();
+        let results: Vec<_> = reader.scan_indicators_3m(&txn, "BTCUSDT", 0, 2000).unwrap().collect();
+        assert!(results[0].is_ok());
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("BTCUSDT:1180"));
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_configured_candle_timeframes() {
+        use lmdb::{DatabaseFlags, WriteFlags};
+
+        let path = std::env::temp_dir().join(format!("rag_reader_timeframes_{}", std::process::id()));
+        write_keys(&path, &[], &[]);
+        {
+            let env = Environment::new().set_max_dbs(10).open(&path).unwrap();
+            let db = env.create_db(Some("candles_1h"), DatabaseFlags::empty()).unwrap();
+            let mut txn = env.begin_rw_txn().unwrap();
+            let candle = r#"{"open": 1.0, "high": 2.0, "low": 0.5, "close": 1.5, "volume": 10.0}"#;
+            txn.put(db, &"BTCUSDT:3600000", &candle, WriteFlags::empty()).unwrap();
+            txn.commit().unwrap();
+        }
+
+        // Defaults open 1h (present) and skip 1d (absent)
+        let reader = LmdbReader::new(&path).unwrap();
+        let timeframes: Vec<Timeframe> = reader.timeframes().collect();
+        assert_eq!(timeframes, vec![Timeframe::M3, Timeframe::H1, Timeframe::H4]);
+        let candles = reader.candles("BTCUSDT", Timeframe::H1, 0, 7_200_000).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].1.close, 1.5);
+        assert!(reader.candles("BTCUSDT", Timeframe::D1, 0, 7_200_000).unwrap().is_empty());
+
+        // Timeframes left out of the configuration are never read
+        let reader = LmdbReader::with_timeframes(&path, &[Timeframe::D1]).unwrap();
+        assert!(!reader.has_timeframe(Timeframe::H1));
+        assert!(reader.read_candle(Timeframe::H1, "BTCUSDT", 3_600_000).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_optional_microstructure_databases() {
+        use lmdb::{DatabaseFlags, WriteFlags};
+
+        let path = std::env::temp_dir().join(format!("rag_reader_microstructure_{}", std::process::id()));
+        write_keys(&path, &[], &[]);
+        let reader = LmdbReader::new(&path).unwrap();
+        assert!(!reader.has_order_book() && !reader.has_trade_flow() && !reader.has_liquidations());
+        assert!(reader.order_book("BTCUSDT", 0, 10_000).unwrap().is_empty());
+        drop(reader);
+
+        {
+            let env = Environment::new().set_max_dbs(10).open(&path).unwrap();
+            let order_book = env.create_db(Some("order_book"), DatabaseFlags::empty()).unwrap();
+            let trade_flow = env.create_db(Some("trade_flow"), DatabaseFlags::empty()).unwrap();
+            let mut txn = env.begin_rw_txn().unwrap();
+            let book = r#"{"bid_price": 99.0, "bid_size": 3.0, "ask_price": 101.0, "ask_size": 1.0}"#;
+            txn.put(order_book, &"BTCUSDT:1000", &book, WriteFlags::empty()).unwrap();
+            let flow = r#"{"taker_buy_volume": 5.0, "taker_sell_volume": 2.0}"#;
+            txn.put(trade_flow, &"BTCUSDT:1000", &flow, WriteFlags::empty()).unwrap();
+            txn.put(trade_flow, &"BTCUSDT:1180", &flow, WriteFlags::empty()).unwrap();
+            txn.commit().unwrap();
+        }
+
+        let reader = LmdbReader::new(&path).unwrap();
+        assert!(reader.has_order_book() && reader.has_trade_flow() && !reader.has_liquidations());
+        let books = reader.order_book("BTCUSDT", 0, 10_000).unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].1.imbalance(), Some(0.5));
+        assert_eq!(reader.trade_flow("BTCUSDT", 1100, 10_000).unwrap().len(), 1);
+        assert!(reader.liquidations("BTCUSDT", 0, 10_000).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
     // Integration test - requires actual LMDB database
     #[test]
     #[ignore]
@@ -0,0 +1,428 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use trading_core::indicators::Indicators4hCalculator;
+use trading_core::{
+    Candle, FundingRateRecord, Indicators3m, Indicators4h, LiquidationRecord, OpenInterestRecord,
+    OrderBookRecord, Timeframe, TradeFlowRecord,
+};
+
+const INTERVAL_3M_MS: i64 = 180_000;
+const FOUR_HOURS_MS: i64 = 4 * 60 * 60 * 1000;
+
+/// Source of historical market data for snapshot extraction
+///
+/// Implemented by [`LmdbReader`](super::lmdb_reader::LmdbReader) (real data),
+/// [`MockDataSource`](super::mock_data_source::MockDataSource) (synthetic data)
+/// and [`InMemoryDataSource`] (exact test fixtures).
+///
+/// Range methods are inclusive on both ends and return records ordered from
+/// oldest to newest. Candles are keyed by open time. Candles other than 3m,
+/// open interest, funding and the microstructure feeds (order book, trade
+/// flow, liquidations) are optional: sources without them return empty ranges
+/// and snapshots mark those fields as unavailable.
+pub trait MarketDataSource: Send + Sync {
+    /// Short name for logging (e.g., "lmdb", "mock")
+    fn name(&self) -> &str;
+
+    /// 3-minute candles for a symbol in a time range
+    fn candles_3m(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>>;
+
+    /// 4-hour candles for a symbol in a time range
+    fn candles_4h(&self, _symbol: &str, _start_ms: i64, _end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        Ok(Vec::new())
+    }
+
+    /// Candles of any timeframe for a symbol in a time range
+    ///
+    /// Defaults to [`candles_3m`](Self::candles_3m) and
+    /// [`candles_4h`](Self::candles_4h), with no other timeframes.
+    fn candles(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Candle)>> {
+        match timeframe {
+            Timeframe::M3 => self.candles_3m(symbol, start_ms, end_ms),
+            Timeframe::H4 => self.candles_4h(symbol, start_ms, end_ms),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// 3-minute indicators for a symbol in a time range
+    fn indicators_3m(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators3m)>>;
+
+    /// 4-hour indicators for a symbol in a time range
+    fn indicators_4h(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators4h)>>;
+
+    /// Open interest for a symbol in a time range
+    fn open_interest(
+        &self,
+        _symbol: &str,
+        _start_ms: i64,
+        _end_ms: i64,
+    ) -> Result<Vec<(i64, OpenInterestRecord)>> {
+        Ok(Vec::new())
+    }
+
+    /// Funding rates for a symbol in a time range
+    fn funding_rate(
+        &self,
+        _symbol: &str,
+        _start_ms: i64,
+        _end_ms: i64,
+    ) -> Result<Vec<(i64, FundingRateRecord)>> {
+        Ok(Vec::new())
+    }
+
+    /// Top-of-book snapshots for a symbol in a time range
+    fn order_book(
+        &self,
+        _symbol: &str,
+        _start_ms: i64,
+        _end_ms: i64,
+    ) -> Result<Vec<(i64, OrderBookRecord)>> {
+        Ok(Vec::new())
+    }
+
+    /// Taker buy/sell volume per 3m candle for a symbol in a time range
+    fn trade_flow(
+        &self,
+        _symbol: &str,
+        _start_ms: i64,
+        _end_ms: i64,
+    ) -> Result<Vec<(i64, TradeFlowRecord)>> {
+        Ok(Vec::new())
+    }
+
+    /// Liquidated volume per 3m candle for a symbol in a time range
+    fn liquidations(
+        &self,
+        _symbol: &str,
+        _start_ms: i64,
+        _end_ms: i64,
+    ) -> Result<Vec<(i64, LiquidationRecord)>> {
+        Ok(Vec::new())
+    }
+
+    /// 3-minute indicators at exactly `timestamp_ms`
+    fn indicators_3m_at(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators3m>> {
+        Ok(self.indicators_3m(symbol, timestamp_ms, timestamp_ms)?.pop().map(|(_, r)| r))
+    }
+
+    /// 4-hour indicators at exactly `timestamp_ms`
+    fn indicators_4h_at(&self, symbol: &str, timestamp_ms: i64) -> Result<Option<Indicators4h>> {
+        Ok(self.indicators_4h(symbol, timestamp_ms, timestamp_ms)?.pop().map(|(_, r)| r))
+    }
+}
+
+/// Records for one symbol, keyed by timestamp
+#[derive(Debug, Clone, Default)]
+struct SymbolData {
+    candles: BTreeMap<Timeframe, BTreeMap<i64, Candle>>,
+    indicators_3m: BTreeMap<i64, Indicators3m>,
+    indicators_4h: BTreeMap<i64, Indicators4h>,
+    open_interest: BTreeMap<i64, OpenInterestRecord>,
+    funding_rate: BTreeMap<i64, FundingRateRecord>,
+    order_book: BTreeMap<i64, OrderBookRecord>,
+    trade_flow: BTreeMap<i64, TradeFlowRecord>,
+    liquidations: BTreeMap<i64, LiquidationRecord>,
+}
+
+/// Market data held in memory
+///
+/// Useful for building exact fixtures in tests, or for feeding data that was
+/// loaded from elsewhere through the snapshot extractor.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDataSource {
+    symbols: HashMap<String, SymbolData>,
+}
+
+impl InMemoryDataSource {
+    /// Create an empty data source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn symbol_mut(&mut self, symbol: &str) -> &mut SymbolData {
+        self.symbols.entry(symbol.to_string()).or_default()
+    }
+
+    /// Add or replace a candle of any timeframe
+    pub fn insert_candle(&mut self, symbol: &str, timeframe: Timeframe, timestamp_ms: i64, candle: Candle) {
+        self.symbol_mut(symbol)
+            .candles
+            .entry(timeframe)
+            .or_default()
+            .insert(timestamp_ms, candle);
+    }
+
+    /// Add or replace a 3-minute candle
+    pub fn insert_candle_3m(&mut self, symbol: &str, timestamp_ms: i64, candle: Candle) {
+        self.insert_candle(symbol, Timeframe::M3, timestamp_ms, candle);
+    }
+
+    /// Add or replace a 4-hour candle
+    pub fn insert_candle_4h(&mut self, symbol: &str, timestamp_ms: i64, candle: Candle) {
+        self.insert_candle(symbol, Timeframe::H4, timestamp_ms, candle);
+    }
+
+    /// Add or replace 3-minute indicators
+    pub fn insert_indicators_3m(&mut self, symbol: &str, timestamp_ms: i64, indicators: Indicators3m) {
+        self.symbol_mut(symbol).indicators_3m.insert(timestamp_ms, indicators);
+    }
+
+    /// Add or replace 4-hour indicators
+    pub fn insert_indicators_4h(&mut self, symbol: &str, timestamp_ms: i64, indicators: Indicators4h) {
+        self.symbol_mut(symbol).indicators_4h.insert(timestamp_ms, indicators);
+    }
+
+    /// Add or replace an open interest value
+    pub fn insert_open_interest(&mut self, symbol: &str, timestamp_ms: i64, open_interest: f64) {
+        self.symbol_mut(symbol)
+            .open_interest
+            .insert(timestamp_ms, OpenInterestRecord { open_interest });
+    }
+
+    /// Add or replace a funding rate
+    pub fn insert_funding_rate(&mut self, symbol: &str, timestamp_ms: i64, funding_rate: f64) {
+        self.symbol_mut(symbol)
+            .funding_rate
+            .insert(timestamp_ms, FundingRateRecord { funding_rate });
+    }
+
+    /// Add or replace a top-of-book snapshot
+    pub fn insert_order_book(&mut self, symbol: &str, timestamp_ms: i64, order_book: OrderBookRecord) {
+        self.symbol_mut(symbol).order_book.insert(timestamp_ms, order_book);
+    }
+
+    /// Add or replace the taker volumes of one 3m candle
+    pub fn insert_trade_flow(&mut self, symbol: &str, timestamp_ms: i64, taker_buy_volume: f64, taker_sell_volume: f64) {
+        self.symbol_mut(symbol)
+            .trade_flow
+            .insert(timestamp_ms, TradeFlowRecord { taker_buy_volume, taker_sell_volume });
+    }
+
+    /// Add or replace the liquidated volumes of one 3m candle
+    pub fn insert_liquidations(&mut self, symbol: &str, timestamp_ms: i64, long_liquidations: f64, short_liquidations: f64) {
+        self.symbol_mut(symbol)
+            .liquidations
+            .insert(timestamp_ms, LiquidationRecord { long_liquidations, short_liquidations });
+    }
+
+    /// Drop candles of one timeframe that opened before `before_ms`
+    pub fn prune_candles(&mut self, symbol: &str, timeframe: Timeframe, before_ms: i64) {
+        if let Some(candles) = self.symbols.get_mut(symbol).and_then(|d| d.candles.get_mut(&timeframe)) {
+            *candles = candles.split_off(&before_ms);
+        }
+    }
+
+    /// Drop indicator, derivatives and microstructure records before `before_ms`
+    ///
+    /// Candles are kept; see [`prune_candles`](Self::prune_candles).
+    pub fn prune_records(&mut self, symbol: &str, before_ms: i64) {
+        fn prune<T>(records: &mut BTreeMap<i64, T>, before_ms: i64) {
+            *records = records.split_off(&before_ms);
+        }
+
+        if let Some(data) = self.symbols.get_mut(symbol) {
+            prune(&mut data.indicators_3m, before_ms);
+            prune(&mut data.indicators_4h, before_ms);
+            prune(&mut data.open_interest, before_ms);
+            prune(&mut data.funding_rate, before_ms);
+            prune(&mut data.order_book, before_ms);
+            prune(&mut data.trade_flow, before_ms);
+            prune(&mut data.liquidations, before_ms);
+        }
+    }
+
+    /// Copy the records of one table in `[start_ms, end_ms]`
+    fn range<T: Copy>(
+        &self,
+        symbol: &str,
+        table: impl Fn(&SymbolData) -> Option<&BTreeMap<i64, T>>,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Vec<(i64, T)> {
+        match self.symbols.get(symbol).and_then(table) {
+            Some(records) if start_ms <= end_ms => records
+                .range(start_ms..=end_ms)
+                .map(|(ts, record)| (*ts, *record))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl MarketDataSource for InMemoryDataSource {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn candles_3m(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        self.candles(symbol, Timeframe::M3, start_ms, end_ms)
+    }
+
+    fn candles_4h(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        self.candles(symbol, Timeframe::H4, start_ms, end_ms)
+    }
+
+    fn candles(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Candle)>> {
+        Ok(self.range(symbol, |d| d.candles.get(&timeframe), start_ms, end_ms))
+    }
+
+    fn indicators_3m(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators3m)>> {
+        Ok(self.range(symbol, |d| Some(&d.indicators_3m), start_ms, end_ms))
+    }
+
+    fn indicators_4h(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators4h)>> {
+        Ok(self.range(symbol, |d| Some(&d.indicators_4h), start_ms, end_ms))
+    }
+
+    fn open_interest(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OpenInterestRecord)>> {
+        Ok(self.range(symbol, |d| Some(&d.open_interest), start_ms, end_ms))
+    }
+
+    fn funding_rate(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, FundingRateRecord)>> {
+        Ok(self.range(symbol, |d| Some(&d.funding_rate), start_ms, end_ms))
+    }
+
+    fn order_book(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OrderBookRecord)>> {
+        Ok(self.range(symbol, |d| Some(&d.order_book), start_ms, end_ms))
+    }
+
+    fn trade_flow(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, TradeFlowRecord)>> {
+        Ok(self.range(symbol, |d| Some(&d.trade_flow), start_ms, end_ms))
+    }
+
+    fn liquidations(
+        &self,
+        symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, LiquidationRecord)>> {
+        Ok(self.range(symbol, |d| Some(&d.liquidations), start_ms, end_ms))
+    }
+}
+
+/// 4h indicators as seen at each 3m timestamp, computed from 4h candles
+///
+/// Each timestamp gets the indicators of the last 4h candle that had closed by
+/// the end of its 3m candle, so nothing from the still-open 4h candle leaks in.
+/// Timestamps whose latest closed 4h candle is missing are left out.
+pub(crate) fn indicators_4h_as_of(
+    candles_4h: &[(i64, Candle)],
+    timestamps: impl IntoIterator<Item = i64>,
+) -> Vec<(i64, Indicators4h)> {
+    let by_close: BTreeMap<i64, Indicators4h> = Indicators4hCalculator::compute(candles_4h)
+        .into_iter()
+        .map(|(open_ts, indicators)| (open_ts + FOUR_HOURS_MS, indicators))
+        .collect();
+
+    timestamps
+        .into_iter()
+        .filter_map(|ts| {
+            let (close_ts, indicators) = by_close.range(..=ts + INTERVAL_3M_MS).next_back()?;
+            (*close_ts > ts + INTERVAL_3M_MS - FOUR_HOURS_MS).then_some((ts, *indicators))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64) -> Candle {
+        Candle { open: close, high: close, low: close, close, volume: 1.0, trades: 1 }
+    }
+
+    #[test]
+    fn test_in_memory_ranges_are_inclusive_and_per_symbol() {
+        let mut source = InMemoryDataSource::new();
+        for i in 0..5 {
+            source.insert_candle_3m("BTCUSDT", i * 180_000, candle(100.0 + i as f64));
+        }
+        source.insert_candle_3m("ETHUSDT", 180_000, candle(1.0));
+
+        let candles = source.candles_3m("BTCUSDT", 180_000, 540_000).unwrap();
+        let timestamps: Vec<i64> = candles.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(timestamps, vec![180_000, 360_000, 540_000]);
+        assert_eq!(candles[0].1.close, 101.0);
+
+        assert!(source.candles_3m("BTCUSDT", 540_000, 180_000).unwrap().is_empty());
+        assert!(source.candles_3m("SOLUSDT", 0, 540_000).unwrap().is_empty());
+        assert!(source.open_interest("BTCUSDT", 0, 540_000).unwrap().is_empty());
+
+        // Each timeframe has its own table
+        source.insert_candle("BTCUSDT", Timeframe::H1, 0, candle(99.0));
+        assert_eq!(source.candles("BTCUSDT", Timeframe::H1, 0, 540_000).unwrap(), vec![(0, candle(99.0))]);
+        assert_eq!(source.candles("BTCUSDT", Timeframe::M3, 0, 0).unwrap(), vec![(0, candle(100.0))]);
+        assert!(source.candles("BTCUSDT", Timeframe::D1, 0, 540_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_indicators_4h_as_of_last_closed_candle() {
+        let candles: Vec<(i64, Candle)> = (0..52)
+            .map(|i| (i * FOUR_HOURS_MS, candle(100.0 + (i % 7) as f64)))
+            .collect();
+        let computed = Indicators4hCalculator::compute(&candles);
+        let (last_open, last) = *computed.last().unwrap();
+        let previous = computed[computed.len() - 2].1;
+
+        // The candle opened at last_open closes at the end of the 3m candle
+        // starting 3 minutes before its close
+        let close_ts = last_open + FOUR_HOURS_MS;
+        let seen = indicators_4h_as_of(
+            &candles,
+            [close_ts - 2 * INTERVAL_3M_MS, close_ts - INTERVAL_3M_MS, close_ts + 3 * FOUR_HOURS_MS],
+        );
+        assert_eq!(seen, vec![(close_ts - 2 * INTERVAL_3M_MS, previous), (close_ts - INTERVAL_3M_MS, last)]);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::Result;
+use std::f64::consts::PI;
+use trading_core::{
+    Candle, FundingRateRecord, Indicators3m, Indicators4h, LiquidationRecord, OpenInterestRecord,
+    OrderBookRecord, Timeframe, TradeFlowRecord,
+};
+
+use super::market_data_source::MarketDataSource;
+
+/// 3-minute grid the mock data is generated on
+const INTERVAL_3M_MS: i64 = 180_000;
+
+/// Deterministic synthetic market data (for testing)
+///
+/// Every series is a smooth function of the timestamp, generated on the
+/// epoch-aligned 3m grid (candles of other timeframes on their own grid). 4h
+/// indicators are sampled on the 3m grid, so a snapshot at any 3m timestamp
+/// has complete data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockDataSource;
+
+impl MockDataSource {
+    /// Create a mock data source
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 3m grid timestamps in `[start_ms, end_ms]`
+    fn grid(start_ms: i64, end_ms: i64) -> impl Iterator<Item = i64> {
+        Self::grid_with(INTERVAL_3M_MS, start_ms, end_ms)
+    }
+
+    /// Grid timestamps with the given spacing in `[start_ms, end_ms]`
+    fn grid_with(interval_ms: i64, start_ms: i64, end_ms: i64) -> impl Iterator<Item = i64> {
+        let first = start_ms.div_euclid(interval_ms) * interval_ms;
+        let first = if first < start_ms { first + interval_ms } else { first };
+        (0..)
+            .map(move |i| first + i * interval_ms)
+            .take_while(move |ts| *ts <= end_ms)
+    }
+
+    /// Phase shared by all mock series
+    fn phase(timestamp_ms: i64) -> f64 {
+        (timestamp_ms as f64) / 1000000.0
+    }
+
+    fn price(timestamp_ms: i64) -> f64 {
+        50000.0 + (Self::phase(timestamp_ms) * PI).sin() * 5000.0
+    }
+
+    fn candle(timestamp_ms: i64) -> Candle {
+        let open = Self::price(timestamp_ms - INTERVAL_3M_MS);
+        let close = Self::price(timestamp_ms);
+        Candle {
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume: 1000.0,
+            trades: 100,
+        }
+    }
+
+    /// Candle spanning `interval_ms / 3m` mock 3m candles (80 for 4h)
+    fn candle_spanning(timestamp_ms: i64, interval_ms: i64) -> Candle {
+        let count = interval_ms / INTERVAL_3M_MS;
+        let open = Self::price(timestamp_ms - INTERVAL_3M_MS);
+        let close = Self::price(timestamp_ms + interval_ms - INTERVAL_3M_MS);
+        Candle {
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume: 1000.0 * count as f64,
+            trades: 100 * count as u64,
+        }
+    }
+
+    fn indicators_3m_at_grid(timestamp_ms: i64) -> Indicators3m {
+        let t = Self::phase(timestamp_ms);
+        let price = Self::price(timestamp_ms);
+        Indicators3m {
+            ema_20: price * 0.99,
+            ema_50: price * 0.985,
+            macd: (t * PI).sin() * 100.0,
+            rsi_7: 50.0 + (t * 2.0 * PI).sin() * 30.0,
+            rsi_14: 50.0 + (t * 1.5 * PI).sin() * 25.0,
+            atr_14: 50.0,
+            avg_volume_20: Some(1000.0),
+        }
+    }
+
+    fn indicators_4h_at_grid(timestamp_ms: i64) -> Indicators4h {
+        let t = Self::phase(timestamp_ms);
+        let price = Self::price(timestamp_ms);
+        Indicators4h {
+            ema_20: price * 0.98,
+            ema_50: price * 0.97,
+            macd: (t * PI).sin() * 100.0,
+            rsi_14: 50.0 + (t * 1.5 * PI).sin() * 25.0,
+            atr_3: 200.0,
+            atr_14: 250.0,
+            avg_volume_20: Some(Self::candle_spanning(timestamp_ms, Timeframe::H4.duration_ms()).volume),
+        }
+    }
+}
+
+impl MarketDataSource for MockDataSource {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn candles_3m(&self, _symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        Ok(Self::grid(start_ms, end_ms).map(|ts| (ts, Self::candle(ts))).collect())
+    }
+
+    fn candles_4h(&self, symbol: &str, start_ms: i64, end_ms: i64) -> Result<Vec<(i64, Candle)>> {
+        self.candles(symbol, Timeframe::H4, start_ms, end_ms)
+    }
+
+    fn candles(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Candle)>> {
+        if timeframe == Timeframe::M3 {
+            return self.candles_3m(symbol, start_ms, end_ms);
+        }
+        let interval_ms = timeframe.duration_ms();
+        Ok(Self::grid_with(interval_ms, start_ms, end_ms)
+            .map(|ts| (ts, Self::candle_spanning(ts, interval_ms)))
+            .collect())
+    }
+
+    fn indicators_3m(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators3m)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| (ts, Self::indicators_3m_at_grid(ts)))
+            .collect())
+    }
+
+    fn indicators_4h(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, Indicators4h)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| (ts, Self::indicators_4h_at_grid(ts)))
+            .collect())
+    }
+
+    fn open_interest(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OpenInterestRecord)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| {
+                let open_interest = 100000.0 + (Self::phase(ts) * PI).sin() * 10000.0;
+                (ts, OpenInterestRecord { open_interest })
+            })
+            .collect())
+    }
+
+    fn funding_rate(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, FundingRateRecord)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| {
+                let funding_rate = (Self::phase(ts) * PI).sin() * 0.0002;
+                (ts, FundingRateRecord { funding_rate })
+            })
+            .collect())
+    }
+
+    fn order_book(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, OrderBookRecord)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| {
+                let price = Self::price(ts);
+                let tilt = (Self::phase(ts) * 2.0 * PI).sin() * 5.0;
+                let book = OrderBookRecord {
+                    bid_price: price - 0.5,
+                    bid_size: 10.0 + tilt,
+                    ask_price: price + 0.5,
+                    ask_size: 10.0 - tilt,
+                };
+                (ts, book)
+            })
+            .collect())
+    }
+
+    fn trade_flow(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, TradeFlowRecord)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| {
+                let skew = (Self::phase(ts) * PI).cos() * 200.0;
+                let flow = TradeFlowRecord {
+                    taker_buy_volume: 500.0 + skew,
+                    taker_sell_volume: 500.0 - skew,
+                };
+                (ts, flow)
+            })
+            .collect())
+    }
+
+    fn liquidations(
+        &self,
+        _symbol: &str,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Result<Vec<(i64, LiquidationRecord)>> {
+        Ok(Self::grid(start_ms, end_ms)
+            .map(|ts| {
+                let wave = (Self::phase(ts) * PI).sin();
+                let liquidations = LiquidationRecord {
+                    long_liquidations: (-wave).max(0.0) * 50.0,
+                    short_liquidations: wave.max(0.0) * 50.0,
+                };
+                (ts, liquidations)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_grid_is_epoch_aligned() {
+        let source = MockDataSource::new();
+        let candles = source.candles_3m("BTCUSDT", 1_000_000_000, 1_000_600_000).unwrap();
+        let timestamps: Vec<i64> = candles.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(timestamps, vec![1_000_080_000, 1_000_260_000, 1_000_440_000]);
+
+        // Same timestamp, same data regardless of the requested range
+        let again = source.candles_3m("BTCUSDT", 1_000_080_000, 1_000_080_000).unwrap();
+        assert_eq!(again[0], candles[0]);
+    }
+}
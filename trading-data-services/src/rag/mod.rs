@@ -1,12 +1,31 @@
 pub mod snapshot_formatter;
 pub mod snapshot_extractor;
+pub mod snapshot_validator;
 pub mod vector_store;
 pub mod ingestion_pipeline;
 pub mod lmdb_reader;
+pub mod market_data_source;
+pub mod mock_data_source;
+pub mod csv_data_source;
+pub mod schema;
+pub mod live_snapshot_builder;
+pub mod embedder;
+pub mod sparse_vector;
+pub mod checkpoint;
+pub mod snapshot_export;
 
 // Re-export commonly used items
 pub use snapshot_formatter::SnapshotFormatter;
-pub use snapshot_extractor::HistoricalSnapshotExtractor;
+pub use snapshot_extractor::{GapFillPolicy, HistoricalSnapshotExtractor};
+pub use snapshot_validator::{SnapshotValidator, ValidationRule};
 pub use vector_store::VectorStore;
 pub use ingestion_pipeline::HistoricalIngestionPipeline;
 pub use lmdb_reader::LmdbReader;
+pub use market_data_source::{InMemoryDataSource, MarketDataSource};
+pub use mock_data_source::MockDataSource;
+pub use csv_data_source::CsvDataSource;
+pub use schema::{FeatureVersion, CURRENT_FEATURE_VERSION, CURRENT_SCHEMA_VERSION};
+pub use live_snapshot_builder::LiveSnapshotBuilder;
+pub use checkpoint::IngestCheckpoint;
+pub use snapshot_export::SnapshotExportRecord;
+pub use embedder::{EmbedderKind, FeatureSnapshotEmbedder, SnapshotEmbedder, TextSnapshotEmbedder};
@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Context, Result};
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use trading_core::MarketStateSnapshot;
+
+use super::vector_store::{snapshot_to_payload, VectorStore, LEGACY_POINT_ID_LIMIT};
+
+/// Embedding model and dimension stored with every point
+pub const EMBEDDING_MODEL: &str = "bge-small-en-v1.5";
+pub const EMBEDDING_DIM: usize = 384;
+
+/// Payload fields indexed as keywords for exact-match filters
+pub const KEYWORD_FIELDS: &[&str] = &["symbol", "trend_regime", "volatility_regime", "positioning_regime"];
+
+/// A set of features (timeframes and derived fields) payloads were built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureVersion {
+    pub id: &'static str,
+    pub schema_version: u32,
+    pub timeframes: &'static [&'static str],
+    pub description: &'static str,
+}
+
+/// Every feature version that has been written to Qdrant, oldest first
+pub const FEATURE_VERSIONS: &[FeatureVersion] = &[
+    FeatureVersion {
+        id: "v1_nofx_3m4h",
+        schema_version: 1,
+        timeframes: &["3m", "4h"],
+        description: "3m/4h indicators, derivatives and flat outcome fields",
+    },
+    FeatureVersion {
+        id: "v2_3m4h_outcomes",
+        schema_version: 2,
+        timeframes: &["3m", "4h"],
+        description: "Adds the source snapshot, data quality, per-horizon outcomes and simulated trades",
+    },
+    FeatureVersion {
+        id: "v3_3m1h4h1d",
+        schema_version: 3,
+        timeframes: &["3m", "1h", "4h", "1d"],
+        description: "Adds optional 1h and 1d context blocks (EMA trend, RSI, prior high/low)",
+    },
+    FeatureVersion {
+        id: "v4_microstructure",
+        schema_version: 4,
+        timeframes: &["3m", "1h", "4h", "1d"],
+        description: "Adds optional spread, book imbalance, 30m taker flow/CVD and liquidations",
+    },
+    FeatureVersion {
+        id: "v5_reference_asset",
+        schema_version: 5,
+        timeframes: &["3m", "1h", "4h", "1d"],
+        description: "Adds the optional reference asset block (BTC 1h/4h change, RSI, 4h correlation)",
+    },
+    FeatureVersion {
+        id: "v6_regimes",
+        schema_version: 6,
+        timeframes: &["3m", "1h", "4h", "1d"],
+        description: "Adds trend, volatility and positioning regime keywords",
+    },
+    FeatureVersion {
+        id: "v7_derived_features",
+        schema_version: 7,
+        timeframes: &["3m", "1h", "4h", "1d"],
+        description: "Adds the feature set: slopes, z-scores, EMA distance in ATRs, volume ratio, curvature, divergences",
+    },
+];
+
+/// Feature version written by this build
+pub const CURRENT_FEATURE_VERSION: FeatureVersion = FEATURE_VERSIONS[FEATURE_VERSIONS.len() - 1];
+
+/// Payload schema written by this build
+pub const CURRENT_SCHEMA_VERSION: u32 = CURRENT_FEATURE_VERSION.schema_version;
+
+/// Look up a registered feature version by id
+pub fn feature_version(id: &str) -> Option<&'static FeatureVersion> {
+    FEATURE_VERSIONS.iter().find(|v| v.id == id)
+}
+
+/// Schema version of a stored payload (payloads without one are version 1)
+pub fn payload_schema_version(payload: &Map<String, Value>) -> u32 {
+    payload
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |v| v as u32)
+}
+
+/// Recover the snapshot a payload was built from, whatever its schema version
+///
+/// Version 2+ payloads carry the full snapshot. Version 1 payloads only have
+/// flat fields, so the snapshot is rebuilt from them: time series are empty,
+/// and values stored only as ratios (4h EMAs, open interest) are rebuilt so
+/// the ratios match.
+pub fn snapshot_from_payload(payload: &Map<String, Value>) -> Result<MarketStateSnapshot> {
+    match payload_schema_version(payload) {
+        1 => snapshot_from_v1(payload),
+        version if version <= CURRENT_SCHEMA_VERSION => {
+            let snapshot = payload
+                .get("snapshot")
+                .ok_or_else(|| anyhow!("Schema {} payload has no snapshot", version))?;
+            serde_json::from_value(snapshot.clone())
+                .with_context(|| format!("Invalid snapshot in schema {} payload", version))
+        }
+        version => Err(anyhow!(
+            "Payload schema {} is newer than supported schema {}",
+            version,
+            CURRENT_SCHEMA_VERSION
+        )),
+    }
+}
+
+/// Fields recording how a point's embedding was produced
+const EMBEDDING_PROVENANCE_FIELDS: [&str; 3] = ["build_id", "embedding_model", "embedding_dim"];
+
+/// Rebuild the payload of a stored point from a snapshot with the current schema
+///
+/// The build id and embedding model of the original payload are kept, since
+/// the point's embedding was produced by that build and model.
+pub fn rebuild_payload(snapshot: &MarketStateSnapshot, original: &Map<String, Value>) -> Map<String, Value> {
+    let mut payload = snapshot_to_payload(snapshot);
+    for key in EMBEDDING_PROVENANCE_FIELDS {
+        if let Some(value) = original.get(key) {
+            payload.insert(key.to_string(), value.clone());
+        }
+    }
+    payload
+}
+
+/// Re-derive a stored payload with the current schema
+///
+/// Provenance fields are kept as in `rebuild_payload`.
+///
+/// # Returns
+/// The migrated payload, or `None` if the payload is already current
+pub fn migrate_payload(payload: &Map<String, Value>) -> Result<Option<Map<String, Value>>> {
+    let version = payload_schema_version(payload);
+    if version >= CURRENT_SCHEMA_VERSION {
+        return Ok(None);
+    }
+
+    let snapshot = snapshot_from_payload(payload)?;
+    let mut migrated = rebuild_payload(&snapshot, payload);
+    migrated.insert("migrated_from".to_string(), Value::from(version));
+
+    Ok(Some(migrated))
+}
+
+/// Statistics from a collection migration
+#[derive(Debug, Default, Clone)]
+pub struct MigrationStats {
+    pub points_scanned: usize,
+    pub points_migrated: usize,
+    pub points_current: usize, // Already at the current schema
+    pub points_failed: usize,  // Payload could not be read (left unchanged)
+}
+
+/// Migrate every point in a collection to the current payload schema
+///
+/// Vectors are left untouched; only payloads older than the current schema
+/// are rewritten.
+///
+/// # Arguments
+/// * `store` - Vector store of the collection to migrate
+/// * `batch_size` - Points read per scroll request
+pub async fn migrate_collection(store: &VectorStore, batch_size: u32) -> Result<MigrationStats> {
+    let mut stats = MigrationStats::default();
+    let mut offset = None;
+
+    // Fields added by the current schema may need their filter indexes
+    store.create_keyword_indexes().await?;
+
+    loop {
+        let (points, next_offset) = store.scroll_payloads(offset, batch_size).await?;
+
+        for (point_id, payload) in points {
+            stats.points_scanned += 1;
+            match migrate_payload(&payload) {
+                Ok(Some(migrated)) => {
+                    store.overwrite_payload(point_id, migrated).await?;
+                    stats.points_migrated += 1;
+                }
+                Ok(None) => stats.points_current += 1,
+                Err(e) => {
+                    tracing::warn!("Skipping point {:?}: {:#}", point_id, e);
+                    stats.points_failed += 1;
+                }
+            }
+        }
+
+        match next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    tracing::info!(
+        "Migrated {} of {} points to schema {} ({} current, {} failed)",
+        stats.points_migrated,
+        stats.points_scanned,
+        CURRENT_SCHEMA_VERSION,
+        stats.points_current,
+        stats.points_failed
+    );
+
+    Ok(stats)
+}
+
+/// Delete the points a collection holds under sequential IDs from before `snapshot_point_id`
+///
+/// Re-ingest the range afterwards to store their snapshots again under
+/// deterministic IDs.
+///
+/// # Returns
+/// The number of points deleted
+pub async fn purge_legacy_points(store: &VectorStore, batch_size: u32) -> Result<usize> {
+    let mut deleted = 0;
+    let mut offset = None;
+
+    loop {
+        let (points, next_offset) = store.scroll_payloads(offset, batch_size).await?;
+
+        let legacy: Vec<_> = points
+            .into_iter()
+            .map(|(point_id, _)| point_id)
+            .filter(|point_id| {
+                matches!(point_id.point_id_options, Some(PointIdOptions::Num(id)) if id < LEGACY_POINT_ID_LIMIT)
+            })
+            .collect();
+        deleted += legacy.len();
+        store.delete_points(legacy).await?;
+
+        match next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    tracing::info!("Deleted {} points with legacy sequential IDs", deleted);
+    Ok(deleted)
+}
+
+/// Rebuild a snapshot from the flat fields of a version 1 payload
+fn snapshot_from_v1(payload: &Map<String, Value>) -> Result<MarketStateSnapshot> {
+    let number = |key: &str| payload.get(key).and_then(Value::as_f64);
+    let flag = |key: &str| payload.get(key).and_then(Value::as_bool);
+
+    let symbol = payload
+        .get("symbol")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Schema 1 payload has no symbol"))?;
+    let timestamp = payload
+        .get("timestamp")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("Schema 1 payload has no timestamp"))?;
+    let price = number("price").ok_or_else(|| anyhow!("Schema 1 payload has no price"))?;
+
+    let mut snapshot = MarketStateSnapshot::new(symbol.to_string(), timestamp, price);
+    snapshot.rsi_7 = number("rsi_7").unwrap_or_default();
+    snapshot.rsi_14 = number("rsi_14").unwrap_or_default();
+    snapshot.macd = number("macd").unwrap_or_default();
+    snapshot.atr_3_4h = number("atr_3_4h").unwrap_or_default();
+    snapshot.atr_14_4h = number("atr_14_4h").unwrap_or_default();
+    snapshot.price_change_1h = number("price_change_1h").unwrap_or_default();
+    snapshot.price_change_4h = number("price_change_4h").unwrap_or_default();
+    snapshot.has_price_change_1h = number("price_change_1h").is_some();
+    snapshot.has_price_change_4h = number("price_change_4h").is_some();
+    snapshot.data_quality = number("data_quality").unwrap_or(1.0);
+
+    // Only the EMA ratio was stored; rebuild the 4h EMAs around the price
+    snapshot.ema_50_4h = price;
+    snapshot.ema_20_4h = price * number("ema_ratio").unwrap_or(1.0);
+
+    // Only the delta to the 24h average was stored for open interest
+    match number("oi_delta_pct") {
+        Some(delta) => {
+            snapshot.open_interest_avg_24h = 100.0;
+            snapshot.open_interest_latest = 100.0 + delta;
+        }
+        None => snapshot.has_open_interest = false,
+    }
+    match number("funding_rate") {
+        Some(funding_rate) => snapshot.funding_rate = funding_rate,
+        None => snapshot.has_funding_rate = false,
+    }
+
+    snapshot.outcome_15m = number("outcome_15m");
+    snapshot.outcome_1h = number("outcome_1h");
+    snapshot.outcome_4h = number("outcome_4h");
+    snapshot.outcome_24h = number("outcome_24h");
+    snapshot.max_runup_1h = number("max_runup_1h");
+    snapshot.max_drawdown_1h = number("max_drawdown_1h");
+    snapshot.hit_stop_loss = flag("hit_stop_loss");
+    snapshot.hit_take_profit = flag("hit_take_profit");
+    snapshot.outcomes_pending = flag("outcomes_pending").unwrap_or(false);
+
+    // Written by later version 1 builds
+    snapshot.outcomes = optional_field(payload, "outcomes")?.unwrap_or_default();
+    snapshot.long_trade = optional_field(payload, "long_trade")?;
+    snapshot.short_trade = optional_field(payload, "short_trade")?;
+
+    Ok(snapshot)
+}
+
+/// Deserialize a payload field that may be missing or null
+fn optional_field<T: DeserializeOwned>(payload: &Map<String, Value>, key: &str) -> Result<Option<T>> {
+    match payload.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .with_context(|| format!("Invalid '{}' in payload", key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_feature_registry() {
+        assert_eq!(CURRENT_FEATURE_VERSION.id, "v7_derived_features");
+        assert_eq!(CURRENT_FEATURE_VERSION.timeframes, &["3m", "1h", "4h", "1d"]);
+        assert_eq!(feature_version("v1_nofx_3m4h").unwrap().schema_version, 1);
+        assert!(feature_version("v0").is_none());
+
+        // Schema versions only ever increase
+        assert!(FEATURE_VERSIONS.windows(2).all(|w| w[0].schema_version < w[1].schema_version));
+    }
+
+    #[test]
+    fn test_current_payload_round_trip() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1_700_000_000_000, 50000.0);
+        snapshot.rsi_7 = 65.0;
+        snapshot.mid_prices = vec![49900.0, 50000.0];
+        snapshot.outcome_4h = Some(1.25);
+
+        let payload = snapshot_to_payload(&snapshot);
+        assert_eq!(payload_schema_version(&payload), CURRENT_SCHEMA_VERSION);
+        assert!(migrate_payload(&payload).unwrap().is_none());
+
+        let restored = snapshot_from_payload(&payload).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&snapshot).unwrap());
+
+        let mut newer = payload.clone();
+        newer.insert("schema_version".to_string(), json!(CURRENT_SCHEMA_VERSION + 1));
+        assert!(snapshot_from_payload(&newer).is_err());
+    }
+
+    #[test]
+    fn test_migrate_v2_payload() {
+        let snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1_700_000_000_000, 50000.0);
+        let mut v2 = snapshot_to_payload(&snapshot);
+        v2.insert("schema_version".to_string(), json!(2));
+        v2.insert("feature_version".to_string(), json!("v2_3m4h_outcomes"));
+        v2.insert("embedding_model".to_string(), json!("snapshot-features-v1"));
+        for key in ["rsi_14_1h", "ema_ratio_1h", "rsi_14_1d", "ema_ratio_1d"] {
+            v2.remove(key);
+        }
+        let mut stored = v2["snapshot"].as_object().unwrap().clone();
+        stored.remove("context_1h");
+        stored.remove("context_1d");
+        v2.insert("snapshot".to_string(), Value::Object(stored));
+
+        // Version 2 snapshots have no 1h/1d blocks; they migrate with the blocks absent
+        let migrated = migrate_payload(&v2).unwrap().unwrap();
+        assert_eq!(migrated["schema_version"], json!(CURRENT_SCHEMA_VERSION));
+        assert_eq!(migrated["migrated_from"], json!(2));
+        assert_eq!(migrated["rsi_14_1d"], Value::Null);
+        assert_eq!(migrated["snapshot"]["context_1d"], Value::Null);
+        assert_eq!(migrated["embedding_model"], json!("snapshot-features-v1"));
+    }
+
+    #[test]
+    fn test_migrate_v1_payload() {
+        let v1 = json!({
+            "symbol": "ETHUSDT",
+            "timestamp": 1_700_000_000_000u64,
+            "price": 2000.0,
+            "date": "2023-11-14T22:13:20+00:00",
+            "rsi_7": 70.0,
+            "rsi_14": 60.0,
+            "macd": 3.5,
+            "ema_ratio": 1.02,
+            "oi_delta_pct": 4.0,
+            "funding_rate": 0.0001,
+            "atr_3_4h": 10.0,
+            "atr_14_4h": 20.0,
+            "volatility_ratio": 0.5,
+            "price_change_1h": 0.3,
+            "price_change_4h": -1.2,
+            "outcome_15m": 0.1,
+            "outcome_1h": 0.4,
+            "outcome_4h": -0.8,
+            "outcome_24h": null,
+            "max_runup_1h": 0.6,
+            "max_drawdown_1h": -0.2,
+            "hit_stop_loss": false,
+            "hit_take_profit": false,
+            "schema_version": 1,
+            "feature_version": "v1_nofx_3m4h",
+            "embedding_model": "bge-small-en-v1.5",
+            "embedding_dim": 384,
+            "build_id": "abc123"
+        });
+        let v1 = v1.as_object().unwrap();
+
+        let migrated = migrate_payload(v1).unwrap().unwrap();
+        assert_eq!(migrated["schema_version"], json!(CURRENT_SCHEMA_VERSION));
+        assert_eq!(migrated["feature_version"], json!(CURRENT_FEATURE_VERSION.id));
+        assert_eq!(migrated["migrated_from"], json!(1));
+        assert_eq!(migrated["build_id"], json!("abc123"));
+        assert!(migrated.contains_key("snapshot"));
+
+        // Derived fields are reproduced from the rebuilt snapshot
+        for key in ["ema_ratio", "oi_delta_pct", "volatility_ratio", "outcome_4h", "max_runup_1h"] {
+            let (old, new) = (v1[key].as_f64().unwrap(), migrated[key].as_f64().unwrap());
+            assert!((old - new).abs() < 1e-9, "{}: {} vs {}", key, old, new);
+        }
+        assert_eq!(migrated["outcome_24h"], Value::Null);
+        assert_eq!(migrated["date"], v1["date"]);
+
+        // Migrating again is a no-op
+        assert!(migrate_payload(&migrated).unwrap().is_none());
+    }
+}
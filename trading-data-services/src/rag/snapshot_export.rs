@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use trading_core::MarketStateSnapshot;
+
+use super::embedder::SnapshotEmbedder;
+use super::snapshot_formatter::SnapshotFormatter;
+use super::vector_store::snapshot_point_payload;
+
+/// One line of a JSONL snapshot dump
+///
+/// Holds what ingestion would store for a snapshot, minus the embedding: the
+/// snapshot, its embedding text and its payload. The payload leaves out its
+/// copy of the snapshot, which is already on the record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotExportRecord {
+    pub snapshot: MarketStateSnapshot,
+    pub embedding_text: String,
+    pub payload: Map<String, Value>,
+}
+
+impl SnapshotExportRecord {
+    /// Build the record of a snapshot as `embedder` would ingest it (the model is not loaded)
+    pub fn new(snapshot: MarketStateSnapshot, embedder: &dyn SnapshotEmbedder) -> Result<Self> {
+        let mut payload = snapshot_point_payload(&snapshot, embedder)?;
+        payload.remove("snapshot");
+        Ok(Self {
+            embedding_text: snapshot.to_embedding_text(),
+            snapshot,
+            payload,
+        })
+    }
+}
+
+/// Write records as JSONL (one object per line)
+pub fn write_export<W: Write>(writer: &mut W, records: &[SnapshotExportRecord]) -> Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Read the records of a JSONL snapshot dump (blank lines are skipped)
+pub fn read_export(path: &Path) -> Result<Vec<SnapshotExportRecord>> {
+    let file = File::open(path).with_context(|| format!("Failed to open snapshot dump {}", path.display()))?;
+
+    let mut records = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read snapshot dump {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid record on line {} of {}", index + 1, path.display()))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedder::{FeatureSnapshotEmbedder, FEATURE_EMBEDDING_MODEL};
+
+    #[test]
+    fn test_export_round_trip() {
+        let embedder = FeatureSnapshotEmbedder::new();
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1_700_000_000_000, 50000.0);
+        snapshot.rsi_7 = 72.0;
+        let records = vec![
+            SnapshotExportRecord::new(snapshot.clone(), &embedder).unwrap(),
+            SnapshotExportRecord::new(MarketStateSnapshot::new("ETHUSDT".to_string(), 1_700_000_000_000, 3000.0), &embedder)
+                .unwrap(),
+        ];
+        assert_eq!(records[0].embedding_text, snapshot.to_embedding_text());
+        assert_eq!(records[0].payload["embedding_model"], FEATURE_EMBEDDING_MODEL);
+        assert_eq!(records[0].payload["rsi_7"], 72.0);
+        assert!(!records[0].payload.contains_key("snapshot"));
+
+        let path = std::env::temp_dir().join(format!("rag_export_{}.jsonl", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        write_export(&mut file, &records).unwrap();
+        writeln!(file).unwrap();
+        drop(file);
+
+        let read = read_export(&path).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].snapshot.rsi_7, 72.0);
+        assert_eq!(read[1].snapshot.symbol, "ETHUSDT");
+        assert_eq!(read[0].payload, records[0].payload);
+
+        std::fs::write(&path, "{\"snapshot\": 1}\n").unwrap();
+        let err = read_export(&path).unwrap_err();
+        assert!(err.to_string().contains("line 1"), "{}", err);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,34 +1,92 @@
 use anyhow::{anyhow, Context, Result};
-use serde_json::Value;
-use trading_core::{MarketStateSnapshot, TimestampMS};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::str::FromStr;
+use trading_core::indicators::Indicators3mCalculator;
+use trading_core::{
+    Candle, FundingRateRecord, Indicators3m, Indicators4h, LiquidationRecord, MarketStateSnapshot,
+    OpenInterestRecord, OrderBookRecord, OutcomeSpec, ReferenceContext, RegimeClassifier, Timeframe,
+    TimeframeContext, TimestampMS, TradeFlowRecord,
+};
 use tracing;
 
 use super::lmdb_reader::LmdbReader;
+use super::market_data_source::{indicators_4h_as_of, MarketDataSource};
+use super::mock_data_source::MockDataSource;
 
-/// Data source for snapshot extraction
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum DataSource {
-    /// Use LMDB storage (real historical data from llm-trader-data)
-    Lmdb,
-    /// Use mock data generator (for testing)
-    Mock,
+/// 3-minute candle interval in milliseconds
+const INTERVAL_3M_MS: i64 = 180_000;
+
+/// Lookback windows in milliseconds
+const ONE_HOUR_MS: i64 = 60 * 60_000;
+const FOUR_HOURS_MS: i64 = 4 * 60 * 60_000;
+const ONE_DAY_MS: i64 = 24 * 60 * 60_000;
+
+/// Window for trade flow and liquidation sums
+const FLOW_WINDOW_MS: i64 = 30 * 60_000;
+
+/// Window of 3m returns correlated with the reference symbol
+const CORRELATION_WINDOW_MS: i64 = FOUR_HOURS_MS;
+
+/// Number of points in each snapshot time series
+const SERIES_LEN: usize = 10;
+
+/// Candles read ahead of a gap so recomputed indicators have converged
+pub(crate) const INDICATOR_WARMUP: i64 = 200;
+
+/// Higher timeframes summarised in the snapshot's 1h/1d blocks
+const CONTEXT_TIMEFRAMES: [Timeframe; 2] = [Timeframe::H1, Timeframe::D1];
+
+/// How missing points in the snapshot time series are handled
+///
+/// Filled points are recorded in `missing_points_3m`/`missing_points_4h` and
+/// lower the snapshot's `data_quality`, so every series keeps `SERIES_LEN`
+/// evenly spaced points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapFillPolicy {
+    /// Skip snapshots with any missing point
+    Reject,
+    /// Repeat the previous point (the first present one for leading gaps)
+    #[default]
+    ForwardFill,
+    /// Interpolate linearly between neighbours (nearest point at the edges)
+    Interpolate,
+}
+
+impl FromStr for GapFillPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "ffill" | "forward-fill" => Ok(Self::ForwardFill),
+            "interpolate" => Ok(Self::Interpolate),
+            _ => Err(anyhow!(
+                "Invalid gap policy '{}'. Must be 'reject', 'ffill' or 'interpolate'",
+                s
+            )),
+        }
+    }
 }
 
-/// Extracts historical market snapshots from LMDB storage or mock data
-pub struct HistoricalSnapshotExtractor {
-    data_source: DataSource,
-    lmdb_reader: Option<LmdbReader>,
+/// Extracts historical market snapshots from any [`MarketDataSource`]
+pub struct HistoricalSnapshotExtractor<S> {
+    source: S,
+    gap_policy: GapFillPolicy,
+    outcome_spec: OutcomeSpec,
+    reference_symbol: Option<String>,
+    regime_classifier: RegimeClassifier,
 }
 
-impl HistoricalSnapshotExtractor {
+impl HistoricalSnapshotExtractor<MockDataSource> {
     /// Create a new snapshot extractor with mock data
     pub fn new() -> Self {
-        Self {
-            data_source: DataSource::Mock,
-            lmdb_reader: None,
-        }
+        Self::with_source(MockDataSource::new())
     }
+}
 
+impl HistoricalSnapshotExtractor<LmdbReader> {
     /// Create a snapshot extractor with LMDB backend
     ///
     /// # Arguments
@@ -42,14 +100,76 @@ impl HistoricalSnapshotExtractor {
 
         tracing::info!("SnapshotExtractor initialized with LMDB backend at {}", lmdb_path);
 
-        Ok(Self {
-            data_source: DataSource::Lmdb,
-            lmdb_reader: Some(lmdb_reader),
-        })
+        Ok(Self::with_source(lmdb_reader))
+    }
+}
+
+impl<S: MarketDataSource> HistoricalSnapshotExtractor<S> {
+    /// Create a snapshot extractor reading from the given data source
+    pub fn with_source(source: S) -> Self {
+        Self {
+            source,
+            gap_policy: GapFillPolicy::default(),
+            outcome_spec: OutcomeSpec::default(),
+            reference_symbol: None,
+            regime_classifier: RegimeClassifier::default(),
+        }
+    }
+
+    /// Set how missing time series points are handled
+    pub fn with_gap_policy(mut self, gap_policy: GapFillPolicy) -> Self {
+        self.gap_policy = gap_policy;
+        self
+    }
+
+    /// How missing time series points are handled
+    pub fn gap_policy(&self) -> GapFillPolicy {
+        self.gap_policy
+    }
+
+    /// Set the outcome horizons and stop/target rules
+    pub fn with_outcome_spec(mut self, outcome_spec: OutcomeSpec) -> Self {
+        self.outcome_spec = outcome_spec;
+        self
+    }
+
+    /// The outcome horizons and stop/target rules
+    pub fn outcome_spec(&self) -> &OutcomeSpec {
+        &self.outcome_spec
+    }
+
+    /// Attach the state of a reference symbol (e.g. "BTCUSDT") to snapshots of other symbols
+    pub fn with_reference_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.reference_symbol = Some(symbol.into());
+        self
+    }
+
+    /// The reference symbol, if one is attached
+    pub fn reference_symbol(&self) -> Option<&str> {
+        self.reference_symbol.as_deref()
+    }
+
+    /// Set the thresholds snapshots are labelled with
+    pub fn with_regime_classifier(mut self, regime_classifier: RegimeClassifier) -> Self {
+        self.regime_classifier = regime_classifier;
+        self
+    }
+
+    /// The underlying data source
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Mutable access to the underlying data source (e.g. to feed live updates)
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
     }
 
     /// Extract snapshots for a symbol in a time range
     ///
+    /// Snapshot timestamps start at the first 3m candle boundary at or after
+    /// `start_timestamp`, so they line up with the stored data.
+    ///
     /// # Arguments
     /// * `symbol` - Trading symbol (e.g., "BTCUSDT")
     /// * `start_timestamp` - Start time in milliseconds
@@ -65,37 +185,30 @@ impl HistoricalSnapshotExtractor {
         end_timestamp: TimestampMS,
         interval_minutes: u64,
     ) -> Result<Vec<MarketStateSnapshot>> {
-        match self.data_source {
-            DataSource::Lmdb => {
-                self.extract_from_lmdb(symbol, start_timestamp, end_timestamp, interval_minutes)
-            }
-            DataSource::Mock => {
-                self.extract_mock_snapshots(symbol, start_timestamp, end_timestamp, interval_minutes)
-            }
+        if interval_minutes == 0 {
+            return Err(anyhow!("Snapshot interval must be greater than zero"));
         }
-    }
-
-    /// Extract snapshots from LMDB storage
-    fn extract_from_lmdb(
-        &self,
-        symbol: &str,
-        start_timestamp: TimestampMS,
-        end_timestamp: TimestampMS,
-        interval_minutes: u64,
-    ) -> Result<Vec<MarketStateSnapshot>> {
-        let lmdb = self.lmdb_reader.as_ref()
-            .ok_or_else(|| anyhow!("LMDB reader not initialized"))?;
 
         let mut snapshots = Vec::new();
         let interval_ms = (interval_minutes * 60_000) as i64;
-        let mut current_ts = start_timestamp as i64;
+        let mut current_ts = align_to_3m(start_timestamp as i64);
         let end_ts = end_timestamp as i64;
+        let now_ms = chrono::Utc::now().timestamp_millis();
+
+        if current_ts >= end_ts {
+            return Ok(snapshots);
+        }
+
+        // Load everything the range needs up front, then build snapshots from memory
+        let lookahead_ms = self.outcome_spec.max_horizon_ms();
+        let window = DataWindow::load(&self.source, symbol, current_ts, end_ts - 1, lookahead_ms)?;
+        let reference = self.load_reference(symbol, current_ts, end_ts - 1)?;
 
         let mut success_count = 0;
         let mut skip_count = 0;
 
         while current_ts < end_ts {
-            match self.build_snapshot_from_lmdb(lmdb, symbol, current_ts) {
+            match self.build_snapshot(&window, reference.as_ref(), current_ts, now_ms) {
                 Ok(snapshot) => {
                     snapshots.push(snapshot);
                     success_count += 1;
@@ -115,124 +228,356 @@ impl HistoricalSnapshotExtractor {
         }
 
         tracing::info!(
-            "Extracted {} snapshots for {} from {} to {} ({} skipped due to missing data)",
+            "Extracted {} snapshots for {} from {} to {} using {} data ({} skipped due to missing data)",
             success_count,
             symbol,
             start_timestamp,
             end_timestamp,
+            self.source.name(),
             skip_count
         );
 
         Ok(snapshots)
     }
 
-    /// Build a complete snapshot from LMDB data
-    fn build_snapshot_from_lmdb(
+    /// Build the snapshot at a single timestamp, with `now_ms` taken as the present
+    ///
+    /// Unlike [`extract_snapshots`](Self::extract_snapshots) the timestamp is
+    /// used as given and failures are returned rather than skipped. With
+    /// `now_ms` at the snapshot time every outcome is left pending, as for a
+    /// snapshot built live.
+    pub fn extract_snapshot_at(
         &self,
-        lmdb: &LmdbReader,
         symbol: &str,
+        timestamp: TimestampMS,
+        now_ms: i64,
+    ) -> Result<MarketStateSnapshot> {
+        let timestamp = timestamp as i64;
+        let lookahead_ms = self.outcome_spec.max_horizon_ms().min(now_ms - timestamp).max(0);
+        let window = DataWindow::load(&self.source, symbol, timestamp, timestamp, lookahead_ms)?;
+        let reference = self.load_reference(symbol, timestamp, timestamp)?;
+        self.build_snapshot(&window, reference.as_ref(), timestamp, now_ms)
+    }
+
+    /// Recompute the outcomes of stored snapshots of a symbol with `now_ms` taken as the present
+    ///
+    /// Reads the 3m candles once, from the oldest snapshot to the last
+    /// horizon that has passed. Only the outcome fields change, so the
+    /// snapshots still match their embeddings. Used to backfill outcomes once
+    /// their horizons have passed.
+    pub fn refresh_outcomes(&self, symbol: &str, snapshots: &mut [MarketStateSnapshot], now_ms: i64) -> Result<()> {
+        if let Some(other) = snapshots.iter().find(|s| s.symbol != symbol) {
+            return Err(anyhow!("Cannot refresh outcomes of {} with those of {}", other.symbol, symbol));
+        }
+        let Some(start_ts) = snapshots.iter().map(|s| s.timestamp as i64).min() else {
+            return Ok(());
+        };
+        let horizon_ms = self.outcome_spec.max_horizon_ms();
+        let end_ts = snapshots
+            .iter()
+            .map(|s| s.timestamp as i64 + horizon_ms)
+            .max()
+            .unwrap_or(start_ts)
+            .min(now_ms)
+            .max(start_ts);
+
+        let candles_3m: BTreeMap<i64, Candle> = self
+            .source
+            .candles_3m(symbol, start_ts, end_ts)
+            .context("Failed to read 3m candles")?
+            .into_iter()
+            .collect();
+        for snapshot in snapshots.iter_mut() {
+            let timestamp = snapshot.timestamp as i64;
+            self.fill_outcomes(&candles_3m, symbol, timestamp, now_ms, snapshot);
+        }
+        Ok(())
+    }
+
+    /// Load the reference symbol's data, unless `symbol` is the reference itself
+    fn load_reference(&self, symbol: &str, start_ts: i64, end_ts: i64) -> Result<Option<ReferenceWindow>> {
+        match self.reference_symbol.as_deref() {
+            Some(reference) if reference != symbol => {
+                Ok(Some(ReferenceWindow::load(&self.source, reference, start_ts, end_ts)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Build a complete snapshot from preloaded market data
+    ///
+    /// `now_ms` bounds the outcome lookahead: horizons ending after it are left
+    /// unset and the snapshot is marked as pending. The reference context is
+    /// left unset when the reference has no data at `timestamp`.
+    fn build_snapshot(
+        &self,
+        window: &DataWindow,
+        reference: Option<&ReferenceWindow>,
         timestamp: i64,
+        now_ms: i64,
     ) -> Result<MarketStateSnapshot> {
+        let symbol = window.symbol.as_str();
+
         // Read 3-minute indicators (current point)
-        let indicators_3m = lmdb.read_indicators_3m(symbol, timestamp)?
+        let indicators_3m = window.indicators_3m.get(&timestamp)
             .ok_or_else(|| anyhow!("Missing 3m indicators for {} at {}", symbol, timestamp))?;
 
         // Read 4-hour indicators (current point)
-        let indicators_4h = lmdb.read_indicators_4h(symbol, timestamp)?
+        let indicators_4h = window.indicators_4h.get(&timestamp)
             .ok_or_else(|| anyhow!("Missing 4h indicators for {} at {}", symbol, timestamp))?;
 
         // Read candle for price data
-        let candle_3m = lmdb.read_candles_3m(symbol, timestamp)?
+        let candle_3m = window.candles_3m.get(&timestamp)
             .ok_or_else(|| anyhow!("Missing 3m candle for {} at {}", symbol, timestamp))?;
 
-        // Extract price from candle
-        let price = candle_3m.get("close")
-            .and_then(|v| v.as_f64())
-            .ok_or_else(|| anyhow!("Missing close price in candle"))?;
-
         // Create snapshot
         let mut snapshot = MarketStateSnapshot::new(
             symbol.to_string(),
             timestamp as TimestampMS,
-            price
+            candle_3m.close
         );
 
         // Fill 3-minute indicators
-        snapshot.rsi_7 = Self::extract_f64(&indicators_3m, "rsi_7")?;
-        snapshot.rsi_14 = Self::extract_f64(&indicators_3m, "rsi_14")?;
-        snapshot.macd = Self::extract_f64(&indicators_3m, "macd")?;
-        snapshot.ema_20 = Self::extract_f64(&indicators_3m, "ema_20")?;
+        snapshot.rsi_7 = indicators_3m.rsi_7;
+        snapshot.rsi_14 = indicators_3m.rsi_14;
+        snapshot.macd = indicators_3m.macd;
+        snapshot.ema_20 = indicators_3m.ema_20;
 
         // Fill 4-hour indicators
-        snapshot.ema_20_4h = Self::extract_f64(&indicators_4h, "ema_20")?;
-        snapshot.ema_50_4h = Self::extract_f64(&indicators_4h, "ema_50")?;
-        snapshot.atr_3_4h = Self::extract_f64(&indicators_4h, "atr_3")?;
-        snapshot.atr_14_4h = Self::extract_f64(&indicators_4h, "atr_14")?;
+        snapshot.ema_20_4h = indicators_4h.ema_20;
+        snapshot.ema_50_4h = indicators_4h.ema_50;
+        snapshot.atr_3_4h = indicators_4h.atr_3;
+        snapshot.atr_14_4h = indicators_4h.atr_14;
+        self.fill_volume_4h(window, timestamp, indicators_4h, &mut snapshot);
+
+        // Higher timeframe blocks (left unset when the source has no such candles)
+        snapshot.context_1h = window.context_as_of(Timeframe::H1, timestamp);
+        snapshot.context_1d = window.context_as_of(Timeframe::D1, timestamp);
 
         // Read time series data (last 10 points)
-        self.fill_time_series_3m(lmdb, symbol, timestamp, &mut snapshot)?;
-        self.fill_time_series_4h(lmdb, symbol, timestamp, &mut snapshot)?;
-
-        // TODO: Add derivatives data (OI, funding rate) when available in LMDB
-        // For now, use placeholder values
-        snapshot.open_interest_latest = 0.0;
-        snapshot.open_interest_avg_24h = 0.0;
-        snapshot.funding_rate = 0.0;
-        snapshot.price_change_1h = 0.0;
-        snapshot.price_change_4h = 0.0;
-
-        // TODO: Calculate outcomes from future data
-        // This requires querying future candles and calculating price changes
-        snapshot.outcome_15m = None;
-        snapshot.outcome_1h = None;
-        snapshot.outcome_4h = None;
-        snapshot.outcome_24h = None;
+        self.fill_time_series_3m(window, timestamp, &mut snapshot)?;
+        self.fill_time_series_4h(window, timestamp, &mut snapshot)?;
+        let missing = snapshot.missing_points_3m.len() + snapshot.missing_points_4h.len();
+        snapshot.data_quality = 1.0 - missing as f64 / (2 * SERIES_LEN) as f64;
+
+        // Derivatives data (marked unavailable when the databases are missing)
+        self.fill_derivatives(window, timestamp, &mut snapshot);
+        self.fill_microstructure(window, timestamp, &mut snapshot);
+
+        // Price changes from the 3m candle history
+        self.fill_price_changes(window, timestamp, &mut snapshot);
+
+        // Reference asset state (e.g. BTC) for other symbols
+        snapshot.reference = reference.and_then(|reference| reference.context_at(window, timestamp));
+
+        // Regime labels from the filled indicators and derivatives
+        snapshot.regime = Some(self.regime_classifier.classify(&snapshot));
+
+        // Calculate outcomes from future 3m candles
+        self.fill_outcomes(&window.candles_3m, symbol, timestamp, now_ms, &mut snapshot);
 
         Ok(snapshot)
     }
 
-    /// Fill 3-minute time series data
-    fn fill_time_series_3m(
+    /// Fill the latest closed 4h candle volume and its average
+    ///
+    /// The average is the `avg_volume_20` of the 4h indicators at this point.
+    /// Fields stay at 0.0 when the source has no closed 4h candle here or no
+    /// volume average could be computed.
+    fn fill_volume_4h(
         &self,
-        lmdb: &LmdbReader,
-        symbol: &str,
-        end_timestamp: i64,
+        window: &DataWindow,
+        timestamp: i64,
+        indicators_4h: &Indicators4h,
         snapshot: &mut MarketStateSnapshot,
-    ) -> Result<()> {
-        let interval_3m = 180_000; // 3 minutes in ms
-        let series = lmdb.read_indicators_3m_series(symbol, end_timestamp, interval_3m, 10)?;
+    ) {
+        // Candles opened at or before this point have closed by the end of the 3m candle
+        let last_closed_open = timestamp + INTERVAL_3M_MS - FOUR_HOURS_MS;
+        let current = window
+            .candles_4h
+            .range(..=last_closed_open)
+            .next_back()
+            .filter(|(ts, _)| **ts > last_closed_open - FOUR_HOURS_MS);
 
-        if series.is_empty() {
-            return Err(anyhow!("No 3m time series data available"));
+        match (current, indicators_4h.avg_volume_20) {
+            (Some((_, candle)), Some(avg_volume)) => {
+                snapshot.current_volume_4h = candle.volume;
+                snapshot.avg_volume_4h = avg_volume;
+            }
+            _ => tracing::debug!(
+                "4h volume unavailable for {} at {}",
+                window.symbol,
+                timestamp
+            ),
         }
+    }
 
-        // Extract vectors from series
-        snapshot.ema_20_values = series.iter()
-            .filter_map(|(_, data)| data.get("ema_20").and_then(|v| v.as_f64()))
-            .collect();
+    /// Fill open interest and funding rate
+    ///
+    /// Fields stay at 0.0 with `has_open_interest`/`has_funding_rate` set to false
+    /// when the database is missing or has no record for this timestamp.
+    fn fill_derivatives(
+        &self,
+        window: &DataWindow,
+        timestamp: i64,
+        snapshot: &mut MarketStateSnapshot,
+    ) {
+        let oi_latest = window.open_interest.get(&timestamp).map(|r| r.open_interest);
+        let (oi_sum, oi_count) = window
+            .open_interest
+            .range(timestamp - ONE_DAY_MS + INTERVAL_3M_MS..=timestamp)
+            .fold((0.0, 0usize), |(sum, count), (_, r)| (sum + r.open_interest, count + 1));
+        let oi_avg = (oi_count > 0).then(|| oi_sum / oi_count as f64);
 
-        snapshot.macd_values = series.iter()
-            .filter_map(|(_, data)| data.get("macd").and_then(|v| v.as_f64()))
-            .collect();
+        match (oi_latest, oi_avg) {
+            (Some(latest), Some(avg)) => {
+                snapshot.open_interest_latest = latest;
+                snapshot.open_interest_avg_24h = avg;
+                snapshot.has_open_interest = true;
+            }
+            _ => {
+                snapshot.open_interest_latest = 0.0;
+                snapshot.open_interest_avg_24h = 0.0;
+                snapshot.has_open_interest = false;
+            }
+        }
 
-        snapshot.rsi_7_values = series.iter()
-            .filter_map(|(_, data)| data.get("rsi_7").and_then(|v| v.as_f64()))
-            .collect();
+        let funding_rate = window.funding_rate.get(&timestamp).map(|r| r.funding_rate);
+
+        snapshot.funding_rate = funding_rate.unwrap_or(0.0);
+        snapshot.has_funding_rate = funding_rate.is_some();
+    }
+
+    /// Fill order book, trade flow and liquidation features
+    ///
+    /// The order book is read from the latest top-of-book snapshot within the
+    /// 3m candle; flows and liquidations are summed over the 3m candles of the
+    /// last 30 minutes. Each field stays `None` when its database is missing
+    /// or has no records in the window.
+    fn fill_microstructure(
+        &self,
+        window: &DataWindow,
+        timestamp: i64,
+        snapshot: &mut MarketStateSnapshot,
+    ) {
+        let book = window
+            .order_book
+            .range(timestamp - INTERVAL_3M_MS + 1..=timestamp)
+            .next_back()
+            .map(|(_, book)| book);
+        snapshot.spread_bps = book.and_then(OrderBookRecord::spread_bps);
+        snapshot.book_imbalance = book.and_then(OrderBookRecord::imbalance);
+
+        let flow_start = timestamp - FLOW_WINDOW_MS + INTERVAL_3M_MS;
+        let flow = window
+            .trade_flow
+            .range(flow_start..=timestamp)
+            .fold(None, |acc: Option<(f64, f64)>, (_, r)| {
+                let (buy, sell) = acc.unwrap_or_default();
+                Some((buy + r.taker_buy_volume, sell + r.taker_sell_volume))
+            });
+        if let Some((buy, sell)) = flow {
+            snapshot.cvd_30m = Some(buy - sell);
+            snapshot.taker_buy_sell_ratio_30m = (sell > 0.0).then(|| buy / sell);
+        }
+
+        let liquidations = window
+            .liquidations
+            .range(flow_start..=timestamp)
+            .fold(None, |acc: Option<(f64, f64)>, (_, r)| {
+                let (long, short) = acc.unwrap_or_default();
+                Some((long + r.long_liquidations, short + r.short_liquidations))
+            });
+        if let Some((long, short)) = liquidations {
+            snapshot.long_liquidations_30m = Some(long);
+            snapshot.short_liquidations_30m = Some(short);
+        }
+    }
+
+    /// Fill 1h and 4h price changes from past 3-minute closes
+    ///
+    /// A change whose past candle is missing stays at 0.0 with its
+    /// `has_price_change_*` flag set to false.
+    fn fill_price_changes(
+        &self,
+        window: &DataWindow,
+        timestamp: i64,
+        snapshot: &mut MarketStateSnapshot,
+    ) {
+        let price = snapshot.price;
+        let change_since = |lookback_ms: i64| -> Option<f64> {
+            window
+                .close_3m(timestamp - lookback_ms)
+                .filter(|past| past.abs() > 1e-10)
+                .map(|past| ((price - past) / past) * 100.0)
+        };
+
+        let change_1h = change_since(ONE_HOUR_MS);
+        if change_1h.is_none() {
+            tracing::debug!("No 3m candle 1h before {} for {}", timestamp, window.symbol);
+        }
+        snapshot.price_change_1h = change_1h.unwrap_or(0.0);
+        snapshot.has_price_change_1h = change_1h.is_some();
+
+        let change_4h = change_since(FOUR_HOURS_MS);
+        if change_4h.is_none() {
+            tracing::debug!("No 3m candle 4h before {} for {}", timestamp, window.symbol);
+        }
+        snapshot.price_change_4h = change_4h.unwrap_or(0.0);
+        snapshot.has_price_change_4h = change_4h.is_some();
+    }
 
-        snapshot.rsi_14_values = series.iter()
-            .filter_map(|(_, data)| data.get("rsi_14").and_then(|v| v.as_f64()))
+    /// Fill outcomes from future 3-minute closes
+    ///
+    /// Reads every 3m candle up to the longest horizon of the outcome spec,
+    /// evaluates each horizon on the closes and simulates a long and a short
+    /// trade on the highs/lows. Horizons that end after `now_ms`
+    /// are left unset and the snapshot is marked as pending so it can be backfilled.
+    fn fill_outcomes(
+        &self,
+        candles_3m: &BTreeMap<i64, Candle>,
+        symbol: &str,
+        timestamp: i64,
+        now_ms: i64,
+        snapshot: &mut MarketStateSnapshot,
+    ) {
+        let lookahead_ms = self.outcome_spec.max_horizon_ms().min(now_ms - timestamp).max(0);
+        let path: Vec<(i64, Candle)> = candles_3m
+            .range((Bound::Excluded(timestamp), Bound::Included(timestamp + lookahead_ms)))
+            .map(|(ts, candle)| (ts - timestamp, *candle))
             .collect();
 
-        // Fill mid_prices from candles
-        let candles: Result<Vec<_>> = series.iter()
-            .map(|(ts, _)| {
-                lmdb.read_candles_3m(symbol, *ts)?
-                    .and_then(|c| c.get("close").and_then(|v| v.as_f64()))
-                    .ok_or_else(|| anyhow!("Missing candle close price"))
-            })
+        snapshot.calculate_outcomes(&self.outcome_spec, &path, now_ms - timestamp);
+        snapshot.outcomes_pending = timestamp + self.outcome_spec.max_horizon_ms() > now_ms;
+
+        if snapshot.outcomes_pending {
+            tracing::debug!(
+                "Outcomes pending for {} at {} (horizon extends past now)",
+                symbol,
+                timestamp
+            );
+        }
+    }
+
+    /// Fill 3-minute time series data
+    fn fill_time_series_3m(
+        &self,
+        window: &DataWindow,
+        end_timestamp: i64,
+        snapshot: &mut MarketStateSnapshot,
+    ) -> Result<()> {
+        // A point needs both its indicators and its candle
+        let points: Vec<Option<(&Indicators3m, f64)>> = series_timestamps(end_timestamp, INTERVAL_3M_MS)
+            .map(|ts| window.indicators_3m.get(&ts).zip(window.close_3m(ts)))
             .collect();
+        let missing = self.check_gaps(&points, "3m", window, end_timestamp)?;
 
-        snapshot.mid_prices = candles?;
+        snapshot.ema_20_values = fill_gaps(points.iter().map(|p| p.map(|(data, _)| data.ema_20)), self.gap_policy);
+        snapshot.macd_values = fill_gaps(points.iter().map(|p| p.map(|(data, _)| data.macd)), self.gap_policy);
+        snapshot.rsi_7_values = fill_gaps(points.iter().map(|p| p.map(|(data, _)| data.rsi_7)), self.gap_policy);
+        snapshot.rsi_14_values = fill_gaps(points.iter().map(|p| p.map(|(data, _)| data.rsi_14)), self.gap_policy);
+        snapshot.mid_prices = fill_gaps(points.iter().map(|p| p.map(|(_, close)| close)), self.gap_policy);
+        snapshot.missing_points_3m = missing;
 
         Ok(())
     }
@@ -240,120 +585,403 @@ impl HistoricalSnapshotExtractor {
     /// Fill 4-hour time series data
     fn fill_time_series_4h(
         &self,
-        lmdb: &LmdbReader,
-        symbol: &str,
+        window: &DataWindow,
         end_timestamp: i64,
         snapshot: &mut MarketStateSnapshot,
     ) -> Result<()> {
-        let interval_4h = 14_400_000; // 4 hours in ms
-        let series = lmdb.read_indicators_4h_series(symbol, end_timestamp, interval_4h, 10)?;
+        let points: Vec<Option<&Indicators4h>> = series_timestamps(end_timestamp, FOUR_HOURS_MS)
+            .map(|ts| window.indicators_4h.get(&ts))
+            .collect();
+        let missing = self.check_gaps(&points, "4h", window, end_timestamp)?;
+
+        snapshot.macd_4h_values = fill_gaps(points.iter().map(|p| p.map(|data| data.macd)), self.gap_policy);
+        snapshot.rsi_14_4h_values = fill_gaps(points.iter().map(|p| p.map(|data| data.rsi_14)), self.gap_policy);
+        snapshot.missing_points_4h = missing;
+
+        Ok(())
+    }
+
+    /// Indices of missing series points, or an error if the gap policy rejects them
+    fn check_gaps<T>(
+        &self,
+        points: &[Option<T>],
+        timeframe: &str,
+        window: &DataWindow,
+        end_timestamp: i64,
+    ) -> Result<Vec<usize>> {
+        let missing: Vec<usize> = (0..points.len()).filter(|i| points[*i].is_none()).collect();
 
-        if series.is_empty() {
-            return Err(anyhow!("No 4h time series data available"));
+        if missing.len() == points.len() {
+            return Err(anyhow!("No {} time series data available", timeframe));
+        }
+        if !missing.is_empty() {
+            if self.gap_policy == GapFillPolicy::Reject {
+                return Err(anyhow!(
+                    "{} of {} {} time series points missing",
+                    missing.len(),
+                    points.len(),
+                    timeframe
+                ));
+            }
+            tracing::debug!(
+                "Filling {} missing {} series points for {} at {} ({:?})",
+                missing.len(),
+                timeframe,
+                window.symbol,
+                end_timestamp,
+                self.gap_policy
+            );
         }
 
-        snapshot.macd_4h_values = series.iter()
-            .filter_map(|(_, data)| data.get("macd").and_then(|v| v.as_f64()))
+        Ok(missing)
+    }
+}
+
+/// Timestamps of the `SERIES_LEN` points ending at `end_timestamp`, oldest first
+fn series_timestamps(end_timestamp: i64, interval_ms: i64) -> impl Iterator<Item = i64> {
+    (0..SERIES_LEN as i64)
+        .rev()
+        .map(move |i| end_timestamp - i * interval_ms)
+}
+
+/// Replace missing values according to the gap policy
+///
+/// At least one value must be present. `Reject` is handled before filling and
+/// behaves like `ForwardFill` here.
+fn fill_gaps(values: impl Iterator<Item = Option<f64>>, policy: GapFillPolicy) -> Vec<f64> {
+    let values: Vec<Option<f64>> = values.collect();
+    let present: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.map(|v| (i, v)))
+        .collect();
+    let Some(&(_, first)) = present.first() else {
+        return Vec::new();
+    };
+
+    let mut previous = None;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            if let Some(value) = value {
+                previous = Some((i, *value));
+                return *value;
+            }
+            let next = present.iter().find(|(j, _)| *j > i).copied();
+            match (policy, previous, next) {
+                (GapFillPolicy::Interpolate, Some((i0, v0)), Some((i1, v1))) => {
+                    v0 + (v1 - v0) * (i - i0) as f64 / (i1 - i0) as f64
+                }
+                (GapFillPolicy::Interpolate, None, Some((_, v1))) => v1,
+                (_, Some((_, v0)), _) => v0,
+                _ => first,
+            }
+        })
+        .collect()
+}
+
+/// Round a timestamp up to the next 3m candle boundary
+fn align_to_3m(timestamp_ms: i64) -> i64 {
+    let aligned = timestamp_ms.div_euclid(INTERVAL_3M_MS) * INTERVAL_3M_MS;
+    if aligned < timestamp_ms {
+        aligned + INTERVAL_3M_MS
+    } else {
+        aligned
+    }
+}
+
+/// 3m grid timestamps in `[from_ts, to_ts]`
+fn grid_3m(from_ts: i64, to_ts: i64) -> impl Iterator<Item = i64> {
+    let first = align_to_3m(from_ts);
+    (0..)
+        .map(move |i| first + i * INTERVAL_3M_MS)
+        .take_while(move |ts| *ts <= to_ts)
+}
+
+/// Compute 3m indicators of a symbol from candles wherever `indicators` has none
+fn recompute_missing_3m<S: MarketDataSource>(
+    source: &S,
+    symbol: &str,
+    indicators: &mut BTreeMap<i64, Indicators3m>,
+    from_ts: i64,
+    to_ts: i64,
+) -> Result<()> {
+    let missing = grid_3m(from_ts, to_ts).any(|ts| !indicators.contains_key(&ts));
+    if !missing {
+        return Ok(());
+    }
+
+    let candles = source
+        .candles_3m(symbol, from_ts - INDICATOR_WARMUP * INTERVAL_3M_MS, to_ts)
+        .context("Failed to read 3m candles for indicators")?;
+    let computed = Indicators3mCalculator::compute(&candles)
+        .into_iter()
+        .filter(|(ts, _)| *ts >= from_ts);
+    let filled = insert_missing(indicators, computed);
+
+    if filled > 0 {
+        tracing::debug!("Recomputed {} 3m indicator points for {} from candles", filled, symbol);
+    }
+    Ok(())
+}
+
+/// Insert records for timestamps that have none, returning how many were added
+fn insert_missing<T: Copy>(records: &mut BTreeMap<i64, T>, computed: impl IntoIterator<Item = (i64, T)>) -> usize {
+    computed
+        .into_iter()
+        .filter(|(ts, record)| match records.entry(*ts) {
+            Entry::Vacant(entry) => {
+                entry.insert(*record);
+                true
+            }
+            Entry::Occupied(_) => false,
+        })
+        .count()
+}
+
+/// Market data for one symbol, loaded with one range read per dataset
+///
+/// Covers the snapshot range plus the lookback needed for time series, price
+/// changes, the 24h open interest average and the 30m flow sums, and the lookahead needed for
+/// the longest outcome horizon, so every snapshot in the range is built from memory.
+///
+/// Indicators missing from the source are recomputed from its candles.
+/// Higher timeframe contexts are always computed from candles, keyed by the
+/// close time of the candle they describe.
+struct DataWindow {
+    symbol: String,
+    candles_3m: BTreeMap<i64, Candle>,
+    candles_4h: BTreeMap<i64, Candle>,
+    indicators_3m: BTreeMap<i64, Indicators3m>,
+    indicators_4h: BTreeMap<i64, Indicators4h>,
+    open_interest: BTreeMap<i64, OpenInterestRecord>,
+    funding_rate: BTreeMap<i64, FundingRateRecord>,
+    order_book: BTreeMap<i64, OrderBookRecord>,
+    trade_flow: BTreeMap<i64, TradeFlowRecord>,
+    liquidations: BTreeMap<i64, LiquidationRecord>,
+    contexts: BTreeMap<Timeframe, BTreeMap<i64, TimeframeContext>>,
+}
+
+impl DataWindow {
+    /// Load all data needed for snapshots between `start_ts` and `end_ts` (inclusive)
+    /// with 3m candles `lookahead_ms` past the end for outcomes
+    fn load<S: MarketDataSource>(
+        source: &S,
+        symbol: &str,
+        start_ts: i64,
+        end_ts: i64,
+        lookahead_ms: i64,
+    ) -> Result<Self> {
+        let series_3m_lookback = (SERIES_LEN as i64 - 1) * INTERVAL_3M_MS;
+        let series_4h_lookback = (SERIES_LEN as i64 - 1) * FOUR_HOURS_MS;
+
+        let candles_3m = source
+            .candles_3m(symbol, start_ts - FOUR_HOURS_MS.max(series_3m_lookback), end_ts + lookahead_ms)
+            .context("Failed to read 3m candles")?
+            .into_iter()
+            .collect();
+        let candles_4h = source
+            .candles_4h(symbol, start_ts - 2 * FOUR_HOURS_MS, end_ts)
+            .context("Failed to read 4h candles")?
+            .into_iter()
+            .collect();
+        let indicators_3m = source
+            .indicators_3m(symbol, start_ts - series_3m_lookback, end_ts)
+            .context("Failed to read 3m indicators")?
+            .into_iter()
+            .collect();
+        let indicators_4h = source
+            .indicators_4h(symbol, start_ts - series_4h_lookback, end_ts)
+            .context("Failed to read 4h indicators")?
+            .into_iter()
+            .collect();
+        let open_interest = source
+            .open_interest(symbol, start_ts - ONE_DAY_MS + INTERVAL_3M_MS, end_ts)
+            .context("Failed to read open interest")?
+            .into_iter()
+            .collect();
+        let funding_rate = source
+            .funding_rate(symbol, start_ts, end_ts)
+            .context("Failed to read funding rate")?
+            .into_iter()
+            .collect();
+        let order_book = source
+            .order_book(symbol, start_ts - INTERVAL_3M_MS + 1, end_ts)
+            .context("Failed to read order book")?
+            .into_iter()
+            .collect();
+        let trade_flow = source
+            .trade_flow(symbol, start_ts - FLOW_WINDOW_MS + INTERVAL_3M_MS, end_ts)
+            .context("Failed to read trade flow")?
+            .into_iter()
+            .collect();
+        let liquidations = source
+            .liquidations(symbol, start_ts - FLOW_WINDOW_MS + INTERVAL_3M_MS, end_ts)
+            .context("Failed to read liquidations")?
+            .into_iter()
             .collect();
 
-        snapshot.rsi_14_4h_values = series.iter()
-            .filter_map(|(_, data)| data.get("rsi_14").and_then(|v| v.as_f64()))
+        let mut contexts = BTreeMap::new();
+        for timeframe in CONTEXT_TIMEFRAMES {
+            let duration_ms = timeframe.duration_ms();
+            let candles = source
+                .candles(symbol, timeframe, start_ts - (INDICATOR_WARMUP + 1) * duration_ms, end_ts)
+                .with_context(|| format!("Failed to read {} candles", timeframe))?;
+            let by_close: BTreeMap<i64, TimeframeContext> = TimeframeContext::compute(&candles)
+                .into_iter()
+                .map(|(open_ts, context)| (open_ts + duration_ms, context))
+                .collect();
+            contexts.insert(timeframe, by_close);
+        }
+
+        let mut window = Self {
+            symbol: symbol.to_string(),
+            candles_3m,
+            candles_4h,
+            indicators_3m,
+            indicators_4h,
+            open_interest,
+            funding_rate,
+            order_book,
+            trade_flow,
+            liquidations,
+            contexts,
+        };
+        window.recompute_missing_3m(source, start_ts - series_3m_lookback, end_ts)?;
+        window.recompute_missing_4h(source, start_ts - series_4h_lookback, end_ts)?;
+
+        Ok(window)
+    }
+
+    /// Compute 3m indicators from candles wherever the source has none
+    fn recompute_missing_3m<S: MarketDataSource>(&mut self, source: &S, from_ts: i64, to_ts: i64) -> Result<()> {
+        recompute_missing_3m(source, &self.symbol, &mut self.indicators_3m, from_ts, to_ts)
+    }
+
+    /// Compute 4h indicators from 4h candles wherever the source has none
+    ///
+    /// Records from a source without volume averages (e.g. LMDB) keep their
+    /// values and only get the computed `avg_volume_20`.
+    fn recompute_missing_4h<S: MarketDataSource>(&mut self, source: &S, from_ts: i64, to_ts: i64) -> Result<()> {
+        let missing: Vec<i64> = grid_3m(from_ts, to_ts)
+            .filter(|ts| self.indicators_4h.get(ts).is_none_or(|ind| ind.avg_volume_20.is_none()))
             .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let candles = source
+            .candles_4h(&self.symbol, from_ts - (INDICATOR_WARMUP + 1) * FOUR_HOURS_MS, to_ts)
+            .context("Failed to read 4h candles for indicators")?;
+        let computed = indicators_4h_as_of(&candles, missing);
+        for (ts, indicators) in &computed {
+            if let Some(stored) = self.indicators_4h.get_mut(ts) {
+                stored.avg_volume_20 = indicators.avg_volume_20;
+            }
+        }
+        let filled = insert_missing(&mut self.indicators_4h, computed);
 
+        if filled > 0 {
+            tracing::debug!("Recomputed {} 4h indicator points for {} from candles", filled, self.symbol);
+        }
         Ok(())
     }
 
-    /// Extract f64 value from JSON with error handling
-    fn extract_f64(json: &Value, field: &str) -> Result<f64> {
-        json.get(field)
-            .and_then(|v| v.as_f64())
-            .ok_or_else(|| anyhow!("Missing or invalid field: {}", field))
+    /// Context of the last candle of a timeframe that had closed by the end
+    /// of the 3m candle at `timestamp` (None if that candle is missing)
+    fn context_as_of(&self, timeframe: Timeframe, timestamp: i64) -> Option<TimeframeContext> {
+        let seen_by = timestamp + INTERVAL_3M_MS;
+        let (close_ts, context) = self.contexts.get(&timeframe)?.range(..=seen_by).next_back()?;
+        (*close_ts > seen_by - timeframe.duration_ms()).then_some(*context)
     }
 
-    /// Extract snapshots using mock data generator
-    fn extract_mock_snapshots(
-        &self,
-        symbol: &str,
-        start_timestamp: TimestampMS,
-        end_timestamp: TimestampMS,
-        interval_minutes: u64,
-    ) -> Result<Vec<MarketStateSnapshot>> {
-        let mut snapshots = Vec::new();
-        let interval_ms = interval_minutes * 60_000;
+    /// Close of the 3m candle at exactly `timestamp`
+    fn close_3m(&self, timestamp: i64) -> Option<f64> {
+        self.candles_3m.get(&timestamp).map(|c| c.close)
+    }
+}
 
-        let mut current_ts = start_timestamp;
+/// 3m candles and indicators of the reference symbol over a snapshot range
+///
+/// Candles reach back 4h before the range for price changes and the return
+/// correlation with the snapshot symbol.
+struct ReferenceWindow {
+    symbol: String,
+    candles_3m: BTreeMap<i64, Candle>,
+    indicators_3m: BTreeMap<i64, Indicators3m>,
+}
 
-        while current_ts < end_timestamp {
-            let snapshot = self.create_mock_snapshot(symbol, current_ts)?;
-            snapshots.push(snapshot);
-            current_ts += interval_ms;
-        }
+impl ReferenceWindow {
+    /// Load reference data for snapshots between `start_ts` and `end_ts` (inclusive)
+    fn load<S: MarketDataSource>(source: &S, symbol: &str, start_ts: i64, end_ts: i64) -> Result<Self> {
+        let candles_3m = source
+            .candles_3m(symbol, start_ts - FOUR_HOURS_MS.max(CORRELATION_WINDOW_MS), end_ts)
+            .with_context(|| format!("Failed to read 3m candles of reference {}", symbol))?
+            .into_iter()
+            .collect();
+        let mut indicators_3m = source
+            .indicators_3m(symbol, start_ts, end_ts)
+            .with_context(|| format!("Failed to read 3m indicators of reference {}", symbol))?
+            .into_iter()
+            .collect();
+        recompute_missing_3m(source, symbol, &mut indicators_3m, start_ts, end_ts)?;
 
-        tracing::info!(
-            "Extracted {} snapshots for {} from {} to {} (using mock data)",
-            snapshots.len(),
-            symbol,
-            start_timestamp,
-            end_timestamp
-        );
+        Ok(Self {
+            symbol: symbol.to_string(),
+            candles_3m,
+            indicators_3m,
+        })
+    }
 
-        Ok(snapshots)
+    /// Reference state at `timestamp` (None without a reference candle and RSI there)
+    fn context_at(&self, window: &DataWindow, timestamp: i64) -> Option<ReferenceContext> {
+        let price = self.close_3m(timestamp)?;
+        let rsi_14 = self.indicators_3m.get(&timestamp)?.rsi_14;
+        let change_since = |lookback_ms: i64| {
+            self.close_3m(timestamp - lookback_ms)
+                .filter(|past| past.abs() > 1e-10)
+                .map(|past| ((price - past) / past) * 100.0)
+        };
+
+        Some(ReferenceContext {
+            symbol: self.symbol.clone(),
+            price_change_1h: change_since(ONE_HOUR_MS),
+            price_change_4h: change_since(FOUR_HOURS_MS),
+            rsi_14,
+            correlation_4h: self.correlation(window, timestamp),
+        })
     }
 
-    /// Create a mock snapshot for testing
-    fn create_mock_snapshot(&self, symbol: &str, timestamp: TimestampMS) -> Result<MarketStateSnapshot> {
-        use std::f64::consts::PI;
-
-        // Create deterministic but varying mock data based on timestamp
-        let t = (timestamp as f64) / 1000000.0;
-        let base_price = 50000.0 + (t * PI).sin() * 5000.0;
-
-        let mut snapshot = MarketStateSnapshot::new(symbol.to_string(), timestamp, base_price);
-
-        // Mock indicators with some variation
-        snapshot.rsi_7 = 50.0 + (t * 2.0 * PI).sin() * 30.0;
-        snapshot.rsi_14 = 50.0 + (t * 1.5 * PI).sin() * 25.0;
-        snapshot.macd = (t * PI).sin() * 100.0;
-        snapshot.ema_20 = base_price * 0.99;
-        snapshot.ema_20_4h = base_price * 0.98;
-        snapshot.ema_50_4h = base_price * 0.97;
-        snapshot.atr_3_4h = 200.0;
-        snapshot.atr_14_4h = 250.0;
-        snapshot.current_volume_4h = 1000000.0;
-        snapshot.avg_volume_4h = 900000.0;
-        snapshot.open_interest_latest = 100000.0 + (t * PI).sin() * 10000.0;
-        snapshot.open_interest_avg_24h = 100000.0;
-        snapshot.funding_rate = (t * PI).sin() * 0.0002;
-        snapshot.price_change_1h = (t * PI).sin() * 2.0;
-        snapshot.price_change_4h = (t * 0.5 * PI).sin() * 4.0;
-
-        // Mock time series data
-        snapshot.mid_prices = vec![base_price; 10];
-        snapshot.ema_20_values = vec![base_price * 0.99; 10];
-        snapshot.macd_values = vec![snapshot.macd; 10];
-        snapshot.rsi_7_values = vec![snapshot.rsi_7; 10];
-        snapshot.rsi_14_values = vec![snapshot.rsi_14; 10];
-        snapshot.macd_4h_values = vec![snapshot.macd; 10];
-        snapshot.rsi_14_4h_values = vec![snapshot.rsi_14; 10];
-
-        // Mock outcomes (simulate future price movement)
-        let future_change = (t * PI * 3.0).sin() * 2.0; // Random-ish % change
-        snapshot.outcome_15m = Some(future_change * 0.25);
-        snapshot.outcome_1h = Some(future_change * 0.5);
-        snapshot.outcome_4h = Some(future_change);
-        snapshot.outcome_24h = Some(future_change * 1.5);
-
-        // Mock intraperiod metrics
-        snapshot.max_runup_1h = Some(future_change.abs() * 1.2);
-        snapshot.max_drawdown_1h = Some(-future_change.abs() * 0.8);
-        snapshot.hit_stop_loss = Some(future_change < -1.5);
-        snapshot.hit_take_profit = Some(future_change > 2.5);
+    /// Correlation of 3m returns with the snapshot symbol over the window
+    /// ending at `timestamp`
+    ///
+    /// Only returns whose two candles exist for both symbols are paired.
+    fn correlation(&self, window: &DataWindow, timestamp: i64) -> Option<f64> {
+        let (mut symbol_returns, mut reference_returns) = (Vec::new(), Vec::new());
+        for ts in grid_3m(timestamp - CORRELATION_WINDOW_MS + INTERVAL_3M_MS, timestamp) {
+            let prev_ts = ts - INTERVAL_3M_MS;
+            let pair = (
+                window.close_3m(prev_ts).zip(window.close_3m(ts)),
+                self.close_3m(prev_ts).zip(self.close_3m(ts)),
+            );
+            if let (Some((s0, s1)), Some((r0, r1))) = pair {
+                if s0.abs() > 1e-10 && r0.abs() > 1e-10 {
+                    symbol_returns.push(s1 / s0 - 1.0);
+                    reference_returns.push(r1 / r0 - 1.0);
+                }
+            }
+        }
+        ReferenceContext::correlation(&symbol_returns, &reference_returns)
+    }
 
-        Ok(snapshot)
+    /// Close of the reference 3m candle at exactly `timestamp`
+    fn close_3m(&self, timestamp: i64) -> Option<f64> {
+        self.candles_3m.get(&timestamp).map(|c| c.close)
     }
 }
 
-impl Default for HistoricalSnapshotExtractor {
+impl Default for HistoricalSnapshotExtractor<MockDataSource> {
     fn default() -> Self {
         Self::new()
     }
@@ -362,6 +990,7 @@ impl Default for HistoricalSnapshotExtractor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rag::market_data_source::InMemoryDataSource;
 
     #[test]
     fn test_extract_mock_snapshots() {
@@ -380,7 +1009,455 @@ mod tests {
     #[test]
     fn test_data_source_selection() {
         let mock_extractor = HistoricalSnapshotExtractor::new();
-        assert_eq!(mock_extractor.data_source, DataSource::Mock);
+        assert_eq!(mock_extractor.source().name(), "mock");
+
+        let memory_extractor = HistoricalSnapshotExtractor::with_source(InMemoryDataSource::new());
+        assert_eq!(memory_extractor.source().name(), "memory");
+    }
+
+    const BASE_TS: i64 = 1_700_000_100_000;
+
+    /// Candle with the same open, high, low and close
+    fn flat_candle(close: f64) -> Candle {
+        Candle { open: close, high: close, low: close, close, volume: 1.0, trades: 1 }
+    }
+
+    /// 3m indicators with EMAs at 100, RSI 50 and ATR 1
+    fn flat_indicators_3m() -> Indicators3m {
+        Indicators3m { ema_20: 100.0, ema_50: 100.0, macd: 0.0, rsi_7: 50.0, rsi_14: 50.0, atr_14: 1.0, avg_volume_20: None }
+    }
+
+    /// 4h indicators with EMAs at 100, RSI 50 and ATR(14) 2
+    fn flat_indicators_4h() -> Indicators4h {
+        Indicators4h { ema_20: 100.0, ema_50: 100.0, macd: 0.0, rsi_14: 50.0, atr_3: 1.0, atr_14: 2.0, avg_volume_20: None }
+    }
+
+    /// Add a symbol's 3m closes, keyed by 3m candles from `BASE_TS`, with flat indicators at `BASE_TS`
+    fn insert_symbol(source: &mut InMemoryDataSource, symbol: &str, closes_3m: impl IntoIterator<Item = (i64, f64)>) {
+        for (offset, close) in closes_3m {
+            source.insert_candle_3m(symbol, BASE_TS + offset * INTERVAL_3M_MS, flat_candle(close));
+        }
+        source.insert_indicators_3m(symbol, BASE_TS, flat_indicators_3m());
+        source.insert_indicators_4h(symbol, BASE_TS, flat_indicators_4h());
+    }
+
+    /// In-memory source holding one symbol (see `insert_symbol`)
+    fn source_with(symbol: &str, closes_3m: impl IntoIterator<Item = (i64, f64)>) -> InMemoryDataSource {
+        let mut source = InMemoryDataSource::new();
+        insert_symbol(&mut source, symbol, closes_3m);
+        source
+    }
+
+    /// Extract the snapshot at exactly `timestamp` (empty when it was skipped)
+    fn extract_at<S: MarketDataSource>(
+        extractor: &HistoricalSnapshotExtractor<S>,
+        symbol: &str,
+        timestamp: i64,
+    ) -> Vec<MarketStateSnapshot> {
+        extractor
+            .extract_snapshots(symbol, timestamp as u64, (timestamp + 1) as u64, 15)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_in_memory_fixture_snapshot() {
+        // 30 minutes of history and 15 minutes of future on the 3m grid
+        let mut source = source_with("BTCUSDT", (-9..=5i64).map(|i| (i, 200.0 + i as f64)));
+        for i in -9..=5i64 {
+            let indicators = Indicators3m { macd: 0.5, rsi_7: 60.0, rsi_14: 55.0, ..flat_indicators_3m() };
+            source.insert_indicators_3m("BTCUSDT", BASE_TS + i * INTERVAL_3M_MS, indicators);
+        }
+        let indicators_4h = Indicators4h { ema_20: 101.0, ema_50: 99.0, atr_3: 2.0, atr_14: 3.0, ..flat_indicators_4h() };
+        source.insert_indicators_4h("BTCUSDT", BASE_TS, indicators_4h);
+
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+        let snapshots = extract_at(&extractor, "BTCUSDT", BASE_TS);
+
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = &snapshots[0];
+        assert_eq!(snapshot.price, 200.0);
+        assert_eq!(snapshot.rsi_7, 60.0);
+        assert_eq!(snapshot.ema_20_4h, 101.0);
+        assert_eq!(snapshot.mid_prices, (191..=200).map(f64::from).collect::<Vec<_>>());
+        // Only the current 4h point exists; the 9 older ones are forward-filled
+        assert_eq!(snapshot.rsi_14_4h_values, vec![50.0; SERIES_LEN]);
+        assert_eq!(snapshot.missing_points_4h, (0..9).collect::<Vec<_>>());
+        assert!(snapshot.missing_points_3m.is_empty());
+        assert!((snapshot.data_quality - 0.55).abs() < 1e-9);
+        assert!((snapshot.outcome_15m.unwrap() - 2.5).abs() < 1e-9);
+        assert_eq!(snapshot.outcome_1h, None);
+        assert!(!snapshot.has_open_interest);
+        assert!(!snapshot.has_funding_rate);
+        // Ten 3m candles reach back neither 1h nor 4h
+        assert!(!snapshot.has_price_change_1h && !snapshot.has_price_change_4h);
+        assert_eq!(snapshot.price_change_1h, 0.0);
+
+        let regime = snapshot.regime.unwrap();
+        assert_eq!(regime.trend, trading_core::TrendRegime::StrongUptrend);
+        assert_eq!(regime.volatility, Some(trading_core::VolatilityRegime::Compressed));
+        assert_eq!(regime.positioning, None);
+    }
+
+    #[test]
+    fn test_higher_timeframe_contexts() {
+        let mut source = source_with("BTCUSDT", [(0, 160.0)]);
+
+        // 60 hourly candles; the last one is still open at BASE_TS
+        let candle = |close: f64| Candle { high: close + 1.0, low: close - 1.0, ..flat_candle(close) };
+        let open_hour = BASE_TS.div_euclid(ONE_HOUR_MS) * ONE_HOUR_MS;
+        let candles_1h: Vec<(i64, Candle)> = (0..60i64)
+            .map(|i| (open_hour - (59 - i) * ONE_HOUR_MS, candle(100.0 + i as f64)))
+            .collect();
+        for (ts, c) in &candles_1h {
+            source.insert_candle("BTCUSDT", Timeframe::H1, *ts, *c);
+        }
+
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+        let snapshots = extract_at(&extractor, "BTCUSDT", BASE_TS);
+
+        let expected = TimeframeContext::compute(&candles_1h[..59]).last().unwrap().1;
+        let context_1h = snapshots[0].context_1h.unwrap();
+        assert_eq!(context_1h, expected);
+        assert_eq!(context_1h.prev_close, 158.0);
+        // No daily candles in the source
+        assert!(snapshots[0].context_1d.is_none());
+    }
+
+    #[test]
+    fn test_reference_context() {
+        // ETH tracks BTC exactly over the last 4h
+        let btc_close = |i: i64| 40_000.0 + i as f64 * 10.0 + (i as f64 * 0.9).sin() * 100.0;
+        let mut source = InMemoryDataSource::new();
+        for (symbol, scale) in [("BTCUSDT", 1.0), ("ETHUSDT", 20.0)] {
+            insert_symbol(&mut source, symbol, (0..=80i64).map(|i| (-i, btc_close(i) / scale)));
+            source.insert_indicators_3m(symbol, BASE_TS, Indicators3m { rsi_14: 62.0, ..flat_indicators_3m() });
+        }
+
+        let extractor = HistoricalSnapshotExtractor::with_source(source).with_reference_symbol("BTCUSDT");
+        let extract = |symbol| extract_at(&extractor, symbol, BASE_TS).remove(0);
+
+        let reference = extract("ETHUSDT").reference.unwrap();
+        assert_eq!(reference.symbol, "BTCUSDT");
+        assert_eq!(reference.rsi_14, 62.0);
+        let expected_1h = (btc_close(0) - btc_close(20)) / btc_close(20) * 100.0;
+        let expected_4h = (btc_close(0) - btc_close(80)) / btc_close(80) * 100.0;
+        assert!((reference.price_change_1h.unwrap() - expected_1h).abs() < 1e-9);
+        assert!((reference.price_change_4h.unwrap() - expected_4h).abs() < 1e-9);
+        assert!((reference.correlation_4h.unwrap() - 1.0).abs() < 1e-9);
+
+        // The reference symbol itself gets no reference block
+        assert!(extract("BTCUSDT").reference.is_none());
+    }
+
+    #[test]
+    fn test_microstructure_features() {
+        let mut source = source_with("BTCUSDT", [(0, 100.0)]);
+
+        // An older book and the latest one inside the 3m candle
+        let book = |bid_size| OrderBookRecord { bid_price: 99.95, bid_size, ask_price: 100.05, ask_size: 10.0 };
+        source.insert_order_book("BTCUSDT", BASE_TS - INTERVAL_3M_MS, book(10.0));
+        source.insert_order_book("BTCUSDT", BASE_TS - 60_000, book(30.0));
+        // 11 candles of flow; the oldest falls outside the 30m window
+        for i in 0..=10i64 {
+            source.insert_trade_flow("BTCUSDT", BASE_TS - i * INTERVAL_3M_MS, 60.0, 40.0);
+        }
+        source.insert_liquidations("BTCUSDT", BASE_TS - 2 * INTERVAL_3M_MS, 500.0, 0.0);
+        source.insert_liquidations("BTCUSDT", BASE_TS, 250.0, 10.0);
+
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+        let snapshot = &extract_at(&extractor, "BTCUSDT", BASE_TS)[0];
+
+        assert!((snapshot.spread_bps.unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(snapshot.book_imbalance, Some(0.5));
+        assert_eq!(snapshot.cvd_30m, Some(200.0));
+        assert_eq!(snapshot.taker_buy_sell_ratio_30m, Some(1.5));
+        assert_eq!(snapshot.long_liquidations_30m, Some(750.0));
+        assert_eq!(snapshot.short_liquidations_30m, Some(10.0));
+
+        // Mock data has every feed; a bare source has none
+        let mock = HistoricalSnapshotExtractor::new();
+        let snapshot = &extract_at(&mock, "BTCUSDT", BASE_TS)[0];
+        assert!(snapshot.spread_bps.is_some() && snapshot.liquidations_30m().is_some());
+
+        let bare = HistoricalSnapshotExtractor::with_source(source_with("BTCUSDT", [(0, 100.0)]));
+        let snapshot = &extract_at(&bare, "BTCUSDT", BASE_TS)[0];
+        assert_eq!(snapshot.spread_bps, None);
+        assert_eq!(snapshot.cvd_30m, None);
+        assert_eq!(snapshot.liquidations_30m(), None);
+    }
+
+    #[test]
+    fn test_outcome_spec_applied() {
+        use trading_core::ExitRule;
+
+        // Price dips 0.1 per candle for 30 minutes, then climbs back for 30 minutes
+        let source = source_with("BTCUSDT", (0..=20i64).map(|i| (i, 100.0 - 0.1 * (10 - (i - 10).abs()) as f64)));
+
+        // 1 ATR is 2% of the price, so the 1% dip only hits a 0.5 ATR stop
+        let spec = OutcomeSpec::default()
+            .with_horizons(vec!["30m".parse().unwrap(), "1h".parse().unwrap(), "8h".parse().unwrap()])
+            .with_stop(ExitRule::AtrMultiple(0.5));
+        let extractor = HistoricalSnapshotExtractor::with_source(source).with_outcome_spec(spec);
+        let window = DataWindow::load(extractor.source(), "BTCUSDT", BASE_TS, BASE_TS, ONE_HOUR_MS).unwrap();
+        let snapshot = extractor.build_snapshot(&window, None, BASE_TS, BASE_TS + 2 * ONE_HOUR_MS).unwrap();
+
+        assert_eq!(snapshot.outcomes.keys().collect::<Vec<_>>(), vec!["1h", "30m", "8h"]);
+        let half_hour = snapshot.outcomes["30m"];
+        assert!((half_hour.return_pct.unwrap() + 1.0).abs() < 1e-9);
+        assert_eq!(half_hour.hit_stop_loss, Some(true));
+        assert_eq!(half_hour.hit_take_profit, Some(false));
+        assert!(snapshot.outcomes["1h"].return_pct.unwrap().abs() < 1e-9);
+        assert_eq!(snapshot.outcomes["8h"].return_pct, None);
+        assert!(snapshot.outcomes_pending);
+
+        // Legacy fields follow the matching labels
+        assert_eq!(snapshot.outcome_1h, snapshot.outcomes["1h"].return_pct);
+        assert_eq!(snapshot.hit_stop_loss, Some(true));
+        assert_eq!(snapshot.outcome_15m, None);
+
+        // The long is stopped at the bottom of the dip; the short is still open
+        let long = snapshot.long_trade.unwrap();
+        assert_eq!(long.exit_reason, trading_core::ExitReason::StopLoss);
+        assert_eq!(long.exit_after_ms, 10 * INTERVAL_3M_MS);
+        assert_eq!(snapshot.short_trade, None);
+
+        // Snapshots built live have every outcome pending until refreshed, together
+        let now_ms = BASE_TS + 2 * ONE_HOUR_MS;
+        let first = extractor.extract_snapshot_at("BTCUSDT", BASE_TS as u64, BASE_TS).unwrap();
+        let mut later = first.clone();
+        later.timestamp += 10 * INTERVAL_3M_MS as u64;
+        let mut live = vec![first, later.clone()];
+        assert!(live[0].outcomes.values().all(|o| o.return_pct.is_none()));
+        extractor.refresh_outcomes("BTCUSDT", &mut live, now_ms).unwrap();
+        assert_eq!(live[0].outcomes, snapshot.outcomes);
+        assert_eq!(live[0].long_trade, snapshot.long_trade);
+        assert!(live[0].outcomes_pending);
+
+        // Refreshing together matches refreshing one at a time
+        extractor.refresh_outcomes("BTCUSDT", std::slice::from_mut(&mut later), now_ms).unwrap();
+        assert_eq!(live[1].outcomes, later.outcomes);
+        assert_ne!(live[1].outcomes, live[0].outcomes);
+
+        // Every snapshot must belong to the symbol whose candles are read
+        assert!(extractor.refresh_outcomes("ETHUSDT", &mut live, now_ms).is_err());
+    }
+
+    #[test]
+    fn test_fill_gaps_policies() {
+        let values = [None, Some(1.0), None, None, Some(4.0), None];
+        assert_eq!(
+            fill_gaps(values.into_iter(), GapFillPolicy::ForwardFill),
+            vec![1.0, 1.0, 1.0, 1.0, 4.0, 4.0]
+        );
+        assert_eq!(
+            fill_gaps(values.into_iter(), GapFillPolicy::Interpolate),
+            vec![1.0, 1.0, 2.0, 3.0, 4.0, 4.0]
+        );
+        assert_eq!("ffill".parse::<GapFillPolicy>().unwrap(), GapFillPolicy::ForwardFill);
+        assert!("drop".parse::<GapFillPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_gap_policy_applied_to_3m_series() {
+        let mut source = InMemoryDataSource::new();
+        for i in -9..=0i64 {
+            let ts = BASE_TS + i * INTERVAL_3M_MS;
+            let close = 200.0 + i as f64;
+            // Point 6 of 10 has no candle, point 3 has no indicators
+            if i != -3 {
+                source.insert_candle_3m("BTCUSDT", ts, flat_candle(close));
+            }
+            if i != -6 {
+                let rsi = 50.0 + i as f64;
+                let indicators = Indicators3m { ema_20: close, ema_50: close, rsi_7: rsi, rsi_14: rsi, ..flat_indicators_3m() };
+                source.insert_indicators_3m("BTCUSDT", ts, indicators);
+            }
+        }
+        for i in 0..SERIES_LEN as i64 {
+            source.insert_indicators_4h("BTCUSDT", BASE_TS - i * FOUR_HOURS_MS, flat_indicators_4h());
+        }
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+
+        let snapshot = &extract_at(&extractor, "BTCUSDT", BASE_TS)[0];
+        assert_eq!(snapshot.missing_points_3m, vec![3, 6]);
+        assert_eq!(snapshot.mid_prices.len(), SERIES_LEN);
+        assert_eq!(snapshot.mid_prices[3], 193.0);
+        assert_eq!(snapshot.mid_prices[6], 196.0);
+        assert!((snapshot.data_quality - 0.9).abs() < 1e-9);
+
+        let extractor = extractor.with_gap_policy(GapFillPolicy::Interpolate);
+        let snapshot = &extract_at(&extractor, "BTCUSDT", BASE_TS)[0];
+        assert_eq!(snapshot.mid_prices[3], 194.0);
+        assert_eq!(snapshot.rsi_7_values[6], 47.0);
+
+        let extractor = extractor.with_gap_policy(GapFillPolicy::Reject);
+        assert!(extract_at(&extractor, "BTCUSDT", BASE_TS).is_empty());
+    }
+
+    #[test]
+    fn test_missing_indicators_recomputed_from_candles() {
+        use trading_core::indicators::Indicators4hCalculator;
+
+        let base_ts = 1_700_006_400_000i64; // 4h boundary
+        let candle = |close: f64, volume: f64| Candle { high: close + 1.0, low: close - 1.0, volume, ..flat_candle(close) };
+        let mut source = InMemoryDataSource::new();
+
+        // 70 4h candles before base_ts with volume 1..=70, and 3m candles (no indicators) after them
+        let candles_4h: Vec<(i64, Candle)> = (0..70i64)
+            .map(|i| (base_ts - (70 - i) * FOUR_HOURS_MS, candle(100.0 + (i % 5) as f64, (i + 1) as f64)))
+            .collect();
+        for (ts, c) in &candles_4h {
+            source.insert_candle_4h("BTCUSDT", *ts, *c);
+        }
+        let candles_3m: Vec<(i64, Candle)> = (-300..20i64)
+            .map(|i| (base_ts + i * INTERVAL_3M_MS, candle(100.0 + (i as f64 * 0.2).sin(), 1.0)))
+            .collect();
+        for (ts, c) in &candles_3m {
+            source.insert_candle_3m("BTCUSDT", *ts, *c);
+        }
+
+        // Same data with stored 4h indicators that carry no volume average (as in LMDB)
+        let mut with_stored_4h = source.clone();
+        for ts in grid_3m(base_ts - 20 * INTERVAL_3M_MS, base_ts) {
+            with_stored_4h.insert_indicators_4h("BTCUSDT", ts, flat_indicators_4h());
+        }
+
+        let extractor = HistoricalSnapshotExtractor::with_source(source);
+        let snapshots = extract_at(&extractor, "BTCUSDT", base_ts);
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = &snapshots[0];
+
+        let expected_3m = Indicators3mCalculator::compute(&candles_3m)
+            .into_iter()
+            .find(|(ts, _)| *ts == base_ts)
+            .unwrap()
+            .1;
+        // Recomputation starts INDICATOR_WARMUP candles back, so values match after convergence
+        assert!((snapshot.rsi_7 - expected_3m.rsi_7).abs() < 1e-6);
+        assert!((snapshot.ema_20 - expected_3m.ema_20).abs() < 1e-6);
+        assert_eq!(snapshot.rsi_14_values.len(), SERIES_LEN);
+
+        // The last 4h candle closes at base_ts, before the snapshot's 3m candle ends
+        let expected_4h = Indicators4hCalculator::compute(&candles_4h).last().unwrap().1;
+        assert_eq!(snapshot.ema_20_4h, expected_4h.ema_20);
+        assert_eq!(snapshot.current_volume_4h, 70.0);
+        assert_eq!(snapshot.avg_volume_4h, (51..=70).sum::<i64>() as f64 / 20.0);
+
+        // Stored values are kept and only the volume average is computed
+        let snapshot = extract_at(&HistoricalSnapshotExtractor::with_source(with_stored_4h), "BTCUSDT", base_ts).remove(0);
+        assert_eq!(snapshot.ema_20_4h, flat_indicators_4h().ema_20);
+        assert_eq!(snapshot.avg_volume_4h, (51..=70).sum::<i64>() as f64 / 20.0);
+    }
+
+    /// Write a small LMDB fixture with flat indicators and a rising 3m close
+    ///
+    /// Derivatives databases are only created when `with_derivatives` is set.
+    fn write_lmdb_fixture(path: &std::path::Path, symbol: &str, base_ts: i64, with_derivatives: bool) {
+        use lmdb::{DatabaseFlags, Environment, Transaction, WriteFlags};
+        use serde_json::{json, Value};
+
+        std::fs::create_dir_all(path).unwrap();
+        let env = Environment::new().set_max_dbs(10).open(path).unwrap();
+        let mut names = vec!["candles_3m", "candles_4h", "indicators_3m", "indicators_4h"];
+        if with_derivatives {
+            names.extend(["open_interest", "funding_rate"]);
+        }
+        let dbs: Vec<_> = names
+            .iter()
+            .map(|name| env.create_db(Some(name), DatabaseFlags::empty()).unwrap())
+            .collect();
+
+        let mut txn = env.begin_rw_txn().unwrap();
+        let mut put = |db_index: usize, ts: i64, value: Value| {
+            let key = format!("{}:{}", symbol, ts);
+            txn.put(dbs[db_index], &key, &value.to_string(), WriteFlags::empty()).unwrap();
+        };
+
+        // 4 hours of history plus 2 hours of future 3m candles (+0.1 per candle)
+        for i in -80..=40i64 {
+            let ts = base_ts + i * INTERVAL_3M_MS;
+            let close = 100.0 + i as f64 * 0.1;
+            put(0, ts, json!({"open": close, "high": close, "low": close, "close": close, "volume": 1.0}));
+            put(2, ts, json!({"ema_20": 100.0, "ema_50": 100.0, "macd": 0.0, "rsi_7": 50.0, "rsi_14": 50.0, "atr_14": 1.0}));
+        }
+        for i in 0..10i64 {
+            let ts = base_ts - i * 14_400_000;
+            put(3, ts, json!({"ema_20": 100.0, "ema_50": 100.0, "macd": 0.0, "rsi_14": 50.0, "atr_3": 1.0, "atr_14": 1.0}));
+        }
+        if with_derivatives {
+            // 24h of open interest rising by 1 per 3m point, ending at 1000
+            for i in -479..=0i64 {
+                put(4, base_ts + i * INTERVAL_3M_MS, json!({"open_interest": 1000.0 + i as f64}));
+            }
+            put(5, base_ts, json!({"funding_rate": 0.0001}));
+        }
+
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_lmdb_outcomes_from_future_candles() {
+        let path = std::env::temp_dir().join(format!("rag_extractor_outcomes_{}", std::process::id()));
+        let base_ts = 1_700_000_100_000i64;
+        write_lmdb_fixture(&path, "BTCUSDT", base_ts, false);
+
+        let extractor = HistoricalSnapshotExtractor::with_lmdb(path.to_str().unwrap()).unwrap();
+        let window = DataWindow::load(extractor.source(), "BTCUSDT", base_ts, base_ts, ONE_DAY_MS).unwrap();
+
+        // "Now" two hours after the snapshot: 15m/1h known, 4h/24h pending
+        let now_ms = base_ts + 2 * ONE_HOUR_MS;
+        let snapshot = extractor
+            .build_snapshot(&window, None, base_ts, now_ms)
+            .unwrap();
+
+        assert!((snapshot.outcome_15m.unwrap() - 0.5).abs() < 1e-9);
+        assert!((snapshot.outcome_1h.unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(snapshot.outcome_4h, None);
+        assert_eq!(snapshot.outcome_24h, None);
+        assert!((snapshot.max_runup_1h.unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(snapshot.max_drawdown_1h, Some(0.0));
+        assert_eq!(snapshot.hit_stop_loss, Some(false));
+        assert!(snapshot.outcomes_pending);
+
+        // "Now" before the first horizon: nothing is known yet
+        let snapshot = extractor
+            .build_snapshot(&window, None, base_ts, base_ts)
+            .unwrap();
+        assert_eq!(snapshot.outcome_15m, None);
+        assert_eq!(snapshot.max_runup_1h, None);
+        assert!(snapshot.outcomes_pending);
+
+        // Derivatives databases are absent from this fixture
+        assert!(!snapshot.has_open_interest);
+        assert!(!snapshot.has_funding_rate);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_lmdb_derivatives_and_price_changes() {
+        let path = std::env::temp_dir().join(format!("rag_extractor_derivatives_{}", std::process::id()));
+        let base_ts = 1_700_000_100_000i64;
+        write_lmdb_fixture(&path, "BTCUSDT", base_ts, true);
+
+        let extractor = HistoricalSnapshotExtractor::with_lmdb(path.to_str().unwrap()).unwrap();
+        let window = DataWindow::load(extractor.source(), "BTCUSDT", base_ts, base_ts, ONE_DAY_MS).unwrap();
+        let snapshot = extractor
+            .build_snapshot(&window, None, base_ts, base_ts)
+            .unwrap();
+
+        assert!(snapshot.has_open_interest);
+        assert_eq!(snapshot.open_interest_latest, 1000.0);
+        assert!((snapshot.open_interest_avg_24h - 760.5).abs() < 1e-9);
+        assert!(snapshot.has_funding_rate);
+        assert_eq!(snapshot.funding_rate, 0.0001);
+
+        // Close is 100.0 now, 98.0 one hour ago and 92.0 four hours ago
+        assert!(snapshot.has_price_change_1h && snapshot.has_price_change_4h);
+        assert!((snapshot.price_change_1h - 2.0 / 98.0 * 100.0).abs() < 1e-9);
+        assert!((snapshot.price_change_4h - 8.0 / 92.0 * 100.0).abs() < 1e-9);
+
+        let _ = std::fs::remove_dir_all(&path);
     }
 
     // Integration test - requires actual LMDB database
@@ -390,7 +1467,7 @@ mod tests {
         let extractor = HistoricalSnapshotExtractor::with_lmdb("/shared/data/trading/lmdb")
             .expect("Failed to create LMDB extractor");
 
-        assert_eq!(extractor.data_source, DataSource::Lmdb);
+        assert_eq!(extractor.source().name(), "lmdb");
 
         // Try to extract some snapshots
         let start = 1730811225000; // Example timestamp
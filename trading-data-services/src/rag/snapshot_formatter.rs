@@ -13,6 +13,7 @@ impl SnapshotFormatter for MarketStateSnapshot {
     /// Detailed natural language format (more semantic info for embeddings)
     fn to_embedding_text(&self) -> String {
         let mut parts = Vec::new();
+        let features = self.features();
 
         // Trend indicators
         parts.push(format!(
@@ -24,7 +25,7 @@ impl SnapshotFormatter for MarketStateSnapshot {
 
         // MACD
         parts.push(format!("MACD is {:.2}", self.macd));
-        let macd_slope = self.macd_slope();
+        let macd_slope = features.macd_slope;
         let macd_mom = if macd_slope > 0.0 {
             "rising"
         } else if macd_slope < 0.0 {
@@ -38,45 +39,127 @@ impl SnapshotFormatter for MarketStateSnapshot {
         ));
 
         // EMA trend
-        let ema_ratio = self.ema_ratio_20_50();
-        let trend = if ema_ratio > 1.005 {
-            "strong uptrend"
-        } else if ema_ratio < 0.995 {
-            "strong downtrend"
-        } else {
-            "sideways"
-        };
+        let regime = self.regime_labels();
         parts.push(format!(
             "EMA(20)/EMA(50) ratio is {:.4}, indicating {}",
-            ema_ratio, trend
+            features.ema_ratio,
+            regime.trend.description()
         ));
+        if let Some(distance) = features.ema_20_4h_distance_atr {
+            let side = if distance >= 0.0 { "above" } else { "below" };
+            parts.push(format!(
+                "Price is {:.1} ATR {} the 4h EMA(20)",
+                distance.abs(),
+                side
+            ));
+        }
 
-        // Open Interest
-        let oi_delta = self.oi_delta_pct();
-        let oi_sentiment = if oi_delta > 5.0 {
-            "rising significantly"
-        } else if oi_delta < -5.0 {
-            "dropping significantly"
-        } else {
-            "stable"
-        };
-        parts.push(format!(
-            "Open interest is {} ({:+.1}% vs 24h average)",
-            oi_sentiment, oi_delta
-        ));
+        // Higher timeframe structure (only when the blocks are present)
+        for (timeframe, context) in self.timeframe_contexts() {
+            parts.push(format!(
+                "{} trend is {} with price {} ({:+.2}% from EMA20), RSI(14) {:.1}",
+                timeframe,
+                context.trend(),
+                context.ema_position(self.price),
+                context.pct_from_ema_20(self.price),
+                context.rsi_14
+            ));
+        }
+        if let Some(daily) = &self.context_1d {
+            let position = if self.price > daily.prev_high {
+                "above the prior-day high"
+            } else if self.price < daily.prev_low {
+                "below the prior-day low"
+            } else {
+                "inside the prior-day range"
+            };
+            parts.push(format!(
+                "Price is {} ({:.2}-{:.2})",
+                position, daily.prev_low, daily.prev_high
+            ));
+        }
+
+        // Open Interest (omitted when unavailable rather than reported as stable)
+        if let Some(oi_delta) = features.oi_delta_pct {
+            let oi_sentiment = if oi_delta > 5.0 {
+                "rising significantly"
+            } else if oi_delta < -5.0 {
+                "dropping significantly"
+            } else {
+                "stable"
+            };
+            parts.push(format!(
+                "Open interest is {} ({:+.1}% vs 24h average)",
+                oi_sentiment, oi_delta
+            ));
+        }
 
         // Funding
-        let funding_sentiment = if self.funding_rate > 0.0005 {
-            "highly positive (longs paying shorts)"
-        } else if self.funding_rate < -0.0005 {
-            "highly negative (shorts paying longs)"
-        } else {
-            "neutral"
-        };
-        parts.push(format!("Funding rate is {}", funding_sentiment));
+        if self.has_funding_rate {
+            let funding_sentiment = if self.funding_rate > 0.0005 {
+                "highly positive (longs paying shorts)"
+            } else if self.funding_rate < -0.0005 {
+                "highly negative (shorts paying longs)"
+            } else {
+                "neutral"
+            };
+            parts.push(format!("Funding rate is {}", funding_sentiment));
+        }
+
+        // Order book
+        if let Some(spread) = self.spread_bps {
+            let liquidity = if spread > 5.0 {
+                "wide"
+            } else if spread > 1.0 {
+                "normal"
+            } else {
+                "tight"
+            };
+            parts.push(format!("Bid/ask spread is {} ({:.2} bps)", liquidity, spread));
+        }
+        if let Some(imbalance) = self.book_imbalance {
+            let side = if imbalance > 0.2 {
+                "bid-heavy"
+            } else if imbalance < -0.2 {
+                "ask-heavy"
+            } else {
+                "balanced"
+            };
+            parts.push(format!("Order book is {} ({:+.2} imbalance)", side, imbalance));
+        }
+
+        // Trade flow
+        if let Some(cvd) = self.cvd_30m {
+            let flow = match self.taker_buy_sell_ratio_30m {
+                Some(ratio) if ratio < 0.8 => "aggressive selling",
+                Some(ratio) if ratio <= 1.2 => "balanced",
+                Some(_) => "aggressive buying",
+                // No taker sells in the window
+                None if cvd > 0.0 => "aggressive buying",
+                None => "no taker trades",
+            };
+            parts.push(format!(
+                "Taker flow over 30m shows {} (CVD {:+.1})",
+                flow, cvd
+            ));
+        }
+
+        // Liquidations
+        if let (Some(long), Some(short)) = (self.long_liquidations_30m, self.short_liquidations_30m) {
+            let cascade = if long + short <= 0.0 {
+                "none"
+            } else if long > short * 2.0 {
+                "mostly longs (long squeeze)"
+            } else if short > long * 2.0 {
+                "mostly shorts (short squeeze)"
+            } else {
+                "on both sides"
+            };
+            parts.push(format!("Liquidations over 30m were {}", cascade));
+        }
 
         // Momentum
-        let rsi_slope = self.rsi_7_slope();
+        let rsi_slope = features.rsi_7_slope;
         if rsi_slope.abs() > 2.0 {
             let direction = if rsi_slope > 0.0 {
                 "accelerating up"
@@ -85,25 +168,83 @@ impl SnapshotFormatter for MarketStateSnapshot {
             };
             parts.push(format!("RSI momentum is {}", direction));
         }
+        if let Some(zscore) = features.rsi_7_zscore.filter(|z| z.abs() > 2.0) {
+            parts.push(format!("RSI(7) is stretched ({:+.1} standard deviations from its recent mean)", zscore));
+        }
+        if features.price_curvature_pct.abs() > 0.005 {
+            let bend = if features.price_curvature_pct > 0.0 {
+                "curving upward"
+            } else {
+                "curving downward"
+            };
+            parts.push(format!("Price path is {}", bend));
+        }
+        for (indicator, divergence) in [("RSI", features.rsi_divergence), ("MACD", features.macd_divergence)] {
+            if let Some(divergence) = divergence {
+                parts.push(format!("{} shows a {} divergence from price", indicator, divergence));
+            }
+        }
 
-        // Volatility context
-        if self.atr_14_4h > 0.0 && self.atr_3_4h > 0.0 {
-            let vol_state = if self.atr_3_4h > self.atr_14_4h * 1.5 {
-                "elevated"
+        // Volume
+        if let Some(volume_ratio) = features.volume_ratio_4h {
+            let activity = if volume_ratio > 1.5 {
+                "heavy"
+            } else if volume_ratio < 0.5 {
+                "light"
             } else {
                 "normal"
             };
-            parts.push(format!("Volatility is {}", vol_state));
+            parts.push(format!("4h volume is {} ({:.1}x average)", activity, volume_ratio));
+        }
+
+        // Volatility context
+        if let Some(volatility) = regime.volatility {
+            parts.push(format!("Volatility is {}", volatility));
+        }
+
+        // Reference asset (e.g. BTC for altcoins)
+        if let Some(reference) = &self.reference {
+            let name = reference.symbol.strip_suffix("USDT").unwrap_or(&reference.symbol);
+            // Direction and changes are left out when the reference history is missing
+            let direction = match reference.price_change_4h {
+                Some(change) if change > 1.0 => " is rallying",
+                Some(change) if change < -1.0 => " is selling off",
+                Some(_) => " is ranging",
+                None => "",
+            };
+            let changes: Vec<String> = [(reference.price_change_1h, "1h"), (reference.price_change_4h, "4h")]
+                .into_iter()
+                .filter_map(|(change, period)| change.map(|c| format!("{:+.2}% in {}", c, period)))
+                .collect();
+            let changes = if changes.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", changes.join(", "))
+            };
+            parts.push(format!(
+                "{}{}{} with RSI(14) at {:.1}",
+                name, direction, changes, reference.rsi_14
+            ));
+            if let Some(correlation) = reference.correlation_4h {
+                let strength = if correlation > 0.7 {
+                    "strongly correlated"
+                } else if correlation > 0.3 {
+                    "loosely correlated"
+                } else {
+                    "decoupled"
+                };
+                parts.push(format!("Price is {} with {} ({:.2})", strength, name, correlation));
+            }
         }
 
-        // Price momentum
-        if self.price_change_1h.abs() > 0.5 {
+        // Price momentum (skipped when the past candle was missing)
+        if self.has_price_change_1h && self.price_change_1h.abs() > 0.5 {
             parts.push(format!(
                 "Price changed {:+.2}% in the last hour",
                 self.price_change_1h
             ));
         }
-        if self.price_change_4h.abs() > 1.0 {
+        if self.has_price_change_4h && self.price_change_4h.abs() > 1.0 {
             parts.push(format!(
                 "Price changed {:+.2}% in the last 4 hours",
                 self.price_change_4h
@@ -116,22 +257,74 @@ impl SnapshotFormatter for MarketStateSnapshot {
 
     /// Simpler numerical format (faster to process)
     fn to_embedding_text_simple(&self) -> String {
-        format!(
+        let features = self.features();
+        let mut text = format!(
             "Symbol: {}, Price: {:.1}, RSI(7): {:.1}, RSI(14): {:.1}, MACD: {:.2}, \
              EMA Ratio 20/50: {:.4}, OI Delta: {:+.1}%, Funding: {:.6}, \
-             ATR(14): {:.2}, Price Change 1h: {:+.2}%, Price Change 4h: {:+.2}%",
+             ATR(14): {:.2}",
             self.symbol,
             self.price,
             self.rsi_7,
             self.rsi_14,
             self.macd,
-            self.ema_ratio_20_50(),
-            self.oi_delta_pct(),
+            features.ema_ratio,
+            features.oi_delta_pct.unwrap_or(0.0),
             self.funding_rate,
-            self.atr_14_4h,
-            self.price_change_1h,
-            self.price_change_4h
-        )
+            self.atr_14_4h
+        );
+        if self.has_price_change_1h {
+            text.push_str(&format!(", Price Change 1h: {:+.2}%", self.price_change_1h));
+        }
+        if self.has_price_change_4h {
+            text.push_str(&format!(", Price Change 4h: {:+.2}%", self.price_change_4h));
+        }
+        text.push_str(&format!(
+            ", Price Slope: {:+.3}%, RSI(7) Slope: {:+.2}, MACD Slope: {:+.3}",
+            features.price_slope_pct, features.rsi_7_slope, features.macd_slope
+        ));
+        if let Some(distance) = features.ema_20_4h_distance_atr {
+            text.push_str(&format!(", EMA20 4h Distance: {:+.1} ATR", distance));
+        }
+        if let Some(volume_ratio) = features.volume_ratio_4h {
+            text.push_str(&format!(", Volume Ratio 4h: {:.1}", volume_ratio));
+        }
+        for (timeframe, context) in self.timeframe_contexts() {
+            text.push_str(&format!(
+                ", RSI(14) {}: {:.1}, EMA Ratio 20/50 {}: {:.4}",
+                timeframe,
+                context.rsi_14,
+                timeframe,
+                context.ema_ratio()
+            ));
+        }
+        if let Some(spread) = self.spread_bps {
+            text.push_str(&format!(", Spread: {:.2}bps", spread));
+        }
+        if let Some(imbalance) = self.book_imbalance {
+            text.push_str(&format!(", Book Imbalance: {:+.2}", imbalance));
+        }
+        if let Some(ratio) = self.taker_buy_sell_ratio_30m {
+            text.push_str(&format!(", Taker Buy/Sell 30m: {:.2}", ratio));
+        }
+        if let Some(cvd) = self.cvd_30m {
+            text.push_str(&format!(", CVD 30m: {:+.1}", cvd));
+        }
+        if let Some(liquidations) = self.liquidations_30m() {
+            text.push_str(&format!(", Liquidations 30m: {:.1}", liquidations));
+        }
+        if let Some(reference) = &self.reference {
+            if let Some(change) = reference.price_change_1h {
+                text.push_str(&format!(", {} Change 1h: {:+.2}%", reference.symbol, change));
+            }
+            if let Some(change) = reference.price_change_4h {
+                text.push_str(&format!(", {} Change 4h: {:+.2}%", reference.symbol, change));
+            }
+            text.push_str(&format!(", {} RSI(14): {:.1}", reference.symbol, reference.rsi_14));
+            if let Some(correlation) = reference.correlation_4h {
+                text.push_str(&format!(", {} Correlation 4h: {:.2}", reference.symbol, correlation));
+            }
+        }
+        text
     }
 }
 
@@ -151,6 +344,7 @@ fn interpret_rsi(rsi: f64) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use trading_core::TimeframeContext;
 
     #[test]
     fn test_embedding_text_generation() {
@@ -170,6 +364,126 @@ mod tests {
         assert!(text.contains("uptrend"));
     }
 
+    #[test]
+    fn test_embedding_text_omits_unavailable_derivatives() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.has_open_interest = false;
+        snapshot.has_funding_rate = false;
+
+        let text = snapshot.to_embedding_text();
+        assert!(!text.contains("Open interest"));
+        assert!(!text.contains("Funding rate"));
+    }
+
+    #[test]
+    fn test_embedding_text_omits_unavailable_price_changes() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.price_change_1h = 2.0;
+        snapshot.price_change_4h = 3.0;
+        assert!(snapshot.to_embedding_text().contains("in the last hour"));
+
+        snapshot.has_price_change_1h = false;
+        snapshot.has_price_change_4h = false;
+        assert!(!snapshot.to_embedding_text().contains("Price changed"));
+        assert!(!snapshot.to_embedding_text_simple().contains("Price Change"));
+    }
+
+    #[test]
+    fn test_embedding_text_includes_timeframe_contexts() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        assert!(!snapshot.to_embedding_text().contains("1d trend"));
+
+        snapshot.context_1d = Some(TimeframeContext {
+            ema_20: 48000.0,
+            ema_50: 46000.0,
+            macd: 150.0,
+            rsi_14: 64.0,
+            atr_14: 1200.0,
+            prev_high: 49500.0,
+            prev_low: 47800.0,
+            prev_close: 49200.0,
+        });
+
+        let text = snapshot.to_embedding_text();
+        assert!(text.contains("1d trend is uptrend with price above EMA20 and EMA50"), "{}", text);
+        assert!(text.contains("above the prior-day high"), "{}", text);
+        assert!(!text.contains("1h trend"));
+        assert!(snapshot.to_embedding_text_simple().contains("RSI(14) 1d: 64.0"));
+    }
+
+    #[test]
+    fn test_embedding_text_includes_microstructure() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        let text = snapshot.to_embedding_text();
+        assert!(!text.contains("spread") && !text.contains("Taker flow") && !text.contains("Liquidations"));
+
+        snapshot.spread_bps = Some(0.4);
+        snapshot.book_imbalance = Some(-0.35);
+        snapshot.taker_buy_sell_ratio_30m = Some(1.6);
+        snapshot.cvd_30m = Some(240.0);
+        snapshot.long_liquidations_30m = Some(12_000.0);
+        snapshot.short_liquidations_30m = Some(1_000.0);
+
+        let text = snapshot.to_embedding_text();
+        assert!(text.contains("Bid/ask spread is tight"), "{}", text);
+        assert!(text.contains("Order book is ask-heavy"), "{}", text);
+        assert!(text.contains("shows aggressive buying (CVD +240.0)"), "{}", text);
+        assert!(text.contains("mostly longs (long squeeze)"), "{}", text);
+
+        let simple = snapshot.to_embedding_text_simple();
+        assert!(simple.contains("Book Imbalance: -0.35"), "{}", simple);
+        assert!(simple.contains("Liquidations 30m: 13000.0"), "{}", simple);
+    }
+
+    #[test]
+    fn test_embedding_text_includes_reference() {
+        let mut snapshot = MarketStateSnapshot::new("SOLUSDT".to_string(), 1000000, 150.0);
+        assert!(!snapshot.to_embedding_text().contains("BTC"));
+
+        snapshot.reference = Some(trading_core::ReferenceContext {
+            symbol: "BTCUSDT".to_string(),
+            price_change_1h: Some(-0.8),
+            price_change_4h: Some(-2.4),
+            rsi_14: 28.5,
+            correlation_4h: Some(0.82),
+        });
+
+        let text = snapshot.to_embedding_text();
+        assert!(text.contains("BTC is selling off (-0.80% in 1h, -2.40% in 4h) with RSI(14) at 28.5"), "{}", text);
+        assert!(text.contains("strongly correlated with BTC (0.82)"), "{}", text);
+        assert!(snapshot.to_embedding_text_simple().contains("BTCUSDT Change 4h: -2.40%"));
+
+        // Missing reference history leaves the changes out instead of reporting 0%
+        if let Some(reference) = snapshot.reference.as_mut() {
+            reference.price_change_4h = None;
+        }
+        let text = snapshot.to_embedding_text();
+        assert!(text.contains("BTC (-0.80% in 1h) with RSI(14) at 28.5"), "{}", text);
+        assert!(!snapshot.to_embedding_text_simple().contains("BTCUSDT Change 4h"));
+    }
+
+    #[test]
+    fn test_embedding_text_includes_derived_features() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50600.0);
+        snapshot.ema_20_4h = 50000.0;
+        snapshot.ema_50_4h = 49000.0;
+        snapshot.atr_14_4h = 400.0;
+        snapshot.current_volume_4h = 2000.0;
+        snapshot.avg_volume_4h = 1000.0;
+        snapshot.mid_prices = (0..10).map(|i| 50000.0 + i as f64 * 60.0).collect();
+        snapshot.rsi_7_values = (0..10).map(|i| 75.0 - i as f64 * 2.0).collect();
+
+        let text = snapshot.to_embedding_text();
+        assert!(text.contains("Price is 1.5 ATR above the 4h EMA(20)"), "{}", text);
+        assert!(text.contains("4h volume is heavy (2.0x average)"), "{}", text);
+        assert!(text.contains("RSI shows a bearish divergence from price"), "{}", text);
+        assert!(!text.contains("MACD shows"), "{}", text);
+
+        let simple = snapshot.to_embedding_text_simple();
+        assert!(simple.contains("EMA20 4h Distance: +1.5 ATR"), "{}", simple);
+        assert!(simple.contains("Volume Ratio 4h: 2.0"), "{}", simple);
+    }
+
     #[test]
     fn test_simple_embedding_text() {
         let snapshot = MarketStateSnapshot::new("ETHUSDT".to_string(), 1000000, 3000.0);
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use trading_core::{MarketStateSnapshot, TimestampMS};
+
+/// A sanity rule checked by [`SnapshotValidator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationRule {
+    /// RSI values (current and series, 3m and 4h, and the 1h/1d blocks) lie in 0..=100
+    RsiRange,
+    /// Price, close series and EMAs (including the 1h/1d blocks) are positive
+    PositivePrices,
+    /// 4h ATR values are not negative
+    NonNegativeAtr,
+    /// MACD values (current and series) are finite
+    FiniteMacd,
+    /// Each snapshot is later than the previous accepted one
+    MonotonicTimestamps,
+}
+
+impl ValidationRule {
+    /// Every rule, in the order they are checked
+    pub const ALL: [ValidationRule; 5] = [
+        ValidationRule::RsiRange,
+        ValidationRule::PositivePrices,
+        ValidationRule::NonNegativeAtr,
+        ValidationRule::FiniteMacd,
+        ValidationRule::MonotonicTimestamps,
+    ];
+}
+
+/// A snapshot that failed validation, with every reason it failed
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedSnapshot {
+    pub symbol: String,
+    pub timestamp: TimestampMS,
+    pub reasons: Vec<String>,
+    pub snapshot: MarketStateSnapshot,
+}
+
+/// Checks snapshots against sanity rules before they are embedded
+///
+/// All rules are enabled by default; use [`without_rule`](Self::without_rule)
+/// to relax one.
+#[derive(Debug, Clone)]
+pub struct SnapshotValidator {
+    rules: BTreeSet<ValidationRule>,
+}
+
+impl SnapshotValidator {
+    /// Create a validator with every rule enabled
+    pub fn new() -> Self {
+        Self {
+            rules: ValidationRule::ALL.into_iter().collect(),
+        }
+    }
+
+    /// Disable a rule
+    pub fn without_rule(mut self, rule: ValidationRule) -> Self {
+        self.rules.remove(&rule);
+        self
+    }
+
+    /// Whether a rule is enabled
+    pub fn has_rule(&self, rule: ValidationRule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    /// Reasons a single snapshot fails the per-field rules (empty if valid)
+    ///
+    /// Timestamp ordering needs the previous snapshot and is only checked by
+    /// [`validate_all`](Self::validate_all).
+    pub fn check(&self, snapshot: &MarketStateSnapshot) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        if self.has_rule(ValidationRule::RsiRange) {
+            let rsi = [("rsi_7", snapshot.rsi_7), ("rsi_14", snapshot.rsi_14)];
+            check_values(&mut reasons, &rsi, "outside 0..100", |v| (0.0..=100.0).contains(&v));
+            check_series(&mut reasons, "rsi_7_values", &snapshot.rsi_7_values, "outside 0..100", |v| (0.0..=100.0).contains(&v));
+            check_series(&mut reasons, "rsi_14_values", &snapshot.rsi_14_values, "outside 0..100", |v| (0.0..=100.0).contains(&v));
+            check_series(&mut reasons, "rsi_14_4h_values", &snapshot.rsi_14_4h_values, "outside 0..100", |v| (0.0..=100.0).contains(&v));
+            for (timeframe, context) in snapshot.timeframe_contexts() {
+                let name = format!("rsi_14_{}", timeframe);
+                check_values(&mut reasons, &[(&name, context.rsi_14)], "outside 0..100", |v| (0.0..=100.0).contains(&v));
+            }
+        }
+
+        if self.has_rule(ValidationRule::PositivePrices) {
+            let prices = [
+                ("price", snapshot.price),
+                ("ema_20", snapshot.ema_20),
+                ("ema_20_4h", snapshot.ema_20_4h),
+                ("ema_50_4h", snapshot.ema_50_4h),
+            ];
+            check_values(&mut reasons, &prices, "not positive", is_positive);
+            check_series(&mut reasons, "mid_prices", &snapshot.mid_prices, "not positive", is_positive);
+            check_series(&mut reasons, "ema_20_values", &snapshot.ema_20_values, "not positive", is_positive);
+            for (timeframe, context) in snapshot.timeframe_contexts() {
+                let (ema_20, ema_50) = (format!("ema_20_{}", timeframe), format!("ema_50_{}", timeframe));
+                check_values(&mut reasons, &[(&ema_20, context.ema_20), (&ema_50, context.ema_50)], "not positive", is_positive);
+            }
+        }
+
+        if self.has_rule(ValidationRule::NonNegativeAtr) {
+            let atr = [("atr_3_4h", snapshot.atr_3_4h), ("atr_14_4h", snapshot.atr_14_4h)];
+            check_values(&mut reasons, &atr, "negative or not finite", |v| v >= 0.0 && v.is_finite());
+        }
+
+        if self.has_rule(ValidationRule::FiniteMacd) {
+            check_values(&mut reasons, &[("macd", snapshot.macd)], "not finite", f64::is_finite);
+            check_series(&mut reasons, "macd_values", &snapshot.macd_values, "not finite", f64::is_finite);
+            check_series(&mut reasons, "macd_4h_values", &snapshot.macd_4h_values, "not finite", f64::is_finite);
+        }
+
+        reasons
+    }
+
+    /// Split snapshots (in extraction order) into valid and rejected ones
+    ///
+    /// # Arguments
+    /// * `snapshots` - Snapshots for one symbol, oldest first
+    ///
+    /// # Returns
+    /// Valid snapshots in their original order, and the rejected ones with reasons
+    pub fn validate_all(
+        &self,
+        snapshots: Vec<MarketStateSnapshot>,
+    ) -> (Vec<MarketStateSnapshot>, Vec<RejectedSnapshot>) {
+        let mut valid: Vec<MarketStateSnapshot> = Vec::with_capacity(snapshots.len());
+        let mut rejected = Vec::new();
+
+        for snapshot in snapshots {
+            let mut reasons = self.check(&snapshot);
+
+            if self.has_rule(ValidationRule::MonotonicTimestamps) {
+                if let Some(previous) = valid.last().filter(|p| snapshot.timestamp <= p.timestamp) {
+                    reasons.push(format!(
+                        "timestamp {} is not after previous snapshot at {}",
+                        snapshot.timestamp, previous.timestamp
+                    ));
+                }
+            }
+
+            if reasons.is_empty() {
+                valid.push(snapshot);
+            } else {
+                rejected.push(RejectedSnapshot {
+                    symbol: snapshot.symbol.clone(),
+                    timestamp: snapshot.timestamp,
+                    reasons,
+                    snapshot,
+                });
+            }
+        }
+
+        (valid, rejected)
+    }
+}
+
+impl Default for SnapshotValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append rejected snapshots to a JSONL quarantine file (one object per line)
+pub fn write_quarantine(path: &Path, rejected: &[RejectedSnapshot]) -> Result<()> {
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open quarantine file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    for entry in rejected {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write quarantine file {}", path.display()))?;
+
+    Ok(())
+}
+
+fn is_positive(value: f64) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+/// Record a reason for each named value that fails `ok`
+fn check_values(reasons: &mut Vec<String>, values: &[(&str, f64)], problem: &str, ok: impl Fn(f64) -> bool) {
+    for (name, value) in values {
+        if !ok(*value) {
+            reasons.push(format!("{} {} ({})", name, problem, value));
+        }
+    }
+}
+
+/// Record one reason for a series with failing points
+fn check_series(reasons: &mut Vec<String>, name: &str, values: &[f64], problem: &str, ok: impl Fn(f64) -> bool) {
+    let bad: Vec<usize> = (0..values.len()).filter(|i| !ok(values[*i])).collect();
+    if !bad.is_empty() {
+        reasons.push(format!("{} {} at points {:?}", name, problem, bad));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_snapshot(timestamp: TimestampMS) -> MarketStateSnapshot {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), timestamp, 50000.0);
+        snapshot.rsi_7 = 60.0;
+        snapshot.rsi_14 = 55.0;
+        snapshot.ema_20 = 49900.0;
+        snapshot.ema_20_4h = 49500.0;
+        snapshot.ema_50_4h = 49000.0;
+        snapshot.atr_3_4h = 200.0;
+        snapshot.atr_14_4h = 250.0;
+        snapshot.mid_prices = vec![50000.0; 10];
+        snapshot.ema_20_values = vec![49900.0; 10];
+        snapshot.rsi_7_values = vec![60.0; 10];
+        snapshot.rsi_14_values = vec![55.0; 10];
+        snapshot.macd_values = vec![1.0; 10];
+        snapshot.macd_4h_values = vec![1.0; 10];
+        snapshot.rsi_14_4h_values = vec![50.0; 10];
+        snapshot
+    }
+
+    #[test]
+    fn test_field_rules() {
+        let validator = SnapshotValidator::new();
+        assert!(validator.check(&valid_snapshot(1)).is_empty());
+
+        let mut snapshot = valid_snapshot(1);
+        snapshot.rsi_7 = 101.0;
+        snapshot.rsi_14_values[3] = f64::NAN;
+        snapshot.price = 0.0;
+        snapshot.atr_14_4h = -1.0;
+        snapshot.macd_4h_values[9] = f64::INFINITY;
+
+        let reasons = validator.check(&snapshot);
+        assert_eq!(reasons.len(), 5, "{:?}", reasons);
+        assert!(reasons[0].starts_with("rsi_7 outside 0..100"));
+        assert_eq!(reasons[1], "rsi_14_values outside 0..100 at points [3]");
+        assert!(reasons[2].starts_with("price not positive"));
+        assert!(reasons[3].starts_with("atr_14_4h negative"));
+        assert_eq!(reasons[4], "macd_4h_values not finite at points [9]");
+
+        let relaxed = validator.without_rule(ValidationRule::RsiRange);
+        assert_eq!(relaxed.check(&snapshot).len(), 3);
+
+        // The optional 1h/1d blocks are checked when present
+        let mut snapshot = valid_snapshot(1);
+        snapshot.context_1h = Some(trading_core::TimeframeContext {
+            ema_20: 49800.0,
+            ema_50: 0.0,
+            macd: 1.0,
+            rsi_14: 120.0,
+            atr_14: 100.0,
+            prev_high: 50100.0,
+            prev_low: 49700.0,
+            prev_close: 50000.0,
+        });
+        let reasons = SnapshotValidator::new().check(&snapshot);
+        assert_eq!(reasons.len(), 2, "{:?}", reasons);
+        assert!(reasons[0].starts_with("rsi_14_1h outside 0..100"));
+        assert!(reasons[1].starts_with("ema_50_1h not positive"));
+    }
+
+    #[test]
+    fn test_monotonic_timestamps_and_quarantine() {
+        let mut bad = valid_snapshot(4000);
+        bad.ema_20 = -5.0;
+        let snapshots = vec![valid_snapshot(1000), valid_snapshot(2000), valid_snapshot(2000), bad, valid_snapshot(3000)];
+
+        let (valid, rejected) = SnapshotValidator::new().validate_all(snapshots.clone());
+        let timestamps: Vec<_> = valid.iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![1000, 2000, 3000]);
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].reasons, vec!["timestamp 2000 is not after previous snapshot at 2000"]);
+
+        let (valid, _) = SnapshotValidator::new()
+            .without_rule(ValidationRule::MonotonicTimestamps)
+            .validate_all(snapshots);
+        assert_eq!(valid.len(), 4);
+
+        let path = std::env::temp_dir().join(format!("rag_quarantine_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        write_quarantine(&path, &rejected).unwrap();
+        write_quarantine(&path, &rejected[1..]).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["timestamp"], 4000);
+        assert_eq!(lines[1]["reasons"][0], "ema_20 not positive (-5)");
+        assert_eq!(lines[1]["snapshot"]["ema_20"], -5.0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use trading_core::MarketStateSnapshot;
+
+/// Name of the sparse keyword vector in hybrid collections
+pub const SPARSE_VECTOR_NAME: &str = "keywords";
+
+/// Weight of regime and divergence tokens (bucket tokens weigh 1.0)
+const LABEL_TOKEN_WEIGHT: f32 = 2.0;
+
+/// Keyword tokens describing a snapshot, with their weights
+///
+/// Tokens are exact labels (`trend_regime:uptrend`, `rsi_divergence:bearish`)
+/// and discretised indicator buckets (`rsi_7:7` for RSI 70-80), so exact
+/// matches count in the sparse half of a hybrid search however little they
+/// move the dense vector. Unavailable inputs produce no token.
+pub fn snapshot_tokens(snapshot: &MarketStateSnapshot) -> Vec<(String, f32)> {
+    let features = snapshot.features();
+    let regime = snapshot.regime_labels();
+    let mut tokens = Vec::new();
+
+    let mut label = |name: &str, value: &str| {
+        tokens.push((format!("{}:{}", name, value.replace(' ', "_")), LABEL_TOKEN_WEIGHT));
+    };
+    label("trend_regime", regime.trend.label());
+    if let Some(volatility) = regime.volatility {
+        label("volatility_regime", volatility.label());
+    }
+    if let Some(positioning) = regime.positioning {
+        label("positioning_regime", positioning.label());
+    }
+    if let Some(divergence) = features.rsi_divergence {
+        label("rsi_divergence", divergence.label());
+    }
+    if let Some(divergence) = features.macd_divergence {
+        label("macd_divergence", divergence.label());
+    }
+    for (timeframe, context) in snapshot.timeframe_contexts() {
+        label(&format!("trend_{}", timeframe), context.trend());
+    }
+
+    let mut bucket = |name: &str, value: Option<f64>, width: f64| {
+        if let Some(value) = value.filter(|v| v.is_finite()) {
+            tokens.push((format!("{}:{}", name, (value / width).floor() as i64), 1.0));
+        }
+    };
+    bucket("rsi_7", Some(snapshot.rsi_7), 10.0);
+    bucket("rsi_14", Some(snapshot.rsi_14), 10.0);
+    bucket("ema_ratio_pct", Some((features.ema_ratio - 1.0) * 100.0), 0.5);
+    bucket("ema_20_4h_distance_atr", features.ema_20_4h_distance_atr, 0.5);
+    bucket("volume_ratio_4h_log2", features.volume_ratio_4h.filter(|r| *r > 0.0).map(f64::log2), 0.5);
+    bucket("volatility_ratio", Some(features.volatility_ratio), 0.25);
+    bucket("oi_delta_pct", features.oi_delta_pct, 5.0);
+    bucket("funding_bps", snapshot.has_funding_rate.then_some(snapshot.funding_rate * 10_000.0), 2.0);
+    bucket("price_change_1h", snapshot.has_price_change_1h.then_some(snapshot.price_change_1h), 0.5);
+    bucket("price_change_4h", snapshot.has_price_change_4h.then_some(snapshot.price_change_4h), 1.0);
+    bucket("ref_price_change_4h", snapshot.reference.as_ref().and_then(|r| r.price_change_4h), 1.0);
+
+    tokens
+}
+
+/// Sparse vector of a snapshot's keyword tokens, sorted by index
+///
+/// Token indices are stable 32-bit FNV-1a hashes, so the same token maps to
+/// the same dimension at ingestion and query time. Colliding tokens add up.
+pub fn snapshot_sparse_vector(snapshot: &MarketStateSnapshot) -> Vec<(u32, f32)> {
+    let mut terms: Vec<(u32, f32)> = snapshot_tokens(snapshot)
+        .into_iter()
+        .map(|(token, weight)| (token_index(&token), weight))
+        .collect();
+    terms.sort_by_key(|(index, _)| *index);
+    terms.dedup_by(|next, kept| {
+        let duplicate = next.0 == kept.0;
+        if duplicate {
+            kept.1 += next.1;
+        }
+        duplicate
+    });
+    terms
+}
+
+/// 32-bit FNV-1a hash of a token
+fn token_index(token: &str) -> u32 {
+    token.bytes().fold(0x811c_9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_tokens() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.rsi_7 = 72.0;
+        snapshot.ema_20_4h = 51500.0;
+        snapshot.ema_50_4h = 50000.0;
+        snapshot.has_open_interest = false;
+        snapshot.has_funding_rate = false;
+        snapshot.has_price_change_1h = false;
+
+        let tokens: Vec<String> = snapshot_tokens(&snapshot).into_iter().map(|(t, _)| t).collect();
+        assert!(tokens.contains(&"trend_regime:strong_uptrend".to_string()), "{:?}", tokens);
+        assert!(tokens.contains(&"rsi_7:7".to_string()), "{:?}", tokens);
+        assert!(tokens.contains(&"ema_ratio_pct:6".to_string()), "{:?}", tokens);
+        assert!(!tokens.iter().any(|t| t.starts_with("positioning_regime") || t.starts_with("oi_delta_pct")));
+        assert!(!tokens.iter().any(|t| t.starts_with("funding_bps") || t.starts_with("trend_1d")));
+        assert!(!tokens.iter().any(|t| t.starts_with("price_change_1h")));
+        assert!(tokens.contains(&"price_change_4h:0".to_string()), "{:?}", tokens);
+    }
+
+    #[test]
+    fn test_sparse_vector_is_stable_and_sorted() {
+        assert_eq!(token_index(""), 0x811c_9dc5);
+        assert_eq!(token_index("a"), 0xe40c_292c);
+
+        let snapshot = MarketStateSnapshot::new("ETHUSDT".to_string(), 1000000, 3000.0);
+        let vector = snapshot_sparse_vector(&snapshot);
+        assert_eq!(vector, snapshot_sparse_vector(&snapshot));
+        assert!(vector.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(vector.iter().any(|(index, weight)| {
+            *index == token_index("trend_regime:sideways") && *weight == LABEL_TOKEN_WEIGHT
+        }));
+    }
+}
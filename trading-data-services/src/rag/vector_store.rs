@@ -1,13 +1,38 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::vector_output::Vector;
+use qdrant_client::qdrant::with_payload_selector::SelectorOptions;
 use qdrant_client::qdrant::{
-    CreateCollectionBuilder, Distance, Filter, PointStruct, ScoredPoint,
-    SearchPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+    CreateCollectionBuilder, CreateFieldIndexCollectionBuilder, DeletePointsBuilder, Distance, FieldType, Filter, GetPointsBuilder, Modifier, PayloadIncludeSelector, NamedVectors, PointId, PointStruct, PointsIdsList, Query, QueryPointsBuilder, ScoredPoint,
+    ScrollPointsBuilder, SearchPointsBuilder, SetPayloadPointsBuilder, SparseVectorParamsBuilder, SparseVectorsConfigBuilder, UpsertPointsBuilder,
+    VectorInput, VectorParamsBuilder, VectorsSelector,
 };
-use qdrant_client::Qdrant;
-use serde_json;
+use qdrant_client::{Payload, Qdrant};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::hash::Hash;
 use trading_core::MarketStateSnapshot;
 use tracing;
 
+use super::embedder::{check_embedder_metadata, SnapshotEmbedder};
+use super::sparse_vector::{snapshot_sparse_vector, SPARSE_VECTOR_NAME};
+use super::schema::{
+    CURRENT_FEATURE_VERSION, CURRENT_SCHEMA_VERSION, EMBEDDING_DIM, EMBEDDING_MODEL, KEYWORD_FIELDS,
+};
+
+/// A page of stored payloads and the offset of the next page
+pub type PayloadPage = (Vec<(PointId, Map<String, Value>)>, Option<PointId>);
+
+/// Rank constant of reciprocal rank fusion (dampens the weight of top ranks)
+pub const RRF_K: f64 = 60.0;
+
+/// Point IDs below this were numbered sequentially by ingestion before
+/// `snapshot_point_id` (a 64-bit hash lands below it with probability 2^-32)
+pub const LEGACY_POINT_ID_LIMIT: u64 = 1 << 32;
+
+/// Candidates fetched from each half of a hybrid search, per result
+const HYBRID_CANDIDATES_PER_RESULT: u64 = 4;
+
 /// Qdrant vector store for market snapshots
 pub struct VectorStore {
     client: Qdrant,
@@ -27,30 +52,83 @@ impl VectorStore {
         })
     }
 
-    /// Create collection if it doesn't exist
-    pub async fn create_collection_if_not_exists(&self, dimension: u64) -> Result<()> {
-        match self
-            .client
-            .create_collection(
-                CreateCollectionBuilder::new(&self.collection_name)
-                    .vectors_config(VectorParamsBuilder::new(dimension, Distance::Cosine))
-            )
-            .await
-        {
-            Ok(_) => {
-                tracing::info!("Created Qdrant collection: {}", self.collection_name);
-                Ok(())
-            }
-            Err(e) => {
-                // Collection might already exist
-                tracing::info!(
-                    "Qdrant collection {} already exists or error: {}",
-                    self.collection_name,
-                    e
-                );
-                Ok(())
+    /// Create collection if it doesn't exist, with its keyword indexes
+    ///
+    /// New collections record the embedder in their metadata and have a
+    /// sparse keyword vector next to the dense one for hybrid search. Existing
+    /// ones must have been built with a compatible embedder.
+    pub async fn create_collection_if_not_exists(&self, embedder: &dyn SnapshotEmbedder) -> Result<()> {
+        if self.client.collection_exists(&self.collection_name).await? {
+            let metadata = self.collection_metadata().await?;
+            check_embedder_metadata(&metadata, embedder)
+                .map_err(|e| anyhow!("Cannot ingest into {}: {}", self.collection_name, e))?;
+            tracing::info!("Qdrant collection {} already exists", self.collection_name);
+        } else {
+            let metadata: HashMap<String, Value> = embedder.metadata().into_iter().collect();
+            let mut sparse_config = SparseVectorsConfigBuilder::default();
+            sparse_config.add_named_vector_params(
+                SPARSE_VECTOR_NAME,
+                SparseVectorParamsBuilder::default().modifier(Modifier::Idf),
+            );
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(&self.collection_name)
+                        .vectors_config(VectorParamsBuilder::new(embedder.dimension() as u64, Distance::Cosine))
+                        .sparse_vectors_config(sparse_config)
+                        .metadata(metadata),
+                )
+                .await?;
+            tracing::info!(
+                "Created Qdrant collection {} for embedder {}",
+                self.collection_name,
+                embedder.model_name()
+            );
+        }
+
+        self.create_keyword_indexes().await
+    }
+
+    /// Whether the collection has the sparse keyword vector used by hybrid search
+    ///
+    /// Collections created before hybrid search only have the dense vector.
+    pub async fn has_sparse_vectors(&self) -> Result<bool> {
+        let info = self.client.collection_info(&self.collection_name).await?;
+        Ok(info
+            .result
+            .and_then(|info| info.config)
+            .and_then(|config| config.params)
+            .and_then(|params| params.sparse_vectors_config)
+            .is_some_and(|sparse| sparse.map.contains_key(SPARSE_VECTOR_NAME)))
+    }
+
+    /// Metadata stored with the collection (empty for collections created without any)
+    pub async fn collection_metadata(&self) -> Result<Map<String, Value>> {
+        let info = self.client.collection_info(&self.collection_name).await?;
+        Ok(info
+            .result
+            .and_then(|info| info.config)
+            .map(|config| config.metadata.into_iter().map(|(k, v)| (k, v.into_json())).collect())
+            .unwrap_or_default())
+    }
+
+    /// Index the keyword payload fields used in exact-match filters
+    ///
+    /// Existing indexes are left as they are, so this is safe to repeat.
+    pub async fn create_keyword_indexes(&self) -> Result<()> {
+        for field in KEYWORD_FIELDS {
+            if let Err(e) = self
+                .client
+                .create_field_index(CreateFieldIndexCollectionBuilder::new(
+                    &self.collection_name,
+                    *field,
+                    FieldType::Keyword,
+                ))
+                .await
+            {
+                tracing::warn!("Failed to index payload field {}: {}", field, e);
             }
         }
+        Ok(())
     }
 
     /// Upload points to Qdrant
@@ -95,6 +173,177 @@ impl VectorStore {
         Ok(search_result.result)
     }
 
+    /// Search the sparse keyword vector
+    ///
+    /// Points come with their dense vector, so callers can score them by
+    /// cosine similarity as well.
+    pub async fn sparse_search(
+        &self,
+        sparse_vector: Vec<(u32, f32)>,
+        limit: u64,
+        filter: Option<Filter>,
+    ) -> Result<Vec<ScoredPoint>> {
+        let (indices, values): (Vec<u32>, Vec<f32>) = sparse_vector.into_iter().unzip();
+        let mut query = QueryPointsBuilder::new(&self.collection_name)
+            .query(Query::new_nearest(VectorInput::new_sparse(indices, values)))
+            .using(SPARSE_VECTOR_NAME)
+            .limit(limit)
+            .with_payload(true)
+            .with_vectors(VectorsSelector::from(vec![String::new()]));
+
+        if let Some(f) = filter {
+            query = query.filter(f);
+        }
+
+        Ok(self.client.query(query).await?.result)
+    }
+
+    /// Hybrid search fusing the dense and sparse rankings
+    ///
+    /// Both halves fetch `limit * 4` candidates, and their union is ranked
+    /// with reciprocal rank fusion, so exact regime and bucket matches are
+    /// found even when their embedding is not among the nearest. Only the
+    /// dense half applies `score_threshold`. Every result's `score` is its
+    /// cosine similarity to `query_vector`.
+    ///
+    /// # Returns
+    /// Up to `limit` points, best fused rank first
+    pub async fn hybrid_search(
+        &self,
+        query_vector: Vec<f32>,
+        sparse_vector: Vec<(u32, f32)>,
+        limit: u64,
+        filter: Option<Filter>,
+        score_threshold: Option<f32>,
+    ) -> Result<Vec<ScoredPoint>> {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let dense = self.search(query_vector.clone(), candidates, filter.clone(), score_threshold).await?;
+        let sparse = self.sparse_search(sparse_vector, candidates, filter).await?;
+        Ok(fuse_hybrid_results(dense, sparse, &query_vector, limit as usize))
+    }
+
+    /// Content hashes stored with existing points
+    ///
+    /// # Returns
+    /// The `content_hash` of each point that exists and has one, by point ID
+    pub async fn content_hashes(&self, point_ids: &[u64]) -> Result<HashMap<u64, String>> {
+        if point_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let ids: Vec<PointId> = point_ids.iter().map(|&id| id.into()).collect();
+        let response = self
+            .client
+            .get_points(
+                GetPointsBuilder::new(&self.collection_name, ids)
+                    .with_payload(SelectorOptions::Include(PayloadIncludeSelector { fields: vec!["content_hash".to_string()] }))
+                    .with_vectors(false),
+            )
+            .await?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                let id = match point.id?.point_id_options? {
+                    PointIdOptions::Num(id) => id,
+                    PointIdOptions::Uuid(_) => return None,
+                };
+                let hash = point.payload.get("content_hash")?.as_str()?.clone();
+                Some((id, hash))
+            })
+            .collect())
+    }
+
+    /// Whether the collection holds points with sequential IDs from before `snapshot_point_id`
+    ///
+    /// Every such ingestion run numbered its points from 0, so point 0 exists.
+    pub async fn has_legacy_point_ids(&self) -> Result<bool> {
+        let response = self
+            .client
+            .get_points(
+                GetPointsBuilder::new(&self.collection_name, vec![PointId::from(0u64)])
+                    .with_payload(false)
+                    .with_vectors(false),
+            )
+            .await?;
+
+        Ok(!response.result.is_empty())
+    }
+
+    /// Delete points by ID
+    pub async fn delete_points(&self, ids: Vec<PointId>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.collection_name)
+                    .points(PointsIdsList { ids })
+                    .wait(true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Read a page of stored payloads (without vectors)
+    ///
+    /// # Arguments
+    /// * `offset` - Point to start from (`None` for the first page)
+    /// * `limit` - Maximum number of points in the page
+    ///
+    /// # Returns
+    /// Point IDs with their payloads, and the offset of the next page if any
+    pub async fn scroll_payloads(&self, offset: Option<PointId>, limit: u32) -> Result<PayloadPage> {
+        self.scroll_payloads_matching(None, offset, limit).await
+    }
+
+    /// Read a page of stored payloads matching a filter (see `scroll_payloads`)
+    pub async fn scroll_payloads_matching(
+        &self,
+        filter: Option<Filter>,
+        offset: Option<PointId>,
+        limit: u32,
+    ) -> Result<PayloadPage> {
+        let mut scroll = ScrollPointsBuilder::new(&self.collection_name)
+            .limit(limit)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(filter) = filter {
+            scroll = scroll.filter(filter);
+        }
+        if let Some(offset) = offset {
+            scroll = scroll.offset(offset);
+        }
+
+        let response = self.client.scroll(scroll).await?;
+        let points = response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                let payload = point.payload.into_iter().map(|(k, v)| (k, v.into_json())).collect();
+                point.id.map(|id| (id, payload))
+            })
+            .collect();
+
+        Ok((points, response.next_page_offset))
+    }
+
+    /// Replace the payload of one point, leaving its vector untouched
+    pub async fn overwrite_payload(&self, point_id: PointId, payload: Map<String, Value>) -> Result<()> {
+        self.client
+            .overwrite_payload(
+                SetPayloadPointsBuilder::new(&self.collection_name, Payload::from(payload))
+                    .points_selector(PointsIdsList { ids: vec![point_id] })
+                    .wait(true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     /// Get collection info
     pub async fn collection_info(&self) -> Result<()> {
         match self.client.collection_info(&self.collection_name).await {
@@ -110,26 +359,188 @@ impl VectorStore {
     }
 }
 
+/// Deterministic point ID of a snapshot
+///
+/// Derived from the symbol and timestamp only (a stable 64-bit FNV-1a
+/// hash), so re-ingesting a range upserts the same points and symbols
+/// sharing a collection never overwrite each other. The feature version is
+/// part of `snapshot_content_hash` instead: a snapshot re-ingested under a
+/// new feature version (or migrated to it) replaces its old point rather
+/// than being stored twice.
+///
+/// Collections ingested before these IDs numbered points from 0 on every
+/// run. Upserting into them would store each snapshot a second time, so
+/// ingestion refuses them (see `VectorStore::has_legacy_point_ids`); ingest
+/// into a new collection, or delete the old points with `purge_legacy_points`
+/// and re-ingest.
+pub fn snapshot_point_id(symbol: &str, timestamp: u64) -> u64 {
+    fnv1a_64(format!("{}|{}", symbol, timestamp).as_bytes())
+}
+
+/// Hash of everything a point is built from: the snapshot, payload schema,
+/// feature version and embedder
+///
+/// `embedder_identity` is `SnapshotEmbedder::identity` (the model name plus
+/// settings such as feature weights). Stored as `content_hash` so
+/// re-ingestion can skip points that would not change.
+pub fn snapshot_content_hash(snapshot: &MarketStateSnapshot, embedder_identity: &str) -> Result<String> {
+    let mut content = serde_json::to_vec(snapshot)
+        .with_context(|| format!("Failed to serialize snapshot {} at {}", snapshot.symbol, snapshot.timestamp))?;
+    content.extend_from_slice(
+        format!("|{}|{}|{}", CURRENT_SCHEMA_VERSION, CURRENT_FEATURE_VERSION.id, embedder_identity).as_bytes(),
+    );
+    Ok(format!("{:016x}", fnv1a_64(&content)))
+}
+
+/// 64-bit FNV-1a hash (stable across runs and platforms, unlike `DefaultHasher`)
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Helper to create Qdrant points from snapshots
+///
+/// `embedder` is the embedder that produced `embedding`; `point_id` is
+/// normally `snapshot_point_id`.
 pub fn snapshot_to_point(
     snapshot: &MarketStateSnapshot,
     embedding: Vec<f32>,
     point_id: u64,
-) -> PointStruct {
+    embedder: &dyn SnapshotEmbedder,
+) -> Result<PointStruct> {
+    let payload = snapshot_point_payload(snapshot, embedder)?;
+    Ok(PointStruct::new(point_id, embedding, payload))
+}
+
+/// Payload of a point: the snapshot payload plus the embedder and content hash
+pub fn snapshot_point_payload(
+    snapshot: &MarketStateSnapshot,
+    embedder: &dyn SnapshotEmbedder,
+) -> Result<Map<String, Value>> {
+    let mut payload = snapshot_to_payload(snapshot);
+    payload.insert("embedding_model".to_string(), Value::from(embedder.model_name()));
+    payload.insert("embedding_dim".to_string(), Value::from(embedder.dimension()));
+    payload.insert(
+        "content_hash".to_string(),
+        Value::from(snapshot_content_hash(snapshot, &embedder.identity())?),
+    );
+    Ok(payload)
+}
+
+/// Helper to create hybrid Qdrant points (dense embedding plus sparse keywords)
+///
+/// For collections created with a sparse vector; see `snapshot_to_point`.
+pub fn snapshot_to_hybrid_point(
+    snapshot: &MarketStateSnapshot,
+    embedding: Vec<f32>,
+    point_id: u64,
+    embedder: &dyn SnapshotEmbedder,
+) -> Result<PointStruct> {
+    let mut point = snapshot_to_point(snapshot, embedding.clone(), point_id, embedder)?;
+    let vectors = NamedVectors::default()
+        .add_vector("", embedding)
+        .add_vector(SPARSE_VECTOR_NAME, snapshot_sparse_vector(snapshot));
+    point.vectors = Some(vectors.into());
+    Ok(point)
+}
+
+/// Fuse several rankings with reciprocal rank fusion
+///
+/// Each item scores `sum(1 / (k + rank))` over the rankings it appears in
+/// (rank starting at 1), so items ranked well by several rankings come first.
+///
+/// # Returns
+/// Every ranked item with its fused score, best first (ties keep first-seen order)
+pub fn reciprocal_rank_fusion<T: Clone + Eq + Hash>(rankings: &[Vec<T>], k: f64) -> Vec<(T, f64)> {
+    let mut scores: Vec<(T, f64)> = Vec::new();
+    let mut positions: HashMap<T, usize> = HashMap::new();
+
+    for ranking in rankings {
+        for (rank, item) in ranking.iter().enumerate() {
+            let score = 1.0 / (k + rank as f64 + 1.0);
+            match positions.get(item) {
+                Some(&position) => scores[position].1 += score,
+                None => {
+                    positions.insert(item.clone(), scores.len());
+                    scores.push((item.clone(), score));
+                }
+            }
+        }
+    }
+
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores
+}
+
+/// Fuse dense and sparse search results with reciprocal rank fusion
+///
+/// Dense results keep their score. Points found only by the sparse search
+/// are scored by the cosine similarity of their dense vector to
+/// `query_vector` (0.0 when it was not returned), and their vectors dropped.
+///
+/// # Returns
+/// Up to `limit` points from either list, best fused rank first
+fn fuse_hybrid_results(
+    dense: Vec<ScoredPoint>,
+    sparse: Vec<ScoredPoint>,
+    query_vector: &[f32],
+    limit: usize,
+) -> Vec<ScoredPoint> {
+    let point_ids = |points: &[ScoredPoint]| -> Vec<PointId> { points.iter().filter_map(|p| p.id.clone()).collect() };
+    let fused = reciprocal_rank_fusion(&[point_ids(&dense), point_ids(&sparse)], RRF_K);
+
+    let mut dense_by_id: HashMap<PointId, ScoredPoint> =
+        dense.into_iter().filter_map(|p| p.id.clone().map(|id| (id, p))).collect();
+    let mut sparse_by_id: HashMap<PointId, ScoredPoint> =
+        sparse.into_iter().filter_map(|p| p.id.clone().map(|id| (id, p))).collect();
+
+    fused
+        .into_iter()
+        .filter_map(|(id, _)| {
+            dense_by_id.remove(&id).or_else(|| {
+                let mut point = sparse_by_id.remove(&id)?;
+                point.score = match point.vectors.take().and_then(|v| v.get_vector_by_name("")) {
+                    Some(Vector::Dense(dense)) => cosine_similarity(query_vector, &dense.data),
+                    _ => 0.0,
+                };
+                Some(point)
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Cosine similarity of two vectors (0.0 when either is zero)
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+/// Build the Qdrant payload for a snapshot with the current schema
+///
+/// Flat fields are derived for filtering and display; the full snapshot is
+/// stored under `"snapshot"` so payloads can be re-derived without re-embedding.
+/// The embedding fields default to the text embedder until `snapshot_to_point`
+/// records the actual one.
+pub fn snapshot_to_payload(snapshot: &MarketStateSnapshot) -> Map<String, Value> {
     let git_sha = std::env::var("GIT_SHA").unwrap_or_else(|_| "dev".to_string());
 
     let date = chrono::DateTime::from_timestamp_millis(snapshot.timestamp as i64)
         .map(|dt| dt.to_rfc3339())
         .unwrap_or_else(|| "unknown".to_string());
 
-    // Calculate derived features for payload
-    let ema_ratio = snapshot.ema_ratio_20_50();
-    let oi_delta_pct = snapshot.oi_delta_pct();
-    let volatility_ratio = if snapshot.atr_14_4h.abs() > 1e-9 {
-        snapshot.atr_3_4h / snapshot.atr_14_4h
-    } else {
-        1.0
-    };
+    // Unavailable derivatives and price changes are stored as null so range filters never match them
+    let funding_rate = snapshot.has_funding_rate.then_some(snapshot.funding_rate);
+    let price_change_1h = snapshot.has_price_change_1h.then_some(snapshot.price_change_1h);
+    let price_change_4h = snapshot.has_price_change_4h.then_some(snapshot.price_change_4h);
+    let regime = snapshot.regime_labels();
 
     let payload_json = serde_json::json!({
         // Identification
@@ -142,20 +553,48 @@ pub fn snapshot_to_point(
         "rsi_7": snapshot.rsi_7,
         "rsi_14": snapshot.rsi_14,
         "macd": snapshot.macd,
-        "ema_ratio": ema_ratio,
 
         // Derivatives
-        "oi_delta_pct": oi_delta_pct,
-        "funding_rate": snapshot.funding_rate,
+        "funding_rate": funding_rate,
+        "has_open_interest": snapshot.has_open_interest,
+        "has_funding_rate": snapshot.has_funding_rate,
 
         // Volatility
         "atr_3_4h": snapshot.atr_3_4h,
         "atr_14_4h": snapshot.atr_14_4h,
-        "volatility_ratio": volatility_ratio,
+
+        // Regime keywords (exact-match filters; null when the inputs were unavailable)
+        "trend_regime": regime.trend.label(),
+        "volatility_regime": regime.volatility.map(|v| v.label()),
+        "positioning_regime": regime.positioning.map(|p| p.label()),
 
         // Price changes
-        "price_change_1h": snapshot.price_change_1h,
-        "price_change_4h": snapshot.price_change_4h,
+        "price_change_1h": price_change_1h,
+        "price_change_4h": price_change_4h,
+
+        // Higher timeframe context (null when the block is absent)
+        "rsi_14_1h": snapshot.context_1h.map(|c| c.rsi_14),
+        "ema_ratio_1h": snapshot.context_1h.map(|c| c.ema_ratio()),
+        "rsi_14_1d": snapshot.context_1d.map(|c| c.rsi_14),
+        "ema_ratio_1d": snapshot.context_1d.map(|c| c.ema_ratio()),
+
+        // Microstructure (null when the source has no such data)
+        "spread_bps": snapshot.spread_bps,
+        "book_imbalance": snapshot.book_imbalance,
+        "taker_buy_sell_ratio_30m": snapshot.taker_buy_sell_ratio_30m,
+        "cvd_30m": snapshot.cvd_30m,
+        "long_liquidations_30m": snapshot.long_liquidations_30m,
+        "short_liquidations_30m": snapshot.short_liquidations_30m,
+
+        // Reference asset (null for the reference symbol itself)
+        "ref_symbol": snapshot.reference.as_ref().map(|r| &r.symbol),
+        "ref_price_change_1h": snapshot.reference.as_ref().and_then(|r| r.price_change_1h),
+        "ref_price_change_4h": snapshot.reference.as_ref().and_then(|r| r.price_change_4h),
+        "ref_rsi_14": snapshot.reference.as_ref().map(|r| r.rsi_14),
+        "ref_correlation_4h": snapshot.reference.as_ref().and_then(|r| r.correlation_4h),
+
+        // Data quality
+        "data_quality": snapshot.data_quality,
 
         // OUTCOMES - THE VALUABLE PART
         "outcome_15m": snapshot.outcome_15m,
@@ -166,24 +605,39 @@ pub fn snapshot_to_point(
         "max_drawdown_1h": snapshot.max_drawdown_1h,
         "hit_stop_loss": snapshot.hit_stop_loss,
         "hit_take_profit": snapshot.hit_take_profit,
+        "outcomes": snapshot.outcomes,
+        "long_trade": snapshot.long_trade,
+        "short_trade": snapshot.short_trade,
+        "outcomes_pending": snapshot.outcomes_pending,
+
+        // Source snapshot (for payload migrations)
+        "snapshot": snapshot,
 
         // Metadata & provenance
-        "schema_version": 1,
-        "feature_version": "v1_nofx_3m4h",
-        "embedding_model": "bge-small-en-v1.5",
-        "embedding_dim": 384,
+        "schema_version": CURRENT_SCHEMA_VERSION,
+        "feature_version": CURRENT_FEATURE_VERSION.id,
+        "embedding_model": EMBEDDING_MODEL,
+        "embedding_dim": EMBEDDING_DIM,
         "build_id": git_sha,
     });
 
     // Convert to Map for Qdrant Payload compatibility
-    let payload = payload_json.as_object().unwrap().clone();
+    let mut payload = payload_json.as_object().unwrap().clone();
 
-    PointStruct::new(point_id, embedding, payload)
+    // Derived features (ema_ratio, oi_delta_pct, slopes, ...; null when unavailable)
+    for (name, value) in snapshot.features().values() {
+        payload.insert(name.to_string(), Value::from(value));
+    }
+
+    payload
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rag::embedder::TextSnapshotEmbedder;
+    use qdrant_client::qdrant::vectors_output::VectorsOptions;
+    use qdrant_client::qdrant::{DenseVector, VectorOutput, VectorsOutput};
 
     #[test]
     fn test_snapshot_to_point() {
@@ -192,9 +646,13 @@ mod tests {
         snapshot.ema_20_4h = 50500.0;
         snapshot.ema_50_4h = 50000.0;
         snapshot.outcome_4h = Some(-1.5);
+        snapshot.outcomes.insert(
+            "8h".to_string(),
+            trading_core::HorizonOutcome { return_pct: Some(2.5), ..Default::default() },
+        );
 
         let embedding = vec![0.1; 384];
-        let point = snapshot_to_point(&snapshot, embedding.clone(), 123);
+        let point = snapshot_to_point(&snapshot, embedding.clone(), 123, &TextSnapshotEmbedder::new()).unwrap();
 
         // Verify point is created with correct structure
         assert!(point.id.is_some());
@@ -205,5 +663,145 @@ mod tests {
         assert!(point.payload.contains_key("symbol"));
         assert!(point.payload.contains_key("rsi_7"));
         assert!(point.payload.contains_key("outcome_4h"));
+        for name in trading_core::FeatureSet::NAMES {
+            assert!(point.payload.contains_key(name), "missing feature {}", name);
+        }
+
+        // Per-horizon outcomes are stored as a nested struct keyed by label
+        match point.payload.get("outcomes").and_then(|v| v.kind.as_ref()) {
+            Some(qdrant_client::qdrant::value::Kind::StructValue(outcomes)) => {
+                assert!(outcomes.fields.contains_key("8h"));
+            }
+            other => panic!("outcomes payload is not a struct: {:?}", other),
+        }
+
+        // Regimes are classified when the snapshot has none stored
+        match point.payload.get("trend_regime").and_then(|v| v.kind.as_ref()) {
+            Some(qdrant_client::qdrant::value::Kind::StringValue(trend)) => assert_eq!(trend, "uptrend"),
+            other => panic!("trend_regime payload is not a keyword: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unavailable_derivatives_are_null() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        snapshot.has_open_interest = false;
+        snapshot.has_funding_rate = false;
+        snapshot.has_price_change_4h = false;
+        snapshot.reference = Some(trading_core::ReferenceContext {
+            symbol: "ETHUSDT".to_string(),
+            price_change_1h: Some(0.4),
+            price_change_4h: None,
+            rsi_14: 55.0,
+            correlation_4h: None,
+        });
+
+        let point = snapshot_to_point(&snapshot, vec![0.1; 384], 1, &TextSnapshotEmbedder::new()).unwrap();
+
+        let is_null = |key: &str| {
+            matches!(
+                point.payload.get(key).and_then(|v| v.kind.as_ref()),
+                Some(qdrant_client::qdrant::value::Kind::NullValue(_))
+            )
+        };
+        assert!(is_null("oi_delta_pct"));
+        assert!(is_null("funding_rate"));
+        assert!(is_null("price_change_4h"));
+        assert!(!is_null("price_change_1h"));
+        assert!(is_null("rsi_14_1h"));
+        assert!(is_null("rsi_14_1d"));
+        assert!(is_null("spread_bps"));
+        assert!(is_null("cvd_30m"));
+        assert!(is_null("long_liquidations_30m"));
+        assert!(is_null("ref_price_change_4h"));
+        assert!(!is_null("ref_price_change_1h"));
+        assert!(is_null("positioning_regime"));
+        assert!(is_null("volume_ratio_4h"));
+        assert!(is_null("rsi_divergence"));
+    }
+
+    #[test]
+    fn test_point_ids_are_deterministic() {
+        let id = snapshot_point_id("BTCUSDT", 1_700_000_000_000);
+        assert_eq!(id, snapshot_point_id("BTCUSDT", 1_700_000_000_000));
+        assert_ne!(id, snapshot_point_id("ETHUSDT", 1_700_000_000_000));
+        assert_ne!(id, snapshot_point_id("BTCUSDT", 1_700_000_180_000));
+        assert_eq!(fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        let hash = snapshot_content_hash(&snapshot, EMBEDDING_MODEL).unwrap();
+        assert_eq!(hash, snapshot_content_hash(&snapshot.clone(), EMBEDDING_MODEL).unwrap());
+        assert_ne!(hash, snapshot_content_hash(&snapshot, "snapshot-features-v1").unwrap());
+        assert_ne!(
+            snapshot_content_hash(&snapshot, "snapshot-features-v1").unwrap(),
+            snapshot_content_hash(&snapshot, "snapshot-features-v1|funding_rate=3").unwrap()
+        );
+        snapshot.outcome_4h = Some(1.0);
+        assert_ne!(hash, snapshot_content_hash(&snapshot, EMBEDDING_MODEL).unwrap());
+
+        let point = snapshot_to_point(&snapshot, vec![0.1; 384], 1, &TextSnapshotEmbedder::new()).unwrap();
+        assert_eq!(
+            point.payload.get("content_hash").and_then(|v| v.as_str()),
+            Some(&snapshot_content_hash(&snapshot, EMBEDDING_MODEL).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_hybrid_point_has_named_vectors() {
+        use qdrant_client::qdrant::vectors::VectorsOptions;
+
+        let snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        let point = snapshot_to_hybrid_point(&snapshot, vec![0.1; 384], 7, &TextSnapshotEmbedder::new()).unwrap();
+
+        match point.vectors.and_then(|v| v.vectors_options) {
+            Some(VectorsOptions::Vectors(named)) => {
+                assert!(named.vectors.contains_key(""));
+                assert!(named.vectors.contains_key(SPARSE_VECTOR_NAME));
+            }
+            other => panic!("hybrid point vectors are not named: {:?}", other),
+        }
+        assert!(point.payload.contains_key("embedding_model"));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let dense = vec!["a", "b", "c"];
+        let sparse = vec!["c", "d", "a"];
+        let fused = reciprocal_rank_fusion(&[dense, sparse], RRF_K);
+
+        let order: Vec<&str> = fused.iter().map(|(id, _)| *id).collect();
+        assert_eq!(order, vec!["a", "c", "b", "d"]);
+        assert!((fused[0].1 - (1.0 / 61.0 + 1.0 / 63.0)).abs() < 1e-12);
+        // a ties with c and b with d; first seen wins
+        assert_eq!(fused[2].1, fused[3].1);
+    }
+
+    #[test]
+    fn test_hybrid_results_include_sparse_only_points() {
+        let point = |id: u64, score: f32, vector: Option<Vec<f32>>| ScoredPoint {
+            id: Some(PointId::from(id)),
+            score,
+            vectors: vector.map(|v| VectorsOutput {
+                vectors_options: Some(VectorsOptions::Vector(VectorOutput {
+                    vector: Some(Vector::Dense(DenseVector { data: v })),
+                    ..Default::default()
+                })),
+            }),
+            ..Default::default()
+        };
+        let query = vec![1.0, 0.0];
+        let dense = vec![point(1, 0.9, None), point(2, 0.8, None)];
+        // Point 3 is below the dense threshold but matches the keywords best
+        let sparse = vec![point(3, 12.0, Some(vec![0.6, 0.8])), point(1, 5.0, Some(vec![1.0, 0.0]))];
+
+        let fused = fuse_hybrid_results(dense, sparse, &query, 3);
+
+        let ids: Vec<PointId> = fused.iter().filter_map(|p| p.id.clone()).collect();
+        assert_eq!(ids, vec![PointId::from(1), PointId::from(3), PointId::from(2)]);
+        assert_eq!(fused[0].score, 0.9);
+        assert!((fused[1].score - 0.6).abs() < 1e-6);
+        assert!(fused[1].vectors.is_none());
+
+        assert_eq!(fuse_hybrid_results(Vec::new(), Vec::new(), &query, 3).len(), 0);
     }
 }
@@ -1,3 +1,6 @@
+// Lints predating the workspace clippy gate; the example is kept as written
+#![allow(clippy::empty_line_after_doc_comments)]
+
 /// Phase 4: LLM RAG V1 Strategy Usage Example
 ///
 /// This example demonstrates how to:
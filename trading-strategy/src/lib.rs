@@ -4,7 +4,7 @@ pub mod strategy;
 // Re-export commonly used items from llm module
 pub use llm::{
     HistoricalMatch, LlmClient, LlmConfig, LlmPromptFormatter, LlmProvider, LlmResponse,
-    RagRetriever, SignalAction, TradingDecision,
+    RagRetriever, RegimeFilter, SideStatistics, SignalAction, TradingDecision,
 };
 
 // Re-export commonly used items from strategy module
@@ -32,17 +32,26 @@ pub struct RagMetrics {
     /// Number of historical matches found
     pub num_matches: usize,
 
-    /// Distribution of 4-hour outcomes from retrieved patterns
+    /// Horizon the outcome statistics are taken at (e.g. "4h")
+    pub outcome_horizon: String,
+
+    /// Distribution of outcomes at the horizon from retrieved patterns
     pub outcomes_distribution: Vec<f64>,
 
-    /// Median 4-hour outcome from historical matches
-    pub outcome_median_4h: Option<f64>,
+    /// Median outcome from historical matches
+    pub outcome_median: Option<f64>,
+
+    /// 10th percentile (P10) of outcomes
+    pub outcome_p10: Option<f64>,
+
+    /// 90th percentile (P90) of outcomes
+    pub outcome_p90: Option<f64>,
 
-    /// 10th percentile (P10) of 4-hour outcomes
-    pub outcome_p10_4h: Option<f64>,
+    /// Share of simulated long trades that closed in profit
+    pub long_win_rate: Option<f64>,
 
-    /// 90th percentile (P90) of 4-hour outcomes
-    pub outcome_p90_4h: Option<f64>,
+    /// Share of simulated short trades that closed in profit
+    pub short_win_rate: Option<f64>,
 }
 
 impl RagMetrics {
@@ -84,12 +93,13 @@ impl RagMetrics {
         self.similarity_scores = scores;
     }
 
-    /// Set outcome distribution and compute statistics
-    pub fn set_outcomes(&mut self, outcomes: Vec<f64>) {
+    /// Set the outcome distribution at a horizon and compute statistics
+    pub fn set_outcomes(&mut self, horizon: &str, outcomes: Vec<f64>) {
+        self.outcome_horizon = horizon.to_string();
         if outcomes.is_empty() {
-            self.outcome_median_4h = None;
-            self.outcome_p10_4h = None;
-            self.outcome_p90_4h = None;
+            self.outcome_median = None;
+            self.outcome_p10 = None;
+            self.outcome_p90 = None;
             self.outcomes_distribution = Vec::new();
             return;
         }
@@ -98,12 +108,18 @@ impl RagMetrics {
         sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
 
         // Calculate percentiles
-        self.outcome_median_4h = Some(percentile(&sorted, 50.0));
-        self.outcome_p10_4h = Some(percentile(&sorted, 10.0));
-        self.outcome_p90_4h = Some(percentile(&sorted, 90.0));
+        self.outcome_median = Some(percentile(&sorted, 50.0));
+        self.outcome_p10 = Some(percentile(&sorted, 10.0));
+        self.outcome_p90 = Some(percentile(&sorted, 90.0));
         self.outcomes_distribution = sorted;
     }
 
+    /// Set the win rates of simulated long and short trades
+    pub fn set_side_win_rates(&mut self, long: Option<f64>, short: Option<f64>) {
+        self.long_win_rate = long;
+        self.short_win_rate = short;
+    }
+
     /// Calculate average similarity score
     pub fn avg_similarity(&self) -> f32 {
         if self.similarity_scores.is_empty() {
@@ -123,7 +139,7 @@ impl RagMetrics {
         let avg_sim = self.avg_similarity();
 
         tracing::info!(
-            "RAG Metrics: retrieval={}ms, embedding={}ms, llm={}ms, total={}ms, avg_sim={:.2}, matches={}, sim_range=[{:?},{:?}], median_4h={:?}, p10_4h={:?}, p90_4h={:?}",
+            "RAG Metrics: retrieval={}ms, embedding={}ms, llm={}ms, total={}ms, avg_sim={:.2}, matches={}, sim_range=[{:?},{:?}], horizon={}, median={:?}, p10={:?}, p90={:?}, long_win={:?}, short_win={:?}",
             self.retrieval_latency_ms,
             self.embedding_latency_ms,
             self.llm_latency_ms,
@@ -132,9 +148,12 @@ impl RagMetrics {
             self.num_matches,
             self.similarity_min,
             self.similarity_max,
-            self.outcome_median_4h,
-            self.outcome_p10_4h,
-            self.outcome_p90_4h,
+            self.outcome_horizon,
+            self.outcome_median,
+            self.outcome_p10,
+            self.outcome_p90,
+            self.long_win_rate,
+            self.short_win_rate,
         );
     }
 
@@ -219,11 +238,12 @@ mod tests {
     fn test_outcomes_distribution() {
         let mut metrics = RagMetrics::new();
         let outcomes = vec![-2.3, 1.1, -1.8, -0.5, 0.9];
-        metrics.set_outcomes(outcomes);
+        metrics.set_outcomes("8h", outcomes);
 
-        assert_eq!(metrics.outcome_median_4h, Some(-0.5));
-        assert!(metrics.outcome_p10_4h.is_some());
-        assert!(metrics.outcome_p90_4h.is_some());
+        assert_eq!(metrics.outcome_horizon, "8h");
+        assert_eq!(metrics.outcome_median, Some(-0.5));
+        assert!(metrics.outcome_p10.is_some());
+        assert!(metrics.outcome_p90.is_some());
     }
 
     #[test]
@@ -245,11 +265,11 @@ mod tests {
     #[test]
     fn test_empty_outcomes() {
         let mut metrics = RagMetrics::new();
-        metrics.set_outcomes(vec![]);
+        metrics.set_outcomes("4h", vec![]);
 
-        assert_eq!(metrics.outcome_median_4h, None);
-        assert_eq!(metrics.outcome_p10_4h, None);
-        assert_eq!(metrics.outcome_p90_4h, None);
+        assert_eq!(metrics.outcome_median, None);
+        assert_eq!(metrics.outcome_p10, None);
+        assert_eq!(metrics.outcome_p90, None);
     }
 
     #[test]
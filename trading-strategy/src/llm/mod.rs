@@ -4,7 +4,7 @@ pub mod llm_client;
 pub mod metrics;
 
 // Re-export commonly used items
-pub use rag_retriever::{HistoricalMatch, RagRetriever};
+pub use rag_retriever::{HistoricalMatch, RagRetriever, RegimeFilter, SideStatistics};
 pub use prompt_formatter::LlmPromptFormatter;
 pub use llm_client::{
     LlmClient, LlmConfig, LlmProvider, LlmResponse, SignalAction, TradingDecision,
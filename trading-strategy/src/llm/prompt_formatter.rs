@@ -1,5 +1,6 @@
+use super::rag_retriever::{SideStatistics, DEFAULT_OUTCOME_HORIZON};
 use super::HistoricalMatch;
-use trading_core::MarketStateSnapshot;
+use trading_core::{ExitReason, MarketStateSnapshot, TradeOutcome, TradeSide};
 
 /// Formatter for LLM prompts with or without RAG context
 pub struct LlmPromptFormatter;
@@ -36,19 +37,22 @@ impl LlmPromptFormatter {
             current_snapshot.ema_ratio_20_50()
         ));
         prompt.push_str(&format!(
-            "  Open Interest: {:.1}% vs 24h avg\n",
-            current_snapshot.oi_delta_pct()
+            "  Open Interest: {} vs 24h avg\n",
+            format_oi_delta(current_snapshot)
         ));
         prompt.push_str(&format!(
-            "  Funding Rate: {:.6}\n",
-            current_snapshot.funding_rate
+            "  Funding Rate: {}\n",
+            format_funding_rate(current_snapshot)
         ));
         prompt.push_str(&format!(
-            "  Price Change 1h: {:+.2}% | 4h: {:+.2}%\n",
-            current_snapshot.price_change_1h, current_snapshot.price_change_4h
+            "  Price Change 1h: {} | 4h: {}\n",
+            format_price_change(current_snapshot.has_price_change_1h, current_snapshot.price_change_1h),
+            format_price_change(current_snapshot.has_price_change_4h, current_snapshot.price_change_4h)
         ));
+        prompt.push_str(&format_features(current_snapshot));
+        prompt.push_str(&format_timeframe_contexts(current_snapshot));
 
-        prompt.push_str("\n");
+        prompt.push('\n');
         prompt.push_str("⚠️  NO HISTORICAL PATTERN CONTEXT AVAILABLE\n\n");
         prompt.push_str("DECISION REQUIRED:\n");
         prompt.push_str("Based on current indicators only, should the strategy:\n");
@@ -63,10 +67,32 @@ impl LlmPromptFormatter {
     /// Format a prompt enriched with historical pattern analysis
     ///
     /// Includes similar patterns, their outcomes, and summary statistics
+    /// for the default 4h horizon
     pub fn format_with_historical_patterns(
         symbol: &str,
         current_snapshot: &MarketStateSnapshot,
         historical_matches: Vec<HistoricalMatch>,
+    ) -> String {
+        Self::format_with_historical_patterns_for_horizon(
+            symbol,
+            current_snapshot,
+            historical_matches,
+            DEFAULT_OUTCOME_HORIZON,
+        )
+    }
+
+    /// Format a prompt enriched with historical pattern analysis for one horizon
+    ///
+    /// # Arguments
+    /// * `symbol` - Trading symbol
+    /// * `current_snapshot` - Current market state
+    /// * `historical_matches` - Similar historical patterns
+    /// * `horizon` - Outcome horizon label to summarise (e.g. "8h")
+    pub fn format_with_historical_patterns_for_horizon(
+        symbol: &str,
+        current_snapshot: &MarketStateSnapshot,
+        historical_matches: Vec<HistoricalMatch>,
+        horizon: &str,
     ) -> String {
         let mut prompt = String::new();
 
@@ -91,18 +117,21 @@ impl LlmPromptFormatter {
             current_snapshot.ema_ratio_20_50()
         ));
         prompt.push_str(&format!(
-            "  OI Delta: {:+.1}% | Funding: {:.6}\n",
-            current_snapshot.oi_delta_pct(),
-            current_snapshot.funding_rate
+            "  OI Delta: {} | Funding: {}\n",
+            format_oi_delta(current_snapshot),
+            format_funding_rate(current_snapshot)
         ));
         prompt.push_str(&format!(
-            "  Price Change 1h: {:+.2}% | 4h: {:+.2}%\n",
-            current_snapshot.price_change_1h, current_snapshot.price_change_4h
+            "  Price Change 1h: {} | 4h: {}\n",
+            format_price_change(current_snapshot.has_price_change_1h, current_snapshot.price_change_1h),
+            format_price_change(current_snapshot.has_price_change_4h, current_snapshot.price_change_4h)
         ));
+        prompt.push_str(&format_features(current_snapshot));
+        prompt.push_str(&format_timeframe_contexts(current_snapshot));
 
         // Historical pattern analysis
         if !historical_matches.is_empty() {
-            prompt.push_str("\n");
+            prompt.push('\n');
             prompt.push_str("═══════════════════════════════════════════════════════════\n");
             prompt.push_str("📊 HISTORICAL PATTERN ANALYSIS\n");
             prompt.push_str("What Happened When Market Looked Like This\n");
@@ -123,41 +152,57 @@ impl LlmPromptFormatter {
                 ));
 
                 prompt.push_str(&format!(
-                    "   State: RSI7={:.1}, MACD={:.1}, EMA_Ratio={:.3}, OI{:+.1}%, Fund={:.4}\n",
-                    m.rsi_7, m.macd, m.ema_ratio, m.oi_delta_pct, m.funding_rate
+                    "   State: RSI7={:.1}, MACD={:.1}, EMA_Ratio={:.3}, OI={}, Fund={}\n",
+                    m.rsi_7,
+                    m.macd,
+                    m.ema_ratio,
+                    m.oi_delta_pct.map_or("n/a".to_string(), |oi| format!("{:+.1}%", oi)),
+                    m.funding_rate.map_or("n/a".to_string(), |funding| format!("{:.4}", funding))
                 ));
 
                 // Outcomes - the valuable part
-                if let Some(outcome_4h) = m.outcome_4h {
-                    prompt.push_str(&format!("   → 4h Result: {:+.2}%", outcome_4h));
-
-                    if let (Some(runup), Some(drawdown)) = (m.max_runup_1h, m.max_drawdown_1h) {
+                if let Some(outcome) = m.outcome(horizon).filter(|o| o.return_pct.is_some()) {
+                    prompt.push_str(&format!(
+                        "   → {} Result: {:+.2}%",
+                        horizon,
+                        outcome.return_pct.unwrap_or_default()
+                    ));
+
+                    if let (Some(runup), Some(drawdown)) = (outcome.max_runup_pct, outcome.max_drawdown_pct) {
                         prompt.push_str(&format!(
                             " (peak: {:+.1}%, trough: {:+.1}%)",
                             runup, drawdown
                         ));
                     }
 
-                    if m.hit_stop_loss == Some(true) {
+                    if outcome.hit_stop_loss == Some(true) {
                         prompt.push_str(" ❌ HIT STOP");
-                    } else if m.hit_take_profit == Some(true) {
+                    } else if outcome.hit_take_profit == Some(true) {
                         prompt.push_str(" ✅ HIT TARGET");
                     }
 
-                    prompt.push_str("\n");
+                    prompt.push('\n');
                 }
 
-                prompt.push_str("\n");
+                if m.long_trade.is_some() || m.short_trade.is_some() {
+                    prompt.push_str(&format!(
+                        "   → Long: {} | Short: {}\n",
+                        format_trade(m.long_trade.as_ref()),
+                        format_trade(m.short_trade.as_ref())
+                    ));
+                }
+
+                prompt.push('\n');
             }
 
             // Summary statistics
-            let stats = OutcomeStatistics::calculate(&historical_matches);
+            let stats = OutcomeStatistics::calculate(&historical_matches, horizon);
 
-            prompt.push_str("OUTCOME SUMMARY (4h horizon):\n");
-            prompt.push_str(&format!("  Average: {:+.2}%\n", stats.avg_outcome_4h));
+            prompt.push_str(&format!("OUTCOME SUMMARY ({} horizon):\n", horizon));
+            prompt.push_str(&format!("  Average: {:+.2}%\n", stats.avg_outcome));
             prompt.push_str(&format!(
                 "  Median: {:+.2}% | P10: {:+.2}% | P90: {:+.2}%\n",
-                stats.median_outcome_4h, stats.p10_outcome_4h, stats.p90_outcome_4h
+                stats.median_outcome, stats.p10_outcome, stats.p90_outcome
             ));
             prompt.push_str(&format!(
                 "  Positive: {}/{} ({:.0}%) | Negative: {}/{} ({:.0}%)\n",
@@ -177,13 +222,22 @@ impl LlmPromptFormatter {
                 stats.min_similarity * 100.0,
                 stats.max_similarity * 100.0
             ));
+
+            // Simulated trades answer the long-vs-short question directly
+            let long = SideStatistics::calculate(&historical_matches, TradeSide::Long);
+            let short = SideStatistics::calculate(&historical_matches, TradeSide::Short);
+            if long.trades > 0 || short.trades > 0 {
+                prompt.push_str("\nSIMULATED TRADES (first of stop/target on 3m highs/lows):\n");
+                prompt.push_str(&format!("  LONG:  {}\n", format_side_statistics(&long)));
+                prompt.push_str(&format!("  SHORT: {}\n", format_side_statistics(&short)));
+            }
         } else {
-            prompt.push_str("\n");
+            prompt.push('\n');
             prompt.push_str("[No similar historical patterns found - using current data only]\n");
         }
 
         // Decision prompt
-        prompt.push_str("\n");
+        prompt.push('\n');
         prompt.push_str("═══════════════════════════════════════════════════════════\n");
         prompt.push_str("DECISION REQUIRED:\n\n");
         prompt.push_str("Based on the CURRENT STATE and HISTORICAL OUTCOMES, choose:\n");
@@ -199,12 +253,118 @@ impl LlmPromptFormatter {
     }
 }
 
-/// Statistics calculated from historical outcomes
+/// Format OI delta, or "n/a" when open interest was unavailable
+fn format_oi_delta(snapshot: &MarketStateSnapshot) -> String {
+    if snapshot.has_open_interest {
+        format!("{:+.1}%", snapshot.oi_delta_pct())
+    } else {
+        "n/a".to_string()
+    }
+}
+
+/// Format funding rate, or "n/a" when funding was unavailable
+fn format_funding_rate(snapshot: &MarketStateSnapshot) -> String {
+    if snapshot.has_funding_rate {
+        format!("{:.6}", snapshot.funding_rate)
+    } else {
+        "n/a".to_string()
+    }
+}
+
+/// Format a price change, or "n/a" when its past candle was missing
+fn format_price_change(available: bool, change: f64) -> String {
+    if available {
+        format!("{:+.2}%", change)
+    } else {
+        "n/a".to_string()
+    }
+}
+
+/// Format the derived features of a snapshot on one line
+///
+/// e.g. "  Features: +1.5 ATR from 4h EMA20 | Volume 2.0x avg | Price slope +0.120%/3m | RSI divergence: bearish"
+fn format_features(snapshot: &MarketStateSnapshot) -> String {
+    let features = snapshot.features();
+    let mut items = Vec::new();
+
+    if let Some(distance) = features.ema_20_4h_distance_atr {
+        items.push(format!("{:+.1} ATR from 4h EMA20", distance));
+    }
+    if let Some(volume_ratio) = features.volume_ratio_4h {
+        items.push(format!("Volume {:.1}x avg", volume_ratio));
+    }
+    items.push(format!("Price slope {:+.3}%/3m", features.price_slope_pct));
+    if let Some(zscore) = features.rsi_7_zscore {
+        items.push(format!("RSI(7) z {:+.1}", zscore));
+    }
+    if let Some(divergence) = features.rsi_divergence {
+        items.push(format!("RSI divergence: {}", divergence));
+    }
+    if let Some(divergence) = features.macd_divergence {
+        items.push(format!("MACD divergence: {}", divergence));
+    }
+
+    format!("  Features: {}\n", items.join(" | "))
+}
+
+/// Format the 1h/1d blocks present in a snapshot, one line each
+///
+/// e.g. "  1d: uptrend, above EMA20 and EMA50 (+2.10% from EMA20) | RSI(14): 61.0 | Prior H/L: $50500.00 / $49200.00"
+fn format_timeframe_contexts(snapshot: &MarketStateSnapshot) -> String {
+    snapshot
+        .timeframe_contexts()
+        .map(|(timeframe, context)| {
+            format!(
+                "  {}: {}, {} ({:+.2}% from EMA20) | RSI(14): {:.1} | Prior H/L: ${:.2} / ${:.2}\n",
+                timeframe,
+                context.trend(),
+                context.ema_position(snapshot.price),
+                context.pct_from_ema_20(snapshot.price),
+                context.rsi_14,
+                context.prev_high,
+                context.prev_low
+            )
+        })
+        .collect()
+}
+
+/// Format one simulated trade, e.g. "+3.00% (target after 95m)"
+fn format_trade(trade: Option<&TradeOutcome>) -> String {
+    match trade {
+        Some(trade) => {
+            let exit = match trade.exit_reason {
+                ExitReason::StopLoss => "stop",
+                ExitReason::TakeProfit => "target",
+                ExitReason::Horizon => "time exit",
+            };
+            format!("{:+.2}% ({} after {}m)", trade.pnl_pct, exit, trade.exit_after_ms / 60_000)
+        }
+        None => "n/a".to_string(),
+    }
+}
+
+/// Format the win rate line of one side
+fn format_side_statistics(stats: &SideStatistics) -> String {
+    match stats.win_rate() {
+        Some(win_rate) => format!(
+            "Won {}/{} ({:.0}%) | Avg PnL: {:+.2}% | Stops: {} | Targets: {}",
+            stats.wins,
+            stats.trades,
+            win_rate * 100.0,
+            stats.avg_pnl_pct,
+            stats.stop_losses,
+            stats.take_profits
+        ),
+        None => "no simulated trades".to_string(),
+    }
+}
+
+/// Statistics calculated from historical outcomes at one horizon
 struct OutcomeStatistics {
-    avg_outcome_4h: f64,
-    median_outcome_4h: f64,
-    p10_outcome_4h: f64,
-    p90_outcome_4h: f64,
+    avg_outcome: f64,
+    median_outcome: f64,
+    p10_outcome: f64,
+    p90_outcome: f64,
     positive_count: usize,
     negative_count: usize,
     total_count: usize,
@@ -217,20 +377,18 @@ struct OutcomeStatistics {
 }
 
 impl OutcomeStatistics {
-    fn calculate(matches: &[HistoricalMatch]) -> Self {
-        let outcomes_4h: Vec<f64> = matches
-            .iter()
-            .filter_map(|m| m.outcome_4h)
-            .collect();
+    fn calculate(matches: &[HistoricalMatch], horizon: &str) -> Self {
+        let outcomes: Vec<_> = matches.iter().filter_map(|m| m.outcome(horizon)).collect();
+        let returns: Vec<f64> = outcomes.iter().filter_map(|o| o.return_pct).collect();
 
-        let total_count = outcomes_4h.len();
+        let total_count = returns.len();
 
-        if outcomes_4h.is_empty() {
+        if returns.is_empty() {
             return Self::default();
         }
 
         // Sort for percentile calculations
-        let mut sorted = outcomes_4h.clone();
+        let mut sorted = returns.clone();
         sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
 
         // Percentile function
@@ -245,20 +403,20 @@ impl OutcomeStatistics {
         let p10 = percentile(0.10);
         let p90 = percentile(0.90);
 
-        let positive_count = outcomes_4h.iter().filter(|&&x| x > 0.0).count();
-        let negative_count = outcomes_4h.iter().filter(|&&x| x < 0.0).count();
+        let positive_count = returns.iter().filter(|&&x| x > 0.0).count();
+        let negative_count = returns.iter().filter(|&&x| x < 0.0).count();
 
         let positive_pct = (positive_count as f64 / total_count as f64) * 100.0;
         let negative_pct = (negative_count as f64 / total_count as f64) * 100.0;
 
-        let stop_loss_hits = matches
+        let stop_loss_hits = outcomes
             .iter()
-            .filter(|m| m.hit_stop_loss == Some(true))
+            .filter(|o| o.hit_stop_loss == Some(true))
             .count();
 
-        let take_profit_hits = matches
+        let take_profit_hits = outcomes
             .iter()
-            .filter(|m| m.hit_take_profit == Some(true))
+            .filter(|o| o.hit_take_profit == Some(true))
             .count();
 
         let min_similarity = matches
@@ -274,10 +432,10 @@ impl OutcomeStatistics {
             .unwrap_or(0.0);
 
         Self {
-            avg_outcome_4h: avg,
-            median_outcome_4h: median,
-            p10_outcome_4h: p10,
-            p90_outcome_4h: p90,
+            avg_outcome: avg,
+            median_outcome: median,
+            p10_outcome: p10,
+            p90_outcome: p90,
             positive_count,
             negative_count,
             total_count,
@@ -294,10 +452,10 @@ impl OutcomeStatistics {
 impl Default for OutcomeStatistics {
     fn default() -> Self {
         Self {
-            avg_outcome_4h: 0.0,
-            median_outcome_4h: 0.0,
-            p10_outcome_4h: 0.0,
-            p90_outcome_4h: 0.0,
+            avg_outcome: 0.0,
+            median_outcome: 0.0,
+            p10_outcome: 0.0,
+            p90_outcome: 0.0,
             positive_count: 0,
             negative_count: 0,
             total_count: 0,
@@ -326,6 +484,43 @@ mod tests {
         assert!(prompt.contains("DECISION REQUIRED"));
     }
 
+    #[test]
+    fn test_prompt_includes_timeframe_contexts() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        let prompt = LlmPromptFormatter::format_baseline("BTCUSDT", &snapshot);
+        assert!(!prompt.contains("Prior H/L"));
+
+        snapshot.context_1d = Some(trading_core::TimeframeContext {
+            ema_20: 51000.0,
+            ema_50: 52000.0,
+            macd: -150.0,
+            rsi_14: 38.0,
+            atr_14: 1200.0,
+            prev_high: 51500.0,
+            prev_low: 49800.0,
+            prev_close: 50200.0,
+        });
+        let prompt = LlmPromptFormatter::format_with_historical_patterns("BTCUSDT", &snapshot, vec![]);
+        assert!(prompt.contains(
+            "  1d: downtrend, below EMA20 and EMA50 (-1.96% from EMA20) | RSI(14): 38.0 | Prior H/L: $51500.00 / $49800.00"
+        ), "{}", prompt);
+        assert!(!prompt.contains("  1h:"));
+    }
+
+    #[test]
+    fn test_prompt_includes_features() {
+        let mut snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50600.0);
+        snapshot.ema_20_4h = 50000.0;
+        snapshot.atr_14_4h = 400.0;
+        snapshot.mid_prices = (0..10).map(|i| 50000.0 + i as f64 * 60.0).collect();
+        snapshot.rsi_7_values = (0..10).map(|i| 75.0 - i as f64 * 2.0).collect();
+
+        let prompt = LlmPromptFormatter::format_baseline("BTCUSDT", &snapshot);
+        assert!(prompt.contains("  Features: +1.5 ATR from 4h EMA20 | Price slope +0.119%/3m"), "{}", prompt);
+        assert!(prompt.contains("RSI divergence: bearish"), "{}", prompt);
+        assert!(!prompt.contains("Volume") && !prompt.contains("MACD divergence"), "{}", prompt);
+    }
+
     #[test]
     fn test_rag_prompt_with_matches() {
         let snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
@@ -339,8 +534,8 @@ mod tests {
                 rsi_14: 72.0,
                 macd: 50.0,
                 ema_ratio: 1.01,
-                oi_delta_pct: 5.0,
-                funding_rate: 0.0001,
+                oi_delta_pct: Some(5.0),
+                funding_rate: Some(0.0001),
                 outcome_1h: Some(2.0),
                 outcome_4h: Some(-1.5),
                 outcome_24h: Some(3.0),
@@ -348,6 +543,9 @@ mod tests {
                 max_drawdown_1h: Some(-0.5),
                 hit_stop_loss: Some(false),
                 hit_take_profit: Some(true),
+                outcomes: Default::default(),
+                long_trade: None,
+                short_trade: None,
             },
             HistoricalMatch {
                 similarity: 0.80,
@@ -357,8 +555,8 @@ mod tests {
                 rsi_14: 74.0,
                 macd: 55.0,
                 ema_ratio: 1.02,
-                oi_delta_pct: 6.0,
-                funding_rate: 0.0002,
+                oi_delta_pct: Some(6.0),
+                funding_rate: Some(0.0002),
                 outcome_1h: Some(1.5),
                 outcome_4h: Some(2.0),
                 outcome_24h: Some(4.0),
@@ -366,6 +564,9 @@ mod tests {
                 max_drawdown_1h: Some(-0.2),
                 hit_stop_loss: Some(false),
                 hit_take_profit: Some(true),
+                outcomes: Default::default(),
+                long_trade: None,
+                short_trade: None,
             },
         ];
 
@@ -383,6 +584,73 @@ mod tests {
         assert!(prompt.contains("Median:"));
     }
 
+    #[test]
+    fn test_rag_prompt_for_custom_horizon() {
+        use trading_core::HorizonOutcome;
+
+        let snapshot = MarketStateSnapshot::new("BTCUSDT".to_string(), 1000000, 50000.0);
+        let with_8h = |similarity: f32, return_pct: f64, hit_stop_loss: bool| HistoricalMatch {
+            similarity,
+            timestamp: 1000000,
+            date: "2025-01-01T00:00:00Z".to_string(),
+            rsi_7: 70.0,
+            rsi_14: 65.0,
+            macd: 10.0,
+            ema_ratio: 1.0,
+            oi_delta_pct: None,
+            funding_rate: None,
+            outcome_1h: Some(0.5),
+            outcome_4h: Some(1.0),
+            outcome_24h: None,
+            max_runup_1h: None,
+            max_drawdown_1h: None,
+            hit_stop_loss: None,
+            hit_take_profit: None,
+            outcomes: [(
+                "8h".to_string(),
+                HorizonOutcome {
+                    return_pct: Some(return_pct),
+                    max_runup_pct: Some(return_pct.max(0.0)),
+                    max_drawdown_pct: Some(return_pct.min(0.0)),
+                    hit_stop_loss: Some(hit_stop_loss),
+                    hit_take_profit: Some(false),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            long_trade: Some(TradeOutcome {
+                exit_reason: if hit_stop_loss { ExitReason::StopLoss } else { ExitReason::TakeProfit },
+                exit_after_ms: 95 * 60_000,
+                pnl_pct: if hit_stop_loss { -2.0 } else { 3.0 },
+                mae_pct: -0.5,
+                mfe_pct: 3.0,
+            }),
+            short_trade: None,
+        };
+        let matches = vec![with_8h(0.9, 4.0, false), with_8h(0.8, -3.0, true)];
+
+        let stats = OutcomeStatistics::calculate(&matches, "8h");
+        assert_eq!(stats.total_count, 2);
+        assert!((stats.avg_outcome - 0.5).abs() < 1e-9);
+        assert_eq!(stats.stop_loss_hits, 1);
+
+        let prompt = LlmPromptFormatter::format_with_historical_patterns_for_horizon(
+            "BTCUSDT",
+            &snapshot,
+            matches,
+            "8h",
+        );
+        assert!(prompt.contains("→ 8h Result: +4.00%"));
+        assert!(prompt.contains("→ 8h Result: -3.00% (peak: +0.0%, trough: -3.0%) ❌ HIT STOP"));
+        assert!(prompt.contains("OUTCOME SUMMARY (8h horizon)"));
+        assert!(!prompt.contains("4h Result"));
+
+        assert!(prompt.contains("→ Long: +3.00% (target after 95m) | Short: n/a"));
+        assert!(prompt.contains("OI=n/a, Fund=n/a"));
+        assert!(prompt.contains("LONG:  Won 1/2 (50%) | Avg PnL: +0.50% | Stops: 1 | Targets: 1"));
+        assert!(prompt.contains("SHORT: no simulated trades"));
+    }
+
     #[test]
     fn test_outcome_statistics() {
         let matches = vec![
@@ -394,8 +662,8 @@ mod tests {
                 rsi_14: 72.0,
                 macd: 50.0,
                 ema_ratio: 1.01,
-                oi_delta_pct: 5.0,
-                funding_rate: 0.0001,
+                oi_delta_pct: Some(5.0),
+                funding_rate: Some(0.0001),
                 outcome_1h: Some(2.0),
                 outcome_4h: Some(-2.0),
                 outcome_24h: Some(3.0),
@@ -403,6 +671,9 @@ mod tests {
                 max_drawdown_1h: Some(-0.5),
                 hit_stop_loss: Some(true),
                 hit_take_profit: Some(false),
+                outcomes: Default::default(),
+                long_trade: None,
+                short_trade: None,
             },
             HistoricalMatch {
                 similarity: 0.90,
@@ -412,8 +683,8 @@ mod tests {
                 rsi_14: 74.0,
                 macd: 55.0,
                 ema_ratio: 1.02,
-                oi_delta_pct: 6.0,
-                funding_rate: 0.0002,
+                oi_delta_pct: Some(6.0),
+                funding_rate: Some(0.0002),
                 outcome_1h: Some(1.5),
                 outcome_4h: Some(3.0),
                 outcome_24h: Some(4.0),
@@ -421,6 +692,9 @@ mod tests {
                 max_drawdown_1h: Some(-0.2),
                 hit_stop_loss: Some(false),
                 hit_take_profit: Some(true),
+                outcomes: Default::default(),
+                long_trade: None,
+                short_trade: None,
             },
             HistoricalMatch {
                 similarity: 0.75,
@@ -430,8 +704,8 @@ mod tests {
                 rsi_14: 73.0,
                 macd: 52.0,
                 ema_ratio: 1.015,
-                oi_delta_pct: 5.5,
-                funding_rate: 0.00015,
+                oi_delta_pct: Some(5.5),
+                funding_rate: Some(0.00015),
                 outcome_1h: Some(1.0),
                 outcome_4h: Some(1.0),
                 outcome_24h: Some(2.0),
@@ -439,15 +713,18 @@ mod tests {
                 max_drawdown_1h: Some(-0.3),
                 hit_stop_loss: Some(false),
                 hit_take_profit: Some(false),
+                outcomes: Default::default(),
+                long_trade: None,
+                short_trade: None,
             },
         ];
 
-        let stats = OutcomeStatistics::calculate(&matches);
+        let stats = OutcomeStatistics::calculate(&matches, "4h");
 
         assert_eq!(stats.total_count, 3);
         assert_eq!(stats.positive_count, 2);
         assert_eq!(stats.negative_count, 1);
-        assert!((stats.avg_outcome_4h - 0.666).abs() < 0.01);
+        assert!((stats.avg_outcome - 0.666).abs() < 0.01);
         assert_eq!(stats.stop_loss_hits, 1);
         assert_eq!(stats.take_profit_hits, 1);
         assert_eq!(stats.min_similarity, 0.75);
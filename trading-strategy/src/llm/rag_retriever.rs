@@ -1,13 +1,60 @@
 use anyhow::{anyhow, Result};
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
 use qdrant_client::qdrant::{Condition, Filter, Range};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use trading_core::MarketStateSnapshot;
-use trading_data_services::{SnapshotFormatter, VectorStore};
+use trading_core::{
+    ExitReason, HorizonOutcome, MarketRegime, MarketStateSnapshot, PositioningRegime, TradeOutcome,
+    TradeSide, TrendRegime, VolatilityRegime,
+};
+use trading_data_services::rag::embedder::embedder_from_metadata;
+use trading_data_services::rag::sparse_vector::snapshot_sparse_vector;
+use trading_data_services::{SnapshotEmbedder, TextSnapshotEmbedder, VectorStore};
 
 use crate::llm::metrics::{MetricsTimer, RagMetrics};
 
+/// Exact-match regime filters for a pattern search (unset regimes match anything)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct RegimeFilter {
+    #[serde(default)]
+    pub trend: Option<TrendRegime>,
+    #[serde(default)]
+    pub volatility: Option<VolatilityRegime>,
+    #[serde(default)]
+    pub positioning: Option<PositioningRegime>,
+}
+
+impl RegimeFilter {
+    /// Filter on every known regime of a snapshot
+    pub fn matching(regime: &MarketRegime) -> Self {
+        Self {
+            trend: Some(regime.trend),
+            volatility: regime.volatility,
+            positioning: regime.positioning,
+        }
+    }
+
+    /// Whether no regime is filtered on
+    pub fn is_empty(&self) -> bool {
+        self.trend.is_none() && self.volatility.is_none() && self.positioning.is_none()
+    }
+
+    /// Payload keyword fields and labels to match
+    pub fn keywords(&self) -> Vec<(&'static str, &'static str)> {
+        let mut keywords = Vec::new();
+        if let Some(trend) = self.trend {
+            keywords.push(("trend_regime", trend.label()));
+        }
+        if let Some(volatility) = self.volatility {
+            keywords.push(("volatility_regime", volatility.label()));
+        }
+        if let Some(positioning) = self.positioning {
+            keywords.push(("positioning_regime", positioning.label()));
+        }
+        keywords
+    }
+}
+
 /// A historical pattern match with its market state and outcomes
 #[derive(Debug, Clone)]
 pub struct HistoricalMatch {
@@ -20,8 +67,8 @@ pub struct HistoricalMatch {
     pub rsi_14: f64,
     pub macd: f64,
     pub ema_ratio: f64,
-    pub oi_delta_pct: f64,
-    pub funding_rate: f64,
+    pub oi_delta_pct: Option<f64>, // None when open interest was unavailable
+    pub funding_rate: Option<f64>, // None when funding was unavailable
 
     // What happened next (THE VALUE)
     pub outcome_1h: Option<f64>,
@@ -31,33 +78,171 @@ pub struct HistoricalMatch {
     pub max_drawdown_1h: Option<f64>,
     pub hit_stop_loss: Option<bool>,
     pub hit_take_profit: Option<bool>,
+
+    // Per-horizon outcomes from the ingestion outcome spec (empty for older points)
+    pub outcomes: BTreeMap<String, HorizonOutcome>,
+
+    // Simulated trades from that point (None while open or for older points)
+    pub long_trade: Option<TradeOutcome>,
+    pub short_trade: Option<TradeOutcome>,
 }
 
+impl HistoricalMatch {
+    /// Outcome for a horizon label such as "4h" or "8h"
+    ///
+    /// Falls back to the legacy 1h/4h/24h fields for points ingested before
+    /// per-horizon outcomes were stored. Those points only recorded the first
+    /// hour's path, so runup/drawdown and stop/target hits come from it.
+    pub fn outcome(&self, horizon: &str) -> Option<HorizonOutcome> {
+        if let Some(outcome) = self.outcomes.get(horizon) {
+            return Some(*outcome);
+        }
+
+        let return_pct = match horizon {
+            "1h" => self.outcome_1h,
+            "4h" => self.outcome_4h,
+            "24h" => self.outcome_24h,
+            _ => return None,
+        };
+        Some(HorizonOutcome {
+            return_pct,
+            max_runup_pct: self.max_runup_1h,
+            max_drawdown_pct: self.max_drawdown_1h,
+            hit_stop_loss: self.hit_stop_loss,
+            hit_take_profit: self.hit_take_profit,
+        })
+    }
+
+    /// Simulated trade for one side
+    pub fn trade(&self, side: TradeSide) -> Option<&TradeOutcome> {
+        match side {
+            TradeSide::Long => self.long_trade.as_ref(),
+            TradeSide::Short => self.short_trade.as_ref(),
+        }
+    }
+
+    /// Price % change at a horizon, if known
+    pub fn return_pct(&self, horizon: &str) -> Option<f64> {
+        self.outcome(horizon).and_then(|o| o.return_pct)
+    }
+}
+
+/// Simulated trade results for one side across historical matches
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SideStatistics {
+    pub trades: usize,
+    pub wins: usize,
+    pub stop_losses: usize,
+    pub take_profits: usize,
+    pub avg_pnl_pct: f64,
+}
+
+impl SideStatistics {
+    /// Summarise the simulated trades of one side (matches without one are skipped)
+    pub fn calculate(matches: &[HistoricalMatch], side: TradeSide) -> Self {
+        let trades: Vec<&TradeOutcome> = matches.iter().filter_map(|m| m.trade(side)).collect();
+        if trades.is_empty() {
+            return Self::default();
+        }
+
+        let count_exits = |reason: ExitReason| trades.iter().filter(|t| t.exit_reason == reason).count();
+        Self {
+            trades: trades.len(),
+            wins: trades.iter().filter(|t| t.is_win()).count(),
+            stop_losses: count_exits(ExitReason::StopLoss),
+            take_profits: count_exits(ExitReason::TakeProfit),
+            avg_pnl_pct: trades.iter().map(|t| t.pnl_pct).sum::<f64>() / trades.len() as f64,
+        }
+    }
+
+    /// Share of trades closed in profit (0.0-1.0), `None` without trades
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.wins as f64 / self.trades as f64)
+    }
+}
+
+/// Horizon summarised when none is configured
+pub const DEFAULT_OUTCOME_HORIZON: &str = "4h";
+
 /// RAG retriever for finding similar historical patterns
 pub struct RagRetriever {
-    embedding_model: TextEmbedding,
+    embedder: Box<dyn SnapshotEmbedder>,
     vector_store: Arc<VectorStore>,
     min_matches: usize,
+    outcome_horizon: String,
+    hybrid_search: bool,
 }
 
 impl RagRetriever {
     /// Create a new RAG retriever
+    ///
+    /// Queries are embedded with the embedder recorded in the collection
+    /// metadata, or the BGE text embedder when the collection has none.
+    /// Hybrid search is enabled when the collection has sparse keyword vectors.
     pub async fn new(vector_store: Arc<VectorStore>, min_matches: usize) -> Result<Self> {
-        tracing::info!("Initializing RAG retriever with BGE-small-en-v1.5 model...");
-
-        let embedding_model = TextEmbedding::try_new(InitOptions::new(
-            EmbeddingModel::BGESmallENV15,
-        ))?;
+        let embedder = match vector_store.collection_metadata().await {
+            Ok(metadata) => embedder_from_metadata(&metadata)?,
+            Err(e) => {
+                tracing::warn!("Failed to read collection metadata, using the text embedder: {}", e);
+                Box::new(TextSnapshotEmbedder::new())
+            }
+        };
+        let hybrid_search = vector_store.has_sparse_vectors().await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read collection vectors, using dense search: {}", e);
+            false
+        });
+        Ok(Self::with_embedder(vector_store, min_matches, embedder)?.with_hybrid_search(hybrid_search))
+    }
 
+    /// Create a new RAG retriever with an explicit embedder
+    ///
+    /// The embedder must match the one the collection was ingested with.
+    pub fn with_embedder(
+        vector_store: Arc<VectorStore>,
+        min_matches: usize,
+        embedder: Box<dyn SnapshotEmbedder>,
+    ) -> Result<Self> {
+        tracing::info!("Initializing RAG retriever with {} embedder...", embedder.model_name());
+        embedder.load()?;
         tracing::info!("RAG retriever initialized successfully");
 
         Ok(Self {
-            embedding_model,
+            embedder,
             vector_store,
             min_matches,
+            outcome_horizon: DEFAULT_OUTCOME_HORIZON.to_string(),
+            hybrid_search: false,
         })
     }
 
+    /// Fuse the dense search with the sparse keyword search (needs a collection
+    /// created with sparse vectors)
+    pub fn with_hybrid_search(mut self, enabled: bool) -> Self {
+        self.hybrid_search = enabled;
+        self
+    }
+
+    /// Whether searches fuse dense and sparse keyword rankings
+    pub fn hybrid_search(&self) -> bool {
+        self.hybrid_search
+    }
+
+    /// Set the outcome horizon summarised in the retrieval metrics (e.g. "8h")
+    pub fn with_outcome_horizon(mut self, horizon: impl Into<String>) -> Self {
+        self.outcome_horizon = horizon.into();
+        self
+    }
+
+    /// The outcome horizon summarised in the retrieval metrics
+    pub fn outcome_horizon(&self) -> &str {
+        &self.outcome_horizon
+    }
+
+    /// Model name of the embedder queries are embedded with
+    pub fn embedding_model(&self) -> &str {
+        self.embedder.model_name()
+    }
+
     /// Find similar historical patterns for the current market state with metrics
     ///
     /// # Arguments
@@ -72,6 +257,27 @@ impl RagRetriever {
         current_snapshot: &MarketStateSnapshot,
         lookback_days: u32,
         top_k: usize,
+    ) -> Result<(Vec<HistoricalMatch>, RagMetrics)> {
+        self.find_similar_patterns_filtered(current_snapshot, lookback_days, top_k, &RegimeFilter::default())
+            .await
+    }
+
+    /// Find similar historical patterns restricted to exact regimes, with metrics
+    ///
+    /// # Arguments
+    /// * `current_snapshot` - Current market state
+    /// * `lookback_days` - How many days back to search
+    /// * `top_k` - Maximum number of similar patterns to return
+    /// * `regime_filter` - Regime keywords the matches must have
+    ///
+    /// # Returns
+    /// Tuple of (matches, metrics)
+    pub async fn find_similar_patterns_filtered(
+        &self,
+        current_snapshot: &MarketStateSnapshot,
+        lookback_days: u32,
+        top_k: usize,
+        regime_filter: &RegimeFilter,
     ) -> Result<(Vec<HistoricalMatch>, RagMetrics)> {
         let mut metrics = RagMetrics::new();
 
@@ -86,16 +292,10 @@ impl RagRetriever {
         );
 
         // 1. Convert current state to embedding
-        let query_text = current_snapshot.to_embedding_text();
-        let query_embedding = self
-            .embedding_model
-            .embed(vec![query_text], None)?
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow!("Failed to generate embedding"))?;
+        let query_embedding = self.embedder.embed_one(current_snapshot)?;
 
         metrics.set_embedding_latency(embedding_timer.stop());
-        tracing::debug!("Generated query embedding (384 dimensions)");
+        tracing::debug!("Generated query embedding ({} dimensions)", query_embedding.len());
 
         // Time retrieval
         let retrieval_timer = MetricsTimer::start();
@@ -107,23 +307,7 @@ impl RagRetriever {
 
         let mut conditions = vec![
             // Must match symbol
-            Condition {
-                condition_one_of: Some(
-                    qdrant_client::qdrant::condition::ConditionOneOf::Field(
-                        qdrant_client::qdrant::FieldCondition {
-                            key: "symbol".to_string(),
-                            r#match: Some(qdrant_client::qdrant::Match {
-                                match_value: Some(
-                                    qdrant_client::qdrant::r#match::MatchValue::Keyword(
-                                        current_snapshot.symbol.clone(),
-                                    ),
-                                ),
-                            }),
-                            ..Default::default()
-                        },
-                    ),
-                ),
-            },
+            keyword_condition("symbol", &current_snapshot.symbol),
             // Must be within lookback window
             Condition {
                 condition_one_of: Some(
@@ -141,9 +325,9 @@ impl RagRetriever {
             },
         ];
 
-        // Optional: Filter by OI delta regime (if significant)
+        // Optional: Filter by OI delta regime (if significant and OI is available)
         let oi_delta = current_snapshot.oi_delta_pct();
-        if oi_delta.abs() > 5.0 {
+        if current_snapshot.has_open_interest && oi_delta.abs() > 5.0 {
             let oi_min = oi_delta - 10.0;
             let oi_max = oi_delta + 10.0;
             conditions.push(Condition {
@@ -164,8 +348,8 @@ impl RagRetriever {
             tracing::debug!("Applied OI delta filter: {}% ±10%", oi_delta);
         }
 
-        // Optional: Filter by funding rate sign
-        if current_snapshot.funding_rate.abs() > 0.0001 {
+        // Optional: Filter by funding rate sign (if funding is available)
+        if current_snapshot.has_funding_rate && current_snapshot.funding_rate.abs() > 0.0001 {
             let funding_condition = if current_snapshot.funding_rate > 0.0 {
                 Range {
                     gte: Some(0.0),
@@ -199,21 +383,71 @@ impl RagRetriever {
             );
         }
 
+        // Optional: Filter by reference asset 4h direction (if attached and significant)
+        if let Some((reference, change_4h)) = current_snapshot
+            .reference
+            .as_ref()
+            .and_then(|r| r.price_change_4h.map(|change| (r, change)))
+            .filter(|(_, change)| change.abs() > 1.0)
+        {
+            let reference_condition = if change_4h > 0.0 {
+                Range {
+                    gt: Some(0.0),
+                    ..Default::default()
+                }
+            } else {
+                Range {
+                    lt: Some(0.0),
+                    ..Default::default()
+                }
+            };
+
+            conditions.push(Condition {
+                condition_one_of: Some(
+                    qdrant_client::qdrant::condition::ConditionOneOf::Field(
+                        qdrant_client::qdrant::FieldCondition {
+                            key: "ref_price_change_4h".to_string(),
+                            range: Some(reference_condition),
+                            ..Default::default()
+                        },
+                    ),
+                ),
+            });
+            tracing::debug!(
+                "Applied reference filter: {} 4h {}",
+                reference.symbol,
+                if change_4h > 0.0 { "up" } else { "down" }
+            );
+        }
+
+        // Optional: Exact regime matches
+        for (field, label) in regime_filter.keywords() {
+            conditions.push(keyword_condition(field, label));
+            tracing::debug!("Applied regime filter: {} = {}", field, label);
+        }
+
         let filter = Filter {
             must: conditions,
             ..Default::default()
         };
 
-        // 3. Search Qdrant
-        let scored_points = self
-            .vector_store
-            .search(
-                query_embedding,
-                top_k as u64,
-                Some(filter),
-                Some(0.7), // Only return good matches (>70% similarity)
-            )
-            .await?;
+        // 3. Search Qdrant (only return good matches: >70% similarity; hybrid search
+        //    also keeps exact keyword matches below it)
+        let scored_points = if self.hybrid_search {
+            self.vector_store
+                .hybrid_search(
+                    query_embedding,
+                    snapshot_sparse_vector(current_snapshot),
+                    top_k as u64,
+                    Some(filter),
+                    Some(0.7),
+                )
+                .await?
+        } else {
+            self.vector_store
+                .search(query_embedding, top_k as u64, Some(filter), Some(0.7))
+                .await?
+        };
 
         metrics.set_retrieval_latency(retrieval_timer.stop());
 
@@ -225,7 +459,7 @@ impl RagRetriever {
         // 4. Parse results into HistoricalMatch structs
         let mut matches = Vec::new();
         let mut similarity_scores = Vec::new();
-        let mut outcome_4h_values = Vec::new();
+        let mut outcome_values = Vec::new();
 
         for scored_point in scored_points {
             let payload = scored_point.payload;
@@ -240,8 +474,9 @@ impl RagRetriever {
                 rsi_14: Self::get_payload_f64(&payload, "rsi_14")?,
                 macd: Self::get_payload_f64(&payload, "macd")?,
                 ema_ratio: Self::get_payload_f64(&payload, "ema_ratio")?,
-                oi_delta_pct: Self::get_payload_f64(&payload, "oi_delta_pct")?,
-                funding_rate: Self::get_payload_f64(&payload, "funding_rate")?,
+                // Null when the derivatives data was unavailable at ingestion
+                oi_delta_pct: Self::get_payload_f64_opt(&payload, "oi_delta_pct"),
+                funding_rate: Self::get_payload_f64_opt(&payload, "funding_rate"),
                 outcome_1h: Self::get_payload_f64_opt(&payload, "outcome_1h"),
                 outcome_4h: Self::get_payload_f64_opt(&payload, "outcome_4h"),
                 outcome_24h: Self::get_payload_f64_opt(&payload, "outcome_24h"),
@@ -249,11 +484,14 @@ impl RagRetriever {
                 max_drawdown_1h: Self::get_payload_f64_opt(&payload, "max_drawdown_1h"),
                 hit_stop_loss: Self::get_payload_bool_opt(&payload, "hit_stop_loss"),
                 hit_take_profit: Self::get_payload_bool_opt(&payload, "hit_take_profit"),
+                outcomes: Self::get_payload_outcomes(&payload, "outcomes"),
+                long_trade: Self::get_payload_trade(&payload, "long_trade"),
+                short_trade: Self::get_payload_trade(&payload, "short_trade"),
             };
 
-            // Collect outcome_4h for distribution analysis
-            if let Some(outcome) = historical_match.outcome_4h {
-                outcome_4h_values.push(outcome);
+            // Collect the configured horizon's outcome for distribution analysis
+            if let Some(outcome) = historical_match.return_pct(&self.outcome_horizon) {
+                outcome_values.push(outcome);
             }
 
             matches.push(historical_match);
@@ -261,7 +499,11 @@ impl RagRetriever {
 
         // Update metrics with similarity and outcome data
         metrics.set_similarity_scores(similarity_scores);
-        metrics.set_outcomes(outcome_4h_values);
+        metrics.set_outcomes(&self.outcome_horizon, outcome_values);
+        metrics.set_side_win_rates(
+            SideStatistics::calculate(&matches, TradeSide::Long).win_rate(),
+            SideStatistics::calculate(&matches, TradeSide::Short).win_rate(),
+        );
 
         // 5. Enforce minimum match count (fallback to baseline if insufficient)
         if matches.len() < self.min_matches {
@@ -347,6 +589,44 @@ impl RagRetriever {
         )
     }
 
+    /// Per-horizon outcomes stored as a struct keyed by horizon label
+    fn get_payload_outcomes(
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+        key: &str,
+    ) -> BTreeMap<String, HorizonOutcome> {
+        let struct_fields = |value: &qdrant_client::qdrant::Value| match value.kind.as_ref() {
+            Some(qdrant_client::qdrant::value::Kind::StructValue(s)) => Some(s.fields.clone()),
+            _ => None,
+        };
+
+        payload
+            .get(key)
+            .and_then(struct_fields)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(label, value)| {
+                let fields = struct_fields(value)?;
+                let outcome = HorizonOutcome {
+                    return_pct: Self::get_payload_f64_opt(&fields, "return_pct"),
+                    max_runup_pct: Self::get_payload_f64_opt(&fields, "max_runup_pct"),
+                    max_drawdown_pct: Self::get_payload_f64_opt(&fields, "max_drawdown_pct"),
+                    hit_stop_loss: Self::get_payload_bool_opt(&fields, "hit_stop_loss"),
+                    hit_take_profit: Self::get_payload_bool_opt(&fields, "hit_take_profit"),
+                };
+                Some((label.clone(), outcome))
+            })
+            .collect()
+    }
+
+    /// Simulated trade stored as a struct (null while the trade was open)
+    fn get_payload_trade(
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+        key: &str,
+    ) -> Option<TradeOutcome> {
+        let value = payload.get(key)?.clone().into_json();
+        serde_json::from_value(value).ok()
+    }
+
     fn get_payload_string(
         payload: &HashMap<String, qdrant_client::qdrant::Value>,
         key: &str,
@@ -377,6 +657,25 @@ impl RagRetriever {
     }
 }
 
+/// Exact keyword match on a payload field
+fn keyword_condition(key: &str, value: &str) -> Condition {
+    Condition {
+        condition_one_of: Some(
+            qdrant_client::qdrant::condition::ConditionOneOf::Field(
+                qdrant_client::qdrant::FieldCondition {
+                    key: key.to_string(),
+                    r#match: Some(qdrant_client::qdrant::Match {
+                        match_value: Some(
+                            qdrant_client::qdrant::r#match::MatchValue::Keyword(value.to_string()),
+                        ),
+                    }),
+                    ..Default::default()
+                },
+            ),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -391,8 +690,8 @@ mod tests {
             rsi_14: 72.0,
             macd: 50.0,
             ema_ratio: 1.01,
-            oi_delta_pct: 5.0,
-            funding_rate: 0.0001,
+            oi_delta_pct: Some(5.0),
+            funding_rate: Some(0.0001),
             outcome_1h: Some(2.0),
             outcome_4h: Some(-1.5),
             outcome_24h: Some(3.0),
@@ -400,10 +699,121 @@ mod tests {
             max_drawdown_1h: Some(-0.5),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(true),
+            outcomes: BTreeMap::new(),
+            long_trade: None,
+            short_trade: None,
         };
 
         assert_eq!(match_result.similarity, 0.85);
         assert_eq!(match_result.outcome_4h, Some(-1.5));
+
+        // Without stored horizons the legacy fields are used
+        assert_eq!(match_result.return_pct("4h"), Some(-1.5));
+        assert_eq!(match_result.outcome("1h").unwrap().hit_take_profit, Some(true));
+        assert!(match_result.outcome("8h").is_none());
+    }
+
+    #[test]
+    fn test_payload_outcomes_parsed() {
+        let payload: qdrant_client::Payload = serde_json::json!({
+            "long_trade": {"exit_reason": "take_profit", "exit_after_ms": 5_400_000,
+                           "pnl_pct": 3.0, "mae_pct": -0.4, "mfe_pct": 3.0},
+            "short_trade": null,
+            "outcomes": {
+                "8h": {"return_pct": 2.5, "max_runup_pct": 3.0, "max_drawdown_pct": -0.5,
+                       "hit_stop_loss": false, "hit_take_profit": true},
+                "24h": {"return_pct": null, "max_runup_pct": null, "max_drawdown_pct": null,
+                        "hit_stop_loss": null, "hit_take_profit": null}
+            }
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+        .into();
+        let payload: HashMap<String, qdrant_client::qdrant::Value> = payload.into();
+
+        let outcomes = RagRetriever::get_payload_outcomes(&payload, "outcomes");
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes["8h"].return_pct, Some(2.5));
+        assert_eq!(outcomes["8h"].hit_take_profit, Some(true));
+        assert_eq!(outcomes["24h"], HorizonOutcome::default());
+
+        assert!(RagRetriever::get_payload_outcomes(&HashMap::new(), "outcomes").is_empty());
+
+        let long = RagRetriever::get_payload_trade(&payload, "long_trade").unwrap();
+        assert_eq!(long.exit_reason, ExitReason::TakeProfit);
+        assert_eq!(long.exit_after_ms, 5_400_000);
+        assert_eq!(RagRetriever::get_payload_trade(&payload, "short_trade"), None);
+        assert_eq!(RagRetriever::get_payload_trade(&payload, "missing"), None);
+    }
+
+    #[test]
+    fn test_regime_filter_keywords() {
+        assert!(RegimeFilter::default().is_empty());
+        assert!(RegimeFilter::default().keywords().is_empty());
+
+        let regime = MarketRegime {
+            trend: TrendRegime::Downtrend,
+            volatility: Some(VolatilityRegime::Elevated),
+            positioning: None,
+        };
+        let filter = RegimeFilter::matching(&regime);
+        assert_eq!(
+            filter.keywords(),
+            vec![("trend_regime", "downtrend"), ("volatility_regime", "elevated")]
+        );
+
+        // RPC requests name regimes by their payload labels
+        let filter: RegimeFilter = serde_json::from_str(r#"{"positioning": "crowded_long"}"#).unwrap();
+        assert_eq!(filter.keywords(), vec![("positioning_regime", "crowded_long")]);
+        assert!(serde_json::from_str::<RegimeFilter>(r#"{"trend": "moon"}"#).is_err());
+    }
+
+    #[test]
+    fn test_side_statistics() {
+        let trade = |exit_reason: ExitReason, pnl_pct: f64| TradeOutcome {
+            exit_reason,
+            exit_after_ms: 3_600_000,
+            pnl_pct,
+            mae_pct: pnl_pct.min(0.0),
+            mfe_pct: pnl_pct.max(0.0),
+        };
+        let mut matches = Vec::new();
+        for (long, short) in [
+            (Some(trade(ExitReason::TakeProfit, 3.0)), Some(trade(ExitReason::StopLoss, -2.0))),
+            (Some(trade(ExitReason::StopLoss, -2.0)), Some(trade(ExitReason::Horizon, 0.5))),
+            (Some(trade(ExitReason::Horizon, 1.0)), None),
+        ] {
+            matches.push(HistoricalMatch {
+                similarity: 0.9,
+                timestamp: 0,
+                date: String::new(),
+                rsi_7: 50.0,
+                rsi_14: 50.0,
+                macd: 0.0,
+                ema_ratio: 1.0,
+                oi_delta_pct: Some(0.0),
+                funding_rate: Some(0.0),
+                outcome_1h: None,
+                outcome_4h: None,
+                outcome_24h: None,
+                max_runup_1h: None,
+                max_drawdown_1h: None,
+                hit_stop_loss: None,
+                hit_take_profit: None,
+                outcomes: BTreeMap::new(),
+                long_trade: long,
+                short_trade: short,
+            });
+        }
+
+        let long = SideStatistics::calculate(&matches, TradeSide::Long);
+        assert_eq!((long.trades, long.wins, long.stop_losses, long.take_profits), (3, 2, 1, 1));
+        assert!((long.avg_pnl_pct - 2.0 / 3.0).abs() < 1e-9);
+
+        let short = SideStatistics::calculate(&matches, TradeSide::Short);
+        assert_eq!(short.win_rate(), Some(0.5));
+        assert_eq!(SideStatistics::calculate(&[], TradeSide::Short).win_rate(), None);
     }
 
     // Note: Integration tests with real Qdrant will be in a separate test module
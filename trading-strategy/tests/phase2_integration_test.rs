@@ -40,8 +40,8 @@ fn test_phase2_end_to_end_prompt_generation() {
             rsi_14: 73.1,
             macd: 128.0,
             ema_ratio: 1.008,
-            oi_delta_pct: 9.2,
-            funding_rate: 0.0006,
+            oi_delta_pct: Some(9.2),
+            funding_rate: Some(0.0006),
             outcome_1h: Some(1.8),
             outcome_4h: Some(3.5),
             outcome_24h: Some(5.2),
@@ -49,6 +49,9 @@ fn test_phase2_end_to_end_prompt_generation() {
             max_drawdown_1h: Some(-0.4),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(true),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
         // Match 2: Similar setup that failed
         HistoricalMatch {
@@ -59,8 +62,8 @@ fn test_phase2_end_to_end_prompt_generation() {
             rsi_14: 71.8,
             macd: 122.0,
             ema_ratio: 1.007,
-            oi_delta_pct: 7.8,
-            funding_rate: 0.0005,
+            oi_delta_pct: Some(7.8),
+            funding_rate: Some(0.0005),
             outcome_1h: Some(-2.1),
             outcome_4h: Some(-4.2),
             outcome_24h: Some(-3.8),
@@ -68,6 +71,9 @@ fn test_phase2_end_to_end_prompt_generation() {
             max_drawdown_1h: Some(-2.5),
             hit_stop_loss: Some(true),
             hit_take_profit: Some(false),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
         // Match 3: Consolidation then breakout
         HistoricalMatch {
@@ -78,8 +84,8 @@ fn test_phase2_end_to_end_prompt_generation() {
             rsi_14: 70.5,
             macd: 118.5,
             ema_ratio: 1.006,
-            oi_delta_pct: 8.9,
-            funding_rate: 0.0004,
+            oi_delta_pct: Some(8.9),
+            funding_rate: Some(0.0004),
             outcome_1h: Some(0.5),
             outcome_4h: Some(2.8),
             outcome_24h: Some(4.1),
@@ -87,6 +93,9 @@ fn test_phase2_end_to_end_prompt_generation() {
             max_drawdown_1h: Some(-0.3),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(true),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
         // Match 4: Quick reversal
         HistoricalMatch {
@@ -97,8 +106,8 @@ fn test_phase2_end_to_end_prompt_generation() {
             rsi_14: 74.2,
             macd: 130.2,
             ema_ratio: 1.009,
-            oi_delta_pct: 10.1,
-            funding_rate: 0.0007,
+            oi_delta_pct: Some(10.1),
+            funding_rate: Some(0.0007),
             outcome_1h: Some(1.2),
             outcome_4h: Some(-1.5),
             outcome_24h: Some(0.3),
@@ -106,6 +115,9 @@ fn test_phase2_end_to_end_prompt_generation() {
             max_drawdown_1h: Some(-0.6),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(false),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
         // Match 5: Strong continuation
         HistoricalMatch {
@@ -116,8 +128,8 @@ fn test_phase2_end_to_end_prompt_generation() {
             rsi_14: 69.9,
             macd: 115.0,
             ema_ratio: 1.005,
-            oi_delta_pct: 7.2,
-            funding_rate: 0.0003,
+            oi_delta_pct: Some(7.2),
+            funding_rate: Some(0.0003),
             outcome_1h: Some(2.5),
             outcome_4h: Some(4.8),
             outcome_24h: Some(6.5),
@@ -125,6 +137,9 @@ fn test_phase2_end_to_end_prompt_generation() {
             max_drawdown_1h: Some(-0.2),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(true),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
     ];
 
@@ -211,8 +226,8 @@ fn test_phase2_edge_cases() {
             rsi_14: 36.0,
             macd: -22.0,
             ema_ratio: 0.985,
-            oi_delta_pct: -11.0,
-            funding_rate: -0.0002,
+            oi_delta_pct: Some(-11.0),
+            funding_rate: Some(-0.0002),
             outcome_1h: Some(3.2), // Reversal bounce
             outcome_4h: Some(5.5),
             outcome_24h: Some(2.8),
@@ -220,6 +235,9 @@ fn test_phase2_edge_cases() {
             max_drawdown_1h: Some(-0.5),
             hit_stop_loss: Some(false),
             hit_take_profit: Some(true),
+            outcomes: Default::default(),
+            long_trade: None,
+            short_trade: None,
         },
     ];
 
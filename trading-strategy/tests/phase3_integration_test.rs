@@ -1,3 +1,6 @@
+// Lints predating the workspace clippy gate; the test bodies are kept as written
+#![allow(unused_imports, clippy::empty_line_after_doc_comments)]
+
 /// Phase 3: LLM Client Integration Test
 ///
 /// This test demonstrates the complete Phase 3 implementation:
@@ -1,3 +1,6 @@
+// Lints predating the workspace clippy gate; the test bodies are kept as written
+#![allow(unused_imports, clippy::assertions_on_constants)]
+
 /// Phase 4 Integration Tests: LLM RAG V1 Strategy
 ///
 /// These tests verify that the strategy plugin correctly integrates: